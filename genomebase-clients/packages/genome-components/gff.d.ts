@@ -0,0 +1,28 @@
+/**
+ * JSON representations of GFF3 annotations, as written by genomebase's `gff::json` module.
+ */
+
+/** Attribute values: a plain string, or an array for multiple values. */
+export type AttributeValue = string | string[];
+
+/** A GFF3 feature line. Attributes are keyed by GFF3 tag names, e.g. `ID` or `Parent`. */
+export interface GffRecord {
+  seqid: string;
+  source: string;
+  type: string;
+  /** 1-based, inclusive. */
+  start: number;
+  end: number;
+  score: number | null;
+  strand: "+" | "-" | null;
+  phase: "0" | "1" | "2" | null;
+  attributes: Record<string, AttributeValue>;
+}
+
+/**
+ * A feature with the features naming it as `Parent`. A feature with several parents appears
+ * under each of them. `children` is omitted when empty.
+ */
+export interface FeatureNode extends GffRecord {
+  children?: FeatureNode[];
+}
@@ -0,0 +1,410 @@
+use std::fmt;
+use std::io::Write;
+use std::path::Path;
+use std::process::ExitCode;
+
+use anyhow::Result;
+use fasta::index::SequenceMismatch;
+use fasta::indexed::IndexedFasta;
+use fasta::{reverse_complement, write_fasta, FastaRecord, SequenceSource, DEFAULT_LINE_WIDTH};
+use gff::bed::write_bed;
+use gff::directive::{GenomeBuild, GffVersion, SequenceRegion, Species};
+use gff::filter::Filter;
+use gff::gene_model::assemble_gene_models;
+use gff::gtf::write_gtf;
+use gff::json::{build_feature_tree, write_json_lines};
+use gff::lint::lint_records;
+use gff::reader::GffReader;
+use gff::stats::AnnotationStats;
+use gff::tbl::Severity;
+use gff::{sort_records, GffRecord, Strand};
+use serde::Serialize;
+
+use crate::io::{
+    create_output, open_fasta, open_input, sequence_regions, write_directives, GffInput,
+};
+use crate::Format;
+
+#[derive(Debug, Serialize)]
+struct ValidationIssue {
+    line: Option<usize>,
+    severity: Severity,
+    feature_id: Option<String>,
+    message: String,
+}
+
+impl fmt::Display for ValidationIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.severity)?;
+        if let Some(line) = self.line {
+            write!(f, " line {}", line)?;
+        }
+        if let Some(id) = &self.feature_id {
+            write!(f, " {}", id)?;
+        }
+        write!(f, ": {}", self.message)
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct ValidationReport {
+    valid: bool,
+    errors: usize,
+    warnings: usize,
+    issues: Vec<ValidationIssue>,
+}
+
+fn error_issue(line: Option<usize>, message: String) -> ValidationIssue {
+    ValidationIssue {
+        line,
+        severity: Severity::Error,
+        feature_id: None,
+        message,
+    }
+}
+
+/// Compares `##sequence-region` directives with the sequences of a FASTA file. Sequences
+/// missing from the annotation are warnings, and only when it declares any region.
+fn sequence_issues(regions: &[SequenceRegion], fasta: &Path) -> Result<Vec<ValidationIssue>> {
+    let index = IndexedFasta::open(fasta)?.index().clone();
+    let declared: Vec<(String, u64)> = regions
+        .iter()
+        .map(|r| (r.seqid.to_string(), r.end))
+        .collect();
+    Ok(index
+        .check_sequences(&declared)
+        .into_iter()
+        .filter_map(|mismatch| {
+            let severity = match mismatch {
+                SequenceMismatch::MissingFromAnnotation(_) if regions.is_empty() => return None,
+                SequenceMismatch::MissingFromAnnotation(_) => Severity::Warning,
+                _ => Severity::Error,
+            };
+            Some(ValidationIssue {
+                line: None,
+                severity,
+                feature_id: None,
+                message: mismatch.to_string(),
+            })
+        })
+        .collect())
+}
+
+fn validation_issues(path: Option<&Path>, fasta: Option<&Path>) -> Result<Vec<ValidationIssue>> {
+    let mut reader = GffReader::new(open_input(path)?);
+    let mut records = Vec::new();
+    let mut lines = Vec::new();
+    while let Some(record) = reader.next() {
+        match record {
+            Ok(record) => {
+                records.push(record);
+                lines.push(reader.line_number());
+            }
+            Err(e) => {
+                let line = reader.line_number();
+                let message = e.to_string();
+                let message = message.trim_start_matches(&format!("line {}: ", line));
+                return Ok(vec![error_issue(Some(line), message.to_string())]);
+            }
+        }
+    }
+
+    let regions = sequence_regions(reader.directives())?;
+    let mut issues: Vec<ValidationIssue> = lint_records(&records, &regions)
+        .into_iter()
+        .map(|issue| ValidationIssue {
+            line: Some(lines[issue.record]),
+            severity: issue.severity(),
+            feature_id: issue.feature_id,
+            message: issue.kind.to_string(),
+        })
+        .collect();
+    if let Err(e) = assemble_gene_models(records) {
+        issues.push(error_issue(None, e.to_string()));
+    }
+    if let Some(fasta) = fasta {
+        issues.extend(sequence_issues(&regions, fasta)?);
+    }
+    Ok(issues)
+}
+
+pub fn validate(
+    path: Option<&Path>,
+    fasta: Option<&Path>,
+    strict: bool,
+    json: bool,
+) -> Result<ExitCode> {
+    let issues = validation_issues(path, fasta)?;
+    let errors = issues
+        .iter()
+        .filter(|i| i.severity == Severity::Error)
+        .count();
+    let warnings = issues.len() - errors;
+    let valid = errors == 0 && !(strict && warnings > 0);
+
+    let mut output = create_output(None)?;
+    if json {
+        let report = ValidationReport {
+            valid,
+            errors,
+            warnings,
+            issues,
+        };
+        serde_json::to_writer_pretty(&mut output, &report)?;
+        writeln!(output)?;
+    } else {
+        for issue in &issues {
+            writeln!(output, "{}", issue)?;
+        }
+        writeln!(output, "{} errors, {} warnings", errors, warnings)?;
+    }
+    output.finish()?;
+
+    Ok(match valid {
+        true => ExitCode::SUCCESS,
+        false => ExitCode::FAILURE,
+    })
+}
+
+fn write_gff<W: Write>(writer: &mut W, directives: &[String], records: &[GffRecord]) -> Result<()> {
+    write_directives(writer, directives)?;
+    for record in records {
+        writeln!(writer, "{}", record)?;
+    }
+    Ok(())
+}
+
+pub fn convert(path: Option<&Path>, to: Format, output: Option<&Path>) -> Result<ExitCode> {
+    let input = GffInput::read(path)?;
+    let mut output = create_output(output)?;
+    match to {
+        Format::Gff3 => write_gff(&mut output, &input.directives, &input.records)?,
+        Format::Gtf => write_gtf(&mut output, &assemble_gene_models(input.records)?)?,
+        Format::Bed => write_bed(&mut output, &assemble_gene_models(input.records)?)?,
+        Format::Jsonl => write_json_lines(&mut output, &input.records)?,
+        Format::JsonTree => write_json_lines(&mut output, build_feature_tree(&input.records))?,
+    }
+    output.finish()?;
+    Ok(ExitCode::SUCCESS)
+}
+
+pub fn sort(path: Option<&Path>, output: Option<&Path>) -> Result<ExitCode> {
+    let mut input = GffInput::read(path)?;
+    sort_records(&mut input.records);
+    let mut output = create_output(output)?;
+    write_gff(&mut output, &input.directives, &input.records)?;
+    output.finish()?;
+    Ok(ExitCode::SUCCESS)
+}
+
+pub fn stats(path: Option<&Path>, json: bool) -> Result<ExitCode> {
+    let input = GffInput::read(path)?;
+    let stats = AnnotationStats::compute(&input.records, &input.sequence_regions()?)?;
+    let mut output = create_output(None)?;
+    match json {
+        true => {
+            stats.write_json(&mut output)?;
+            writeln!(output)?;
+        }
+        false => stats.write_tsv(&mut output)?,
+    }
+    output.finish()?;
+    Ok(ExitCode::SUCCESS)
+}
+
+/// Streams matching records, copying directives as they are read.
+pub fn filter(
+    expression: &str,
+    path: Option<&Path>,
+    output: Option<&Path>,
+    json: bool,
+) -> Result<ExitCode> {
+    let filter: Filter = expression.parse()?;
+    let mut reader = GffReader::new(open_input(path)?);
+    let mut output = create_output(output)?;
+    let mut written_directives = 0;
+
+    while let Some(record) = reader.next() {
+        let record = record?;
+        if !json {
+            write_directives(&mut output, &reader.directives()[written_directives..])?;
+            written_directives = reader.directives().len();
+        }
+        if filter.matches(&record) {
+            match json {
+                true => write_json_lines(&mut output, [&record])?,
+                false => writeln!(output, "{}", record)?,
+            }
+        }
+    }
+    // Directives after the last record, such as a closing `###`.
+    if !json {
+        write_directives(&mut output, &reader.directives()[written_directives..])?;
+    }
+    output.finish()?;
+    Ok(ExitCode::SUCCESS)
+}
+
+#[derive(Debug, Serialize)]
+struct FeatureSequence {
+    id: String,
+    seqid: String,
+    start: u32,
+    end: u32,
+    strand: Option<Strand>,
+    sequence: String,
+}
+
+fn feature_sequence(record: &GffRecord, source: &dyn SequenceSource) -> Result<FeatureSequence> {
+    let mut bases = source.fetch(&record.seqid, record.start as u64, record.end as u64)?;
+    if record.strand == Some(Strand::Reverse) {
+        bases = reverse_complement(&bases);
+    }
+    let region = format!("{}:{}-{}", record.seqid, record.start, record.end);
+    Ok(FeatureSequence {
+        id: record.id().map_or(region, |id| id.to_string()),
+        seqid: record.seqid.to_string(),
+        start: record.start,
+        end: record.end,
+        strand: record.strand,
+        sequence: String::from_utf8_lossy(&bases).into_owned(),
+    })
+}
+
+pub fn extract(
+    path: Option<&Path>,
+    fasta: &Path,
+    filter: Option<&str>,
+    output: Option<&Path>,
+    json: bool,
+) -> Result<ExitCode> {
+    let filter: Option<Filter> = filter.map(|f| f.parse()).transpose()?;
+    let source = open_fasta(fasta)?;
+    let mut output = create_output(output)?;
+
+    for record in GffReader::new(open_input(path)?) {
+        let record = record?;
+        if filter.as_ref().is_some_and(|f| !f.matches(&record)) {
+            continue;
+        }
+        let feature = feature_sequence(&record, source.as_ref())?;
+        match json {
+            true => write_json_lines(&mut output, [&feature])?,
+            false => write_fasta(
+                &mut output,
+                &FastaRecord::new(&feature.id, feature.sequence.into_bytes()),
+                DEFAULT_LINE_WIDTH,
+            )?,
+        }
+    }
+    output.finish()?;
+    Ok(ExitCode::SUCCESS)
+}
+
+/// Header directives, with the standard ones parsed. Directives that fail to parse are only
+/// listed.
+#[derive(Debug, Default, Serialize)]
+struct HeaderSummary {
+    directives: Vec<String>,
+    gff_version: Option<GffVersion>,
+    species: Option<Species>,
+    genome_build: Option<GenomeBuild>,
+    sequence_regions: Vec<SequenceRegion>,
+}
+
+impl HeaderSummary {
+    fn new(directives: Vec<String>) -> Self {
+        let mut summary = Self::default();
+        for directive in &directives {
+            let mut parts = directive.split_whitespace();
+            match parts.next() {
+                Some("##gff-version") => summary.gff_version = directive.parse().ok(),
+                Some("##species") => summary.species = directive.parse().ok(),
+                Some("##genome-build") => summary.genome_build = directive.parse().ok(),
+                Some("##sequence-region") => {
+                    summary.sequence_regions.extend(directive.parse().ok())
+                }
+                _ => {}
+            }
+        }
+        summary.directives = directives;
+        summary
+    }
+}
+
+pub fn header(path: Option<&Path>, json: bool) -> Result<ExitCode> {
+    let mut reader = GffReader::new(open_input(path)?);
+    for record in reader.by_ref() {
+        record?;
+    }
+    let summary = HeaderSummary::new(reader.directives().to_vec());
+
+    let mut output = create_output(None)?;
+    match json {
+        true => {
+            serde_json::to_writer_pretty(&mut output, &summary)?;
+            writeln!(output)?;
+        }
+        false => write_directives(&mut output, &summary.directives)?,
+    }
+    output.finish()?;
+    Ok(ExitCode::SUCCESS)
+}
+
+#[cfg(test)]
+mod test_commands {
+    use std::collections::HashMap;
+
+    use super::*;
+    use gff::parse_line;
+
+    #[test]
+    fn test_feature_sequence() {
+        let genome = HashMap::from([("chr1".to_string(), b"AAGGCTTT".to_vec())]);
+        let record = parse_line("chr1\t.\tCDS\t3\t6\t.\t-\t0\tID=cds1").unwrap();
+        let feature = feature_sequence(&record, &genome).unwrap();
+        assert_eq!(
+            (feature.id.as_str(), feature.sequence.as_str()),
+            ("cds1", "AGCC")
+        );
+
+        let record = parse_line("chr1\t.\tCDS\t6\t9\t.\t+\t0\t.").unwrap();
+        assert!(feature_sequence(&record, &genome).is_err());
+    }
+
+    #[test]
+    fn test_filter_trailing_directives() {
+        let dir = std::env::temp_dir().join(format!("genomebase-filter-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let input = dir.join("input.gff3");
+        let output = dir.join("output.gff3.gz");
+        std::fs::write(
+            &input,
+            "##gff-version 3\nchr1\t.\tgene\t1\t100\t.\t+\t.\tID=gene1\n###\n",
+        )
+        .unwrap();
+
+        filter("type == gene", Some(&input), Some(&output), false).unwrap();
+        let mut written = String::new();
+        open_input(Some(&output))
+            .unwrap()
+            .read_to_string(&mut written)
+            .unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(
+            written,
+            "##gff-version 3\nchr1\t.\tgene\t1\t100\t.\t+\t.\tID=gene1\n###\n"
+        );
+    }
+
+    #[test]
+    fn test_header_summary() {
+        let summary = HeaderSummary::new(vec![
+            "##gff-version 3".to_string(),
+            "##sequence-region chr1 1 1000".to_string(),
+            "##genome-build NCBI GRCh38".to_string(),
+        ]);
+        assert_eq!(summary.sequence_regions.len(), 1);
+        assert_eq!(summary.genome_build.unwrap().name, "GRCh38");
+    }
+}
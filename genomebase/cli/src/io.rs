@@ -0,0 +1,175 @@
+//! Input and output streams of the CLI. `-` or a missing path means stdin or stdout, gzip and
+//! bgzip input is detected from its magic bytes, and output paths ending in `.gz` are
+//! compressed.
+
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, BufWriter, Read, Write};
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use fasta::bgzf;
+use fasta::indexed::IndexedFasta;
+use fasta::{FastaReader, SequenceSource};
+use flate2::bufread::MultiGzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use gff::directive::SequenceRegion;
+use gff::reader::GffReader;
+use gff::GffRecord;
+
+const STDIO_PATH: &str = "-";
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+const GZIP_EXTENSION: &str = "gz";
+const SEQUENCE_REGION_DIRECTIVE: &str = "##sequence-region";
+const FASTA_DIRECTIVE: &str = "##FASTA";
+
+fn is_stdio(path: Option<&Path>) -> bool {
+    path.is_none_or(|p| p.as_os_str() == STDIO_PATH)
+}
+
+pub fn open_input(path: Option<&Path>) -> Result<Box<dyn BufRead>> {
+    let mut reader: Box<dyn BufRead> = match path {
+        Some(path) if !is_stdio(Some(path)) => Box::new(BufReader::new(
+            File::open(path).with_context(|| format!("cannot open {}", path.display()))?,
+        )),
+        _ => Box::new(BufReader::new(io::stdin())),
+    };
+    if reader.fill_buf()?.starts_with(&GZIP_MAGIC) {
+        reader = Box::new(BufReader::new(MultiGzDecoder::new(reader)));
+    }
+    Ok(reader)
+}
+
+/// Output stream of a command. `finish` must be called once everything is written: it
+/// flushes the buffer and, for gzip output, writes the gzip trailer and reports its errors.
+pub enum Output {
+    Stdout(BufWriter<io::Stdout>),
+    File(BufWriter<File>),
+    Gzip(BufWriter<GzEncoder<File>>),
+}
+
+impl Output {
+    pub fn finish(self) -> io::Result<()> {
+        match self {
+            Output::Stdout(mut writer) => writer.flush(),
+            Output::File(mut writer) => writer.flush(),
+            Output::Gzip(writer) => {
+                writer.into_inner().map_err(|e| e.into_error())?.finish()?;
+                Ok(())
+            }
+        }
+    }
+
+    fn writer(&mut self) -> &mut dyn Write {
+        match self {
+            Output::Stdout(writer) => writer,
+            Output::File(writer) => writer,
+            Output::Gzip(writer) => writer,
+        }
+    }
+}
+
+impl Write for Output {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.writer().write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.writer().flush()
+    }
+}
+
+pub fn create_output(path: Option<&Path>) -> Result<Output> {
+    let path = match path {
+        Some(path) if !is_stdio(Some(path)) => path,
+        _ => return Ok(Output::Stdout(BufWriter::new(io::stdout()))),
+    };
+    let file = File::create(path).with_context(|| format!("cannot create {}", path.display()))?;
+    if path.extension().is_some_and(|e| e == GZIP_EXTENSION) {
+        return Ok(Output::Gzip(BufWriter::new(GzEncoder::new(
+            file,
+            Compression::default(),
+        ))));
+    }
+    Ok(Output::File(BufWriter::new(file)))
+}
+
+/// Feature records and directives of a GFF3 file.
+pub struct GffInput {
+    pub records: Vec<GffRecord>,
+    pub directives: Vec<String>,
+}
+
+impl GffInput {
+    pub fn read(path: Option<&Path>) -> Result<Self> {
+        let mut reader = GffReader::new(open_input(path)?);
+        let records = reader.by_ref().collect::<Result<Vec<_>>>()?;
+        Ok(Self {
+            records,
+            directives: reader.directives().to_vec(),
+        })
+    }
+
+    pub fn sequence_regions(&self) -> Result<Vec<SequenceRegion>> {
+        sequence_regions(&self.directives)
+    }
+}
+
+pub fn sequence_regions(directives: &[String]) -> Result<Vec<SequenceRegion>> {
+    directives
+        .iter()
+        .filter(|d| d.starts_with(SEQUENCE_REGION_DIRECTIVE))
+        .map(|d| d.parse().map_err(|e| anyhow::anyhow!("{}: {}", d, e)))
+        .collect()
+}
+
+/// Writes directives, except `##FASTA` as the sequences are not copied.
+pub fn write_directives<W: Write>(writer: &mut W, directives: &[String]) -> io::Result<()> {
+    for directive in directives
+        .iter()
+        .filter(|d| !d.starts_with(FASTA_DIRECTIVE))
+    {
+        writeln!(writer, "{}", directive)?;
+    }
+    Ok(())
+}
+
+/// Opens a FASTA file for random access. Plain gzip files cannot be indexed and are read
+/// into memory.
+pub fn open_fasta(path: &Path) -> Result<Box<dyn SequenceSource>> {
+    let mut header = Vec::new();
+    File::open(path)
+        .with_context(|| format!("cannot open {}", path.display()))?
+        .take(bgzf::HEADER_LENGTH as u64)
+        .read_to_end(&mut header)?;
+    if bgzf::is_gzip(&header) && !bgzf::is_bgzf(&header) {
+        let sequences = FastaReader::new(open_input(Some(path))?)
+            .map(|record| record.map(|r| (r.id, r.sequence)))
+            .collect::<Result<HashMap<_, _>>>()?;
+        return Ok(Box::new(sequences));
+    }
+    Ok(Box::new(IndexedFasta::open(path)?))
+}
+
+#[cfg(test)]
+mod test_io {
+    use super::*;
+
+    #[test]
+    fn test_compressed_roundtrip() {
+        let dir = std::env::temp_dir().join(format!("genomebase-cli-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("records.gff3.gz");
+
+        let mut output = create_output(Some(&path)).unwrap();
+        writeln!(output, "##sequence-region chr1 1 1000").unwrap();
+        writeln!(output, "chr1\t.\tgene\t1\t100\t.\t+\t.\tID=gene1").unwrap();
+        output.finish().unwrap();
+
+        let input = GffInput::read(Some(&path)).unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(input.records[0].id(), Some("gene1"));
+        assert_eq!(input.sequence_regions().unwrap()[0].end, 1000);
+    }
+}
@@ -0,0 +1,127 @@
+mod commands;
+mod io;
+
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+use clap::{Parser, Subcommand, ValueEnum};
+
+/// Command line tools for Genomebase GFF3 annotations.
+///
+/// Inputs default to stdin and outputs to stdout. Gzip and bgzip inputs are detected
+/// automatically, and outputs ending in `.gz` are compressed.
+#[derive(Debug, Parser)]
+#[command(name = "genomebase", version)]
+struct Cli {
+    /// Write reports and records as JSON, for use in pipelines.
+    #[arg(long, global = true)]
+    json: bool,
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Format {
+    Gff3,
+    Gtf,
+    Bed,
+    /// One JSON record per line.
+    Jsonl,
+    /// One JSON feature tree, nested by `Parent`, per line.
+    JsonTree,
+}
+
+#[derive(Debug, Subcommand)]
+enum Command {
+    /// Check records and gene models, exiting with failure on errors.
+    #[command(alias = "lint")]
+    Validate {
+        input: Option<PathBuf>,
+        /// Check `##sequence-region` directives against the sequences of a FASTA file.
+        #[arg(long)]
+        fasta: Option<PathBuf>,
+        /// Also fail on warnings.
+        #[arg(long)]
+        strict: bool,
+    },
+    /// Convert GFF3 to another format.
+    Convert {
+        input: Option<PathBuf>,
+        #[arg(long, value_enum)]
+        to: Format,
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+    /// Sort records by seqid and position.
+    Sort {
+        input: Option<PathBuf>,
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+    /// Summary statistics, as TSV or JSON.
+    Stats { input: Option<PathBuf> },
+    /// Keep records matching a filter expression, e.g. `type == CDS and seqid == chr3`.
+    Filter {
+        expression: String,
+        input: Option<PathBuf>,
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+    /// Extract feature sequences from a FASTA file.
+    Extract {
+        input: Option<PathBuf>,
+        #[arg(long)]
+        fasta: PathBuf,
+        /// Only extract features matching a filter expression.
+        #[arg(long)]
+        filter: Option<String>,
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+    /// Print the directives of the header.
+    Header { input: Option<PathBuf> },
+}
+
+fn run(cli: Cli) -> anyhow::Result<ExitCode> {
+    let json = cli.json;
+    match cli.command {
+        Command::Validate {
+            input,
+            fasta,
+            strict,
+        } => commands::validate(input.as_deref(), fasta.as_deref(), strict, json),
+        Command::Convert { input, to, output } => {
+            commands::convert(input.as_deref(), to, output.as_deref())
+        }
+        Command::Sort { input, output } => commands::sort(input.as_deref(), output.as_deref()),
+        Command::Stats { input } => commands::stats(input.as_deref(), json),
+        Command::Filter {
+            expression,
+            input,
+            output,
+        } => commands::filter(&expression, input.as_deref(), output.as_deref(), json),
+        Command::Extract {
+            input,
+            fasta,
+            filter,
+            output,
+        } => commands::extract(
+            input.as_deref(),
+            &fasta,
+            filter.as_deref(),
+            output.as_deref(),
+            json,
+        ),
+        Command::Header { input } => commands::header(input.as_deref(), json),
+    }
+}
+
+fn main() -> ExitCode {
+    match run(Cli::parse()) {
+        Ok(code) => code,
+        Err(e) => {
+            eprintln!("error: {:#}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
@@ -1,12 +1,10 @@
+pub mod seqid;
+
 use std::collections::HashMap;
 
 use serde::{Deserialize, Serialize};
 
-#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
-pub enum Chromosome {
-    Char(char),
-    Number(u64),
-}
+pub use seqid::{Seqid, SeqidAliases, Topology};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Paper {
@@ -15,9 +13,19 @@ pub struct Paper {
     citaion_styles: HashMap<String, String>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
 pub struct User {
-    name: String,
-    email: String,
-    orc_id: String,
+    pub name: String,
+    pub email: String,
+    pub orc_id: String,
+}
+
+impl User {
+    pub fn new(name: &str, email: &str, orc_id: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            email: email.to_string(),
+            orc_id: orc_id.to_string(),
+        }
+    }
 }
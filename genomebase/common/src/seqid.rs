@@ -0,0 +1,272 @@
+//! Sequence identifiers shared by annotations and genome models: chromosome, scaffold and
+//! contig names such as `chr1`, `NC_000001.11`, `chrUn_KI270302v1` or `MT`.
+
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::fmt;
+use std::ops::Deref;
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+
+/// A sequence name. Seqids compare in natural order, numbers within names by value, so that
+/// `chr2` sorts before `chr10`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct Seqid(String);
+
+impl Seqid {
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl FromStr for Seqid {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.is_empty() || s.contains(char::is_whitespace) {
+            return Err(format!("invalid seqid: {:?}", s));
+        }
+        Ok(Self(s.to_string()))
+    }
+}
+
+impl TryFrom<String> for Seqid {
+    type Error = String;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        s.parse()
+    }
+}
+
+impl From<Seqid> for String {
+    fn from(seqid: Seqid) -> Self {
+        seqid.0
+    }
+}
+
+impl Deref for Seqid {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl AsRef<str> for Seqid {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl fmt::Display for Seqid {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl PartialEq<str> for Seqid {
+    fn eq(&self, other: &str) -> bool {
+        self.0 == other
+    }
+}
+
+impl PartialEq<&str> for Seqid {
+    fn eq(&self, other: &&str) -> bool {
+        self.0 == *other
+    }
+}
+
+impl PartialEq<String> for Seqid {
+    fn eq(&self, other: &String) -> bool {
+        &self.0 == other
+    }
+}
+
+impl PartialEq<Seqid> for String {
+    fn eq(&self, other: &Seqid) -> bool {
+        *self == other.0
+    }
+}
+
+impl Ord for Seqid {
+    fn cmp(&self, other: &Self) -> Ordering {
+        natural_cmp(&self.0, &other.0).then_with(|| self.0.cmp(&other.0))
+    }
+}
+
+impl PartialOrd for Seqid {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// Splits a name into runs of digits and runs of other characters.
+fn chunks(s: &str) -> impl Iterator<Item = &str> {
+    let mut rest = s;
+    std::iter::from_fn(move || {
+        let first = rest.chars().next()?;
+        let digits = first.is_ascii_digit();
+        let end = rest
+            .find(|c: char| c.is_ascii_digit() != digits)
+            .unwrap_or(rest.len());
+        let (chunk, tail) = rest.split_at(end);
+        rest = tail;
+        Some(chunk)
+    })
+}
+
+/// Compares names chunk by chunk, digit runs by value and before other characters. Names
+/// differing only in leading zeros compare equal.
+pub fn natural_cmp(a: &str, b: &str) -> Ordering {
+    let mut a_chunks = chunks(a);
+    let mut b_chunks = chunks(b);
+    loop {
+        let ordering = match (a_chunks.next(), b_chunks.next()) {
+            (None, None) => return Ordering::Equal,
+            (None, Some(_)) => return Ordering::Less,
+            (Some(_), None) => return Ordering::Greater,
+            (Some(x), Some(y)) => {
+                let x_digits = x.starts_with(|c: char| c.is_ascii_digit());
+                let y_digits = y.starts_with(|c: char| c.is_ascii_digit());
+                match (x_digits, y_digits) {
+                    (true, true) => {
+                        let (x, y) = (x.trim_start_matches('0'), y.trim_start_matches('0'));
+                        x.len().cmp(&y.len()).then_with(|| x.cmp(y))
+                    }
+                    (true, false) => Ordering::Less,
+                    (false, true) => Ordering::Greater,
+                    (false, false) => x.cmp(y),
+                }
+            }
+        };
+        if ordering.is_ne() {
+            return ordering;
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Topology {
+    #[default]
+    Linear,
+    Circular,
+}
+
+/// Sets of names for the same sequence, such as `chr1`, `1` and `NC_000001.11`. The first
+/// name added for a sequence is its canonical name.
+#[derive(Debug, Clone, Default)]
+pub struct SeqidAliases {
+    sets: Vec<Vec<Seqid>>,
+    index: HashMap<Seqid, usize>,
+}
+
+impl SeqidAliases {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds names of one sequence. Names already known extend their set, and joining two
+    /// different sets is an error.
+    pub fn insert(&mut self, names: &[Seqid]) -> Result<(), String> {
+        let mut known = names
+            .iter()
+            .filter_map(|name| self.index.get(name).copied());
+        let set = match known.next() {
+            Some(set) => {
+                if let Some(other) = known.find(|&other| other != set) {
+                    return Err(format!(
+                        "{} and {} are different sequences",
+                        self.sets[set][0], self.sets[other][0]
+                    ));
+                }
+                set
+            }
+            None => {
+                self.sets.push(Vec::new());
+                self.sets.len() - 1
+            }
+        };
+        for name in names {
+            if !self.index.contains_key(name) {
+                self.index.insert(name.clone(), set);
+                self.sets[set].push(name.clone());
+            }
+        }
+        Ok(())
+    }
+
+    /// The canonical name of a known sequence.
+    pub fn canonical(&self, seqid: &Seqid) -> Option<&Seqid> {
+        self.index.get(seqid).map(|&set| &self.sets[set][0])
+    }
+
+    /// The canonical name, or the name itself when unknown.
+    pub fn resolve<'a>(&'a self, seqid: &'a Seqid) -> &'a Seqid {
+        self.canonical(seqid).unwrap_or(seqid)
+    }
+
+    /// All names of the sequence, canonical first; empty when unknown.
+    pub fn aliases(&self, seqid: &Seqid) -> &[Seqid] {
+        self.index
+            .get(seqid)
+            .map_or(&[], |&set| self.sets[set].as_slice())
+    }
+
+    pub fn same_sequence(&self, a: &Seqid, b: &Seqid) -> bool {
+        a == b || self.resolve(a) == self.resolve(b)
+    }
+}
+
+#[cfg(test)]
+mod test_seqid {
+    use super::*;
+
+    fn seqid(s: &str) -> Seqid {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn test_natural_order() {
+        let mut seqids: Vec<Seqid> = ["chr10", "chrX", "chr2", "chr1", "chrUn_KI270302v1", "MT"]
+            .iter()
+            .map(|s| seqid(s))
+            .collect();
+        seqids.sort();
+        let names: Vec<&str> = seqids.iter().map(|s| s.as_str()).collect();
+        assert_eq!(
+            names,
+            vec!["MT", "chr1", "chr2", "chr10", "chrUn_KI270302v1", "chrX"]
+        );
+        assert!(seqid("scaffold_9") < seqid("scaffold_123"));
+        assert!(seqid("NC_000001.11") < seqid("NC_000002.12"));
+        assert_ne!(seqid("chr01").cmp(&seqid("chr1")), Ordering::Equal);
+
+        assert!("".parse::<Seqid>().is_err());
+        assert!("chr 1".parse::<Seqid>().is_err());
+    }
+
+    #[test]
+    fn test_aliases() {
+        let mut aliases = SeqidAliases::new();
+        aliases
+            .insert(&[seqid("chr1"), seqid("1"), seqid("NC_000001.11")])
+            .unwrap();
+        aliases.insert(&[seqid("chrM"), seqid("MT")]).unwrap();
+        aliases
+            .insert(&[seqid("MT"), seqid("NC_012920.1")])
+            .unwrap();
+
+        assert_eq!(
+            aliases.canonical(&seqid("NC_000001.11")),
+            Some(&seqid("chr1"))
+        );
+        assert_eq!(aliases.resolve(&seqid("NC_012920.1")), &seqid("chrM"));
+        assert_eq!(aliases.resolve(&seqid("chr2")), &seqid("chr2"));
+        assert_eq!(aliases.aliases(&seqid("chrM")).len(), 3);
+        assert!(aliases.same_sequence(&seqid("1"), &seqid("chr1")));
+        assert!(!aliases.same_sequence(&seqid("1"), &seqid("MT")));
+        assert!(aliases.insert(&[seqid("1"), seqid("MT")]).is_err());
+    }
+}
@@ -0,0 +1,113 @@
+//! BGZF, the blocked gzip format written by `bgzip`, and its `.gzi` block index.
+
+use std::io::{self, BufReader, Read, Seek, SeekFrom, Write};
+
+use anyhow::{anyhow, Result};
+use flate2::bufread::MultiGzDecoder;
+
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+const FLAG_EXTRA: u8 = 0x04;
+/// Length of a BGZF block header, up to and including the block size.
+pub const HEADER_LENGTH: usize = 18;
+const SUBFIELD_ID: [u8; 2] = *b"BC";
+
+/// Whether the first bytes of a file start a BGZF block.
+pub fn is_bgzf(header: &[u8]) -> bool {
+    header.len() >= HEADER_LENGTH
+        && header[..2] == GZIP_MAGIC
+        && header[3] & FLAG_EXTRA != 0
+        && header[12..14] == SUBFIELD_ID
+}
+
+pub fn is_gzip(header: &[u8]) -> bool {
+    header.starts_with(&GZIP_MAGIC)
+}
+
+/// Compressed and uncompressed offsets of the start of every block.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GziIndex {
+    blocks: Vec<(u64, u64)>,
+}
+
+impl GziIndex {
+    /// Scans block headers without decompressing.
+    pub fn build<R: Read + Seek>(reader: &mut R) -> Result<Self> {
+        let mut blocks = Vec::new();
+        let (mut compressed, mut uncompressed) = (0, 0);
+        reader.seek(SeekFrom::Start(0))?;
+        let mut header = [0; HEADER_LENGTH];
+
+        loop {
+            match reader.read_exact(&mut header) {
+                Ok(()) => {}
+                Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => break,
+                Err(e) => return Err(e.into()),
+            }
+            if !is_bgzf(&header) {
+                return Err(anyhow!("no BGZF block at offset {}", compressed));
+            }
+            let block_size = u16::from_le_bytes([header[16], header[17]]) as u64 + 1;
+            reader.seek(SeekFrom::Start(compressed + block_size - 4))?;
+            let mut size = [0; 4];
+            reader.read_exact(&mut size)?;
+
+            blocks.push((compressed, uncompressed));
+            compressed += block_size;
+            uncompressed += u32::from_le_bytes(size) as u64;
+        }
+        Ok(Self { blocks })
+    }
+
+    /// Reads a `.gzi` file, which omits the first block at offset 0.
+    pub fn read<R: Read>(mut reader: R) -> Result<Self> {
+        let mut number = [0; 8];
+        reader.read_exact(&mut number)?;
+        let mut blocks = vec![(0, 0)];
+        for _ in 0..u64::from_le_bytes(number) {
+            let mut pair = [0; 16];
+            reader.read_exact(&mut pair)?;
+            let compressed = u64::from_le_bytes(pair[..8].try_into()?);
+            let uncompressed = u64::from_le_bytes(pair[8..].try_into()?);
+            blocks.push((compressed, uncompressed));
+        }
+        Ok(Self { blocks })
+    }
+
+    pub fn write<W: Write>(&self, mut writer: W) -> Result<()> {
+        let blocks: Vec<&(u64, u64)> = self.blocks.iter().filter(|b| **b != (0, 0)).collect();
+        writer.write_all(&(blocks.len() as u64).to_le_bytes())?;
+        for (compressed, uncompressed) in blocks {
+            writer.write_all(&compressed.to_le_bytes())?;
+            writer.write_all(&uncompressed.to_le_bytes())?;
+        }
+        Ok(())
+    }
+
+    /// The block containing an uncompressed offset.
+    fn block(&self, offset: u64) -> (u64, u64) {
+        let i = self.blocks.partition_point(|&(_, u)| u <= offset);
+        self.blocks
+            .get(i.wrapping_sub(1))
+            .copied()
+            .unwrap_or((0, 0))
+    }
+
+    /// Reads `length` uncompressed bytes from `offset`, or fewer at the end of the file.
+    pub fn read_at<R: Read + Seek>(
+        &self,
+        reader: &mut R,
+        offset: u64,
+        length: u64,
+    ) -> Result<Vec<u8>> {
+        let (compressed, uncompressed) = self.block(offset);
+        reader.seek(SeekFrom::Start(compressed))?;
+        let mut decoder = MultiGzDecoder::new(BufReader::new(reader));
+        io::copy(
+            &mut decoder.by_ref().take(offset - uncompressed),
+            &mut io::sink(),
+        )?;
+        let mut bytes = Vec::new();
+        decoder.take(length).read_to_end(&mut bytes)?;
+        Ok(bytes)
+    }
+}
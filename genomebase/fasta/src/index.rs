@@ -0,0 +1,299 @@
+//! `.fai` indexes, in the format written by `samtools faidx`.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::io::{BufRead, Write};
+
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+
+const FIELD_DELIMITER: char = '\t';
+const HEADER_PREFIX: u8 = b'>';
+
+/// Location of one sequence in the uncompressed FASTA file.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FaiEntry {
+    pub name: String,
+    pub length: u64,
+    /// Byte offset of the first base.
+    pub offset: u64,
+    pub line_bases: u64,
+    /// Bytes per line, including the line terminator.
+    pub line_width: u64,
+}
+
+impl FaiEntry {
+    /// Byte offset of a 0-based position.
+    pub fn byte_offset(&self, position: u64) -> u64 {
+        if self.line_bases == 0 {
+            return self.offset;
+        }
+        self.offset + (position / self.line_bases) * self.line_width + position % self.line_bases
+    }
+}
+
+impl fmt::Display for FaiEntry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}\t{}\t{}\t{}\t{}",
+            self.name, self.length, self.offset, self.line_bases, self.line_width
+        )
+    }
+}
+
+/// A sequence whose name or length differs between the FASTA file and an annotation.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SequenceMismatch {
+    MissingFromFasta(String),
+    MissingFromAnnotation(String),
+    LengthMismatch {
+        name: String,
+        fasta: u64,
+        annotation: u64,
+    },
+}
+
+impl fmt::Display for SequenceMismatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::MissingFromFasta(name) => write!(f, "{} is not in the FASTA file", name),
+            Self::MissingFromAnnotation(name) => {
+                write!(f, "{} is not declared in the annotation", name)
+            }
+            Self::LengthMismatch {
+                name,
+                fasta,
+                annotation,
+            } => write!(
+                f,
+                "{} has length {} in the FASTA file but {} in the annotation",
+                name, fasta, annotation
+            ),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FastaIndex {
+    entries: Vec<FaiEntry>,
+    positions: HashMap<String, usize>,
+}
+
+/// State of the sequence being indexed.
+struct PendingEntry {
+    entry: FaiEntry,
+    /// Set once a line shorter than `line_bases` has been seen, which must be the last.
+    short_line: bool,
+}
+
+impl FastaIndex {
+    pub fn new(entries: Vec<FaiEntry>) -> Result<Self> {
+        let mut positions = HashMap::new();
+        for (i, entry) in entries.iter().enumerate() {
+            if positions.insert(entry.name.clone(), i).is_some() {
+                return Err(anyhow!("duplicate sequence name: {}", entry.name));
+            }
+        }
+        Ok(Self { entries, positions })
+    }
+
+    pub fn entries(&self) -> &[FaiEntry] {
+        &self.entries
+    }
+
+    pub fn get(&self, name: &str) -> Option<&FaiEntry> {
+        self.positions.get(name).map(|&i| &self.entries[i])
+    }
+
+    /// Indexes an uncompressed FASTA stream. Every line of a sequence but the last must have
+    /// the same length.
+    pub fn build<R: BufRead>(mut reader: R) -> Result<Self> {
+        let mut entries = Vec::new();
+        let mut pending: Option<PendingEntry> = None;
+        let mut offset = 0;
+        let mut line = Vec::new();
+        let mut line_number = 0;
+
+        loop {
+            line.clear();
+            let width = reader.read_until(b'\n', &mut line)? as u64;
+            if width == 0 {
+                break;
+            }
+            line_number += 1;
+            offset += width;
+
+            if line.first() == Some(&HEADER_PREFIX) {
+                entries.extend(pending.take().map(|p| p.entry));
+                let header = String::from_utf8_lossy(&line[1..]);
+                let name = header.split_whitespace().next().unwrap_or_default();
+                if name.is_empty() {
+                    return Err(anyhow!("line {}: empty sequence name", line_number));
+                }
+                pending = Some(PendingEntry {
+                    entry: FaiEntry {
+                        name: name.to_string(),
+                        length: 0,
+                        offset,
+                        line_bases: 0,
+                        line_width: 0,
+                    },
+                    short_line: false,
+                });
+                continue;
+            }
+
+            // The last line of a file may have no terminator.
+            let terminated = line.last() == Some(&b'\n');
+            let bases = line
+                .iter()
+                .take_while(|&&b| b != b'\n' && b != b'\r')
+                .count() as u64;
+            let current = pending
+                .as_mut()
+                .ok_or_else(|| anyhow!("line {}: sequence before header", line_number))?;
+            if bases == 0 {
+                current.short_line = true;
+                continue;
+            }
+            if current.short_line {
+                return Err(anyhow!(
+                    "line {}: {} has lines of different lengths",
+                    line_number,
+                    current.entry.name
+                ));
+            }
+            let entry = &mut current.entry;
+            if entry.line_bases == 0 {
+                entry.line_bases = bases;
+                entry.line_width = width;
+            } else if bases > entry.line_bases
+                || (terminated && width - bases != entry.line_width - entry.line_bases)
+            {
+                return Err(anyhow!(
+                    "line {}: {} has lines of different lengths",
+                    line_number,
+                    entry.name
+                ));
+            }
+            current.short_line = bases < entry.line_bases;
+            entry.length += bases;
+        }
+
+        entries.extend(pending.map(|p| p.entry));
+        Self::new(entries)
+    }
+
+    /// Reads a `.fai` file.
+    pub fn read<R: BufRead>(reader: R) -> Result<Self> {
+        let mut entries = Vec::new();
+        for (i, line) in reader.lines().enumerate() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            let fields: Vec<&str> = line.split(FIELD_DELIMITER).collect();
+            let number = |j: usize| -> Result<u64> {
+                fields
+                    .get(j)
+                    .ok_or_else(|| anyhow!("line {}: expected 5 fields", i + 1))?
+                    .parse()
+                    .map_err(|e| anyhow!("line {}: {}", i + 1, e))
+            };
+            entries.push(FaiEntry {
+                name: fields[0].to_string(),
+                length: number(1)?,
+                offset: number(2)?,
+                line_bases: number(3)?,
+                line_width: number(4)?,
+            });
+        }
+        Self::new(entries)
+    }
+
+    pub fn write<W: Write>(&self, mut writer: W) -> Result<()> {
+        for entry in &self.entries {
+            writeln!(writer, "{}", entry)?;
+        }
+        Ok(())
+    }
+
+    /// Compares the sequences with those declared by an annotation, as `(name, length)`.
+    pub fn check_sequences(&self, declared: &[(String, u64)]) -> Vec<SequenceMismatch> {
+        let mut mismatches = Vec::new();
+        for (name, length) in declared {
+            match self.get(name) {
+                None => mismatches.push(SequenceMismatch::MissingFromFasta(name.clone())),
+                Some(entry) if entry.length != *length => {
+                    mismatches.push(SequenceMismatch::LengthMismatch {
+                        name: name.clone(),
+                        fasta: entry.length,
+                        annotation: *length,
+                    })
+                }
+                Some(_) => {}
+            }
+        }
+        for entry in &self.entries {
+            if !declared.iter().any(|(name, _)| *name == entry.name) {
+                mismatches.push(SequenceMismatch::MissingFromAnnotation(entry.name.clone()));
+            }
+        }
+        mismatches
+    }
+}
+
+#[cfg(test)]
+mod test_index {
+    use super::*;
+
+    #[test]
+    fn test_build_index() {
+        let fasta = ">chr1 first\nACGTA\nCGTAC\nGT\n>chr2\r\nAAAA\r\nCC\r\n";
+        let index = FastaIndex::build(fasta.as_bytes()).unwrap();
+
+        let mut fai = Vec::new();
+        index.write(&mut fai).unwrap();
+        assert_eq!(
+            String::from_utf8(fai.clone()).unwrap(),
+            "chr1\t12\t12\t5\t6\nchr2\t6\t34\t4\t6\n"
+        );
+        assert_eq!(FastaIndex::read(fai.as_slice()).unwrap(), index);
+        assert_eq!(index.get("chr1").unwrap().byte_offset(11), 12 + 12 + 1);
+    }
+
+    #[test]
+    fn test_no_trailing_newline() {
+        let index = FastaIndex::build(">chr1\nACGT\nAC".as_bytes()).unwrap();
+        let entry = index.get("chr1").unwrap();
+        assert_eq!(
+            (entry.length, entry.line_bases, entry.line_width),
+            (6, 4, 5)
+        );
+    }
+
+    #[test]
+    fn test_inconsistent_lines() {
+        let err = FastaIndex::build(">chr1\nACG\nACGTA\n".as_bytes()).unwrap_err();
+        assert!(err.to_string().starts_with("line 3:"));
+    }
+
+    #[test]
+    fn test_check_sequences() {
+        let index = FastaIndex::build(">chr1\nACGT\n>chrM\nAC\n".as_bytes()).unwrap();
+        let declared = vec![("chr1".to_string(), 5), ("chr2".to_string(), 10)];
+        assert_eq!(
+            index.check_sequences(&declared),
+            vec![
+                SequenceMismatch::LengthMismatch {
+                    name: "chr1".to_string(),
+                    fasta: 4,
+                    annotation: 5
+                },
+                SequenceMismatch::MissingFromFasta("chr2".to_string()),
+                SequenceMismatch::MissingFromAnnotation("chrM".to_string()),
+            ]
+        );
+    }
+}
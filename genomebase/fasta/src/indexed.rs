@@ -0,0 +1,257 @@
+//! Random access to FASTA files through their `.fai` index, for plain and bgzip-compressed
+//! files.
+
+use std::fs::File;
+use std::io::{BufReader, Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::Mutex;
+
+use anyhow::{anyhow, Result};
+use flate2::bufread::MultiGzDecoder;
+
+use crate::bgzf::{is_bgzf, is_gzip, GziIndex, HEADER_LENGTH};
+use crate::index::FastaIndex;
+use crate::SequenceSource;
+
+const FAI_EXTENSION: &str = "fai";
+const GZI_EXTENSION: &str = "gzi";
+
+/// A region written `chr1:1,000-2,000`, or a whole sequence written `chr1`. Coordinates are
+/// 1-based and inclusive.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Region {
+    pub seqid: String,
+    pub range: Option<(u64, u64)>,
+}
+
+impl FromStr for Region {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let parse_range = |range: &str| -> Option<(u64, u64)> {
+            let range = range.replace(',', "");
+            let (start, end) = range.split_once('-')?;
+            Some((start.parse().ok()?, end.parse().ok()?))
+        };
+        // Sequence names may contain `:`, so a suffix that is not a range is part of the name.
+        match s
+            .rsplit_once(':')
+            .and_then(|(seqid, r)| Some((seqid, parse_range(r)?)))
+        {
+            Some((_, (start, end))) if start == 0 || start > end => {
+                Err(format!("invalid region: {}", s))
+            }
+            Some((seqid, range)) => Ok(Self {
+                seqid: seqid.to_string(),
+                range: Some(range),
+            }),
+            None => Ok(Self {
+                seqid: s.to_string(),
+                range: None,
+            }),
+        }
+    }
+}
+
+enum Storage<R> {
+    Plain(R),
+    Bgzf(R, GziIndex),
+}
+
+impl<R: Read + Seek> Storage<R> {
+    fn read_at(&mut self, offset: u64, length: u64) -> Result<Vec<u8>> {
+        match self {
+            Self::Plain(reader) => {
+                reader.seek(SeekFrom::Start(offset))?;
+                let mut bytes = Vec::new();
+                reader.take(length).read_to_end(&mut bytes)?;
+                Ok(bytes)
+            }
+            Self::Bgzf(reader, gzi) => gzi.read_at(reader, offset, length),
+        }
+    }
+}
+
+/// A FASTA file with its index. Sequences are returned as stored, so soft-masked repeats
+/// keep their lower case.
+pub struct IndexedFasta<R> {
+    storage: Mutex<Storage<R>>,
+    index: FastaIndex,
+}
+
+fn sibling(path: &Path, extension: &str) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(".");
+    name.push(extension);
+    PathBuf::from(name)
+}
+
+impl IndexedFasta<File> {
+    /// Opens a plain or bgzip-compressed FASTA file, loading `<path>.fai` and `<path>.gzi`
+    /// when they exist and building the indexes in memory otherwise.
+    pub fn open(path: &Path) -> Result<Self> {
+        let mut file = File::open(path)?;
+        let mut header = Vec::new();
+        (&mut file)
+            .take(HEADER_LENGTH as u64)
+            .read_to_end(&mut header)?;
+        file.seek(SeekFrom::Start(0))?;
+
+        let fai_path = sibling(path, FAI_EXTENSION);
+        let load_fai = |file: &mut File, bgzf: bool| -> Result<FastaIndex> {
+            if fai_path.exists() {
+                return FastaIndex::read(BufReader::new(File::open(&fai_path)?));
+            }
+            file.seek(SeekFrom::Start(0))?;
+            let reader = BufReader::new(&mut *file);
+            let index = match bgzf {
+                true => FastaIndex::build(BufReader::new(MultiGzDecoder::new(reader)))?,
+                false => FastaIndex::build(reader)?,
+            };
+            file.seek(SeekFrom::Start(0))?;
+            Ok(index)
+        };
+
+        if is_bgzf(&header) {
+            let gzi_path = sibling(path, GZI_EXTENSION);
+            let gzi = match gzi_path.exists() {
+                true => GziIndex::read(BufReader::new(File::open(&gzi_path)?))?,
+                false => GziIndex::build(&mut file)?,
+            };
+            let index = load_fai(&mut file, true)?;
+            return Ok(Self::new_bgzf(file, index, gzi));
+        }
+        if is_gzip(&header) {
+            return Err(anyhow!(
+                "{} is gzip-compressed; random access needs bgzip compression",
+                path.display()
+            ));
+        }
+        let index = load_fai(&mut file, false)?;
+        Ok(Self::new(file, index))
+    }
+}
+
+impl<R: Read + Seek> IndexedFasta<R> {
+    pub fn new(reader: R, index: FastaIndex) -> Self {
+        Self {
+            storage: Mutex::new(Storage::Plain(reader)),
+            index,
+        }
+    }
+
+    pub fn new_bgzf(reader: R, index: FastaIndex, gzi: GziIndex) -> Self {
+        Self {
+            storage: Mutex::new(Storage::Bgzf(reader, gzi)),
+            index,
+        }
+    }
+
+    pub fn index(&self) -> &FastaIndex {
+        &self.index
+    }
+
+    pub fn fetch_region(&self, region: &Region) -> Result<Vec<u8>> {
+        let length = self
+            .sequence_length(&region.seqid)
+            .ok_or_else(|| anyhow!("unknown sequence {}", region.seqid))?;
+        let (start, end) = region.range.unwrap_or((1, length));
+        self.fetch(&region.seqid, start, end)
+    }
+}
+
+impl<R: Read + Seek> SequenceSource for IndexedFasta<R> {
+    fn sequence_length(&self, seqid: &str) -> Option<u64> {
+        self.index.get(seqid).map(|e| e.length)
+    }
+
+    fn fetch(&self, seqid: &str, start: u64, end: u64) -> Result<Vec<u8>> {
+        let entry = self
+            .index
+            .get(seqid)
+            .ok_or_else(|| anyhow!("unknown sequence {}", seqid))?;
+        if start == 0 || start > end || end > entry.length {
+            return Err(anyhow!(
+                "{}:{}-{} is outside the sequence",
+                seqid,
+                start,
+                end
+            ));
+        }
+
+        let first = entry.byte_offset(start - 1);
+        let last = entry.byte_offset(end - 1);
+        let mut storage = self
+            .storage
+            .lock()
+            .map_err(|_| anyhow!("FASTA reader is poisoned"))?;
+        let mut bytes = storage.read_at(first, last - first + 1)?;
+        bytes.retain(|&b| b != b'\n' && b != b'\r');
+        if bytes.len() as u64 != end - start + 1 {
+            return Err(anyhow!("{} is shorter than its index", seqid));
+        }
+        Ok(bytes)
+    }
+}
+
+#[cfg(test)]
+mod test_indexed {
+    use std::io::{Cursor, Write};
+
+    use flate2::write::GzEncoder;
+    use flate2::Compression;
+
+    use super::*;
+
+    const FASTA: &str = ">chr1\nACGTacgtAC\nGGGGtttt\n>chr2:alt\nNNAC\n";
+
+    /// Compresses data in BGZF blocks of `block_size` uncompressed bytes.
+    fn bgzip(data: &[u8], block_size: usize) -> Vec<u8> {
+        let mut out = Vec::new();
+        for chunk in data.chunks(block_size).chain([&[][..]]) {
+            let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+            encoder.write_all(chunk).unwrap();
+            let member = encoder.finish().unwrap();
+            // Rewrite the gzip header with the BC extra field that bgzip adds.
+            let deflate = &member[10..member.len() - 8];
+            let block_size = (HEADER_LENGTH + deflate.len() + 8 - 1) as u16;
+            out.extend([
+                0x1f, 0x8b, 8, 4, 0, 0, 0, 0, 0, 0xff, 6, 0, b'B', b'C', 2, 0,
+            ]);
+            out.extend(block_size.to_le_bytes());
+            out.extend(deflate);
+            out.extend(&member[member.len() - 8..]);
+        }
+        out
+    }
+
+    #[test]
+    fn test_fetch_plain() {
+        let index = FastaIndex::build(FASTA.as_bytes()).unwrap();
+        let fasta = IndexedFasta::new(Cursor::new(FASTA.as_bytes()), index);
+
+        assert_eq!(fasta.fetch("chr1", 7, 13).unwrap(), b"gtACGGG");
+        let region: Region = "chr2:alt".parse().unwrap();
+        assert_eq!(fasta.fetch_region(&region).unwrap(), b"NNAC");
+        let region: Region = "chr1:1-4".parse().unwrap();
+        assert_eq!(fasta.fetch_region(&region).unwrap(), b"ACGT");
+        assert!(fasta.fetch("chr1", 15, 19).is_err());
+    }
+
+    #[test]
+    fn test_fetch_bgzf() {
+        let compressed = bgzip(FASTA.as_bytes(), 7);
+        let mut reader = Cursor::new(compressed);
+        let gzi = GziIndex::build(&mut reader).unwrap();
+        let index = FastaIndex::build(FASTA.as_bytes()).unwrap();
+
+        let mut written = Vec::new();
+        gzi.write(&mut written).unwrap();
+        assert_eq!(GziIndex::read(written.as_slice()).unwrap(), gzi);
+
+        let fasta = IndexedFasta::new_bgzf(reader, index, gzi);
+        assert_eq!(fasta.fetch("chr1", 7, 18).unwrap(), b"gtACGGGGtttt");
+        assert_eq!(fasta.fetch("chr2:alt", 2, 3).unwrap(), b"NA");
+    }
+}
@@ -0,0 +1,217 @@
+pub mod bgzf;
+pub mod index;
+pub mod indexed;
+
+use std::collections::HashMap;
+use std::io::{BufRead, Write};
+
+use anyhow::{anyhow, Result};
+
+const HEADER_PREFIX: char = '>';
+pub const DEFAULT_LINE_WIDTH: usize = 60;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FastaRecord {
+    pub id: String,
+    pub description: Option<String>,
+    pub sequence: Vec<u8>,
+}
+
+impl FastaRecord {
+    pub fn new(id: &str, sequence: Vec<u8>) -> Self {
+        Self {
+            id: id.to_string(),
+            description: None,
+            sequence,
+        }
+    }
+}
+
+/// Random access to the bases of named sequences.
+pub trait SequenceSource {
+    fn sequence_length(&self, seqid: &str) -> Option<u64>;
+
+    /// Bases from `start` to `end`, 1-based and inclusive.
+    fn fetch(&self, seqid: &str, start: u64, end: u64) -> Result<Vec<u8>>;
+}
+
+/// Sequences held in memory, keyed by ID.
+impl SequenceSource for HashMap<String, Vec<u8>> {
+    fn sequence_length(&self, seqid: &str) -> Option<u64> {
+        self.get(seqid).map(|s| s.len() as u64)
+    }
+
+    fn fetch(&self, seqid: &str, start: u64, end: u64) -> Result<Vec<u8>> {
+        let sequence = self
+            .get(seqid)
+            .ok_or_else(|| anyhow!("unknown sequence {}", seqid))?;
+        if start == 0 || start > end || end > sequence.len() as u64 {
+            return Err(anyhow!(
+                "{}:{}-{} is outside the sequence",
+                seqid,
+                start,
+                end
+            ));
+        }
+        Ok(sequence[start as usize - 1..end as usize].to_vec())
+    }
+}
+
+/// Streaming reader over the records of a FASTA file.
+pub struct FastaReader<R> {
+    inner: R,
+    buf: String,
+    line_number: usize,
+    /// Header line of the next record, read while finishing the previous one.
+    next_header: Option<String>,
+}
+
+impl<R: BufRead> FastaReader<R> {
+    pub fn new(inner: R) -> Self {
+        Self {
+            inner,
+            buf: String::new(),
+            line_number: 0,
+            next_header: None,
+        }
+    }
+
+    fn read_line(&mut self) -> Result<Option<String>> {
+        self.buf.clear();
+        if self.inner.read_line(&mut self.buf)? == 0 {
+            return Ok(None);
+        }
+        self.line_number += 1;
+        Ok(Some(self.buf.trim_end_matches(['\n', '\r']).to_string()))
+    }
+
+    fn read_record(&mut self) -> Result<Option<FastaRecord>> {
+        let header = match self.next_header.take() {
+            Some(header) => header,
+            None => loop {
+                match self.read_line()? {
+                    None => return Ok(None),
+                    Some(line) if line.trim().is_empty() => continue,
+                    Some(line) if line.starts_with(HEADER_PREFIX) => break line,
+                    Some(_) => {
+                        return Err(anyhow!("line {}: sequence before header", self.line_number))
+                    }
+                }
+            },
+        };
+
+        let header = header[HEADER_PREFIX.len_utf8()..].trim();
+        let (id, description) = match header.split_once(char::is_whitespace) {
+            Some((id, description)) => (id, Some(description.trim().to_string())),
+            None => (header, None),
+        };
+        if id.is_empty() {
+            return Err(anyhow!("line {}: empty sequence name", self.line_number));
+        }
+
+        let mut record = FastaRecord {
+            id: id.to_string(),
+            description,
+            sequence: Vec::new(),
+        };
+        while let Some(line) = self.read_line()? {
+            if line.starts_with(HEADER_PREFIX) {
+                self.next_header = Some(line);
+                break;
+            }
+            record.sequence.extend(line.trim().bytes());
+        }
+        Ok(Some(record))
+    }
+}
+
+impl<R: BufRead> Iterator for FastaReader<R> {
+    type Item = Result<FastaRecord>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.read_record().transpose()
+    }
+}
+
+/// Writes a record with the sequence wrapped at `width` characters.
+pub fn write_fasta<W: Write>(writer: &mut W, record: &FastaRecord, width: usize) -> Result<()> {
+    match &record.description {
+        Some(description) => writeln!(writer, "{}{} {}", HEADER_PREFIX, record.id, description)?,
+        None => writeln!(writer, "{}{}", HEADER_PREFIX, record.id)?,
+    }
+    for line in record.sequence.chunks(width.max(1)) {
+        writer.write_all(line)?;
+        writeln!(writer)?;
+    }
+    Ok(())
+}
+
+/// Complement of a nucleotide, including IUPAC ambiguity codes, keeping case.
+pub fn complement(base: u8) -> u8 {
+    match base {
+        b'A' => b'T',
+        b'T' | b'U' => b'A',
+        b'G' => b'C',
+        b'C' => b'G',
+        b'R' => b'Y',
+        b'Y' => b'R',
+        b'K' => b'M',
+        b'M' => b'K',
+        b'B' => b'V',
+        b'V' => b'B',
+        b'D' => b'H',
+        b'H' => b'D',
+        b'a' => b't',
+        b't' | b'u' => b'a',
+        b'g' => b'c',
+        b'c' => b'g',
+        b'r' => b'y',
+        b'y' => b'r',
+        b'k' => b'm',
+        b'm' => b'k',
+        b'b' => b'v',
+        b'v' => b'b',
+        b'd' => b'h',
+        b'h' => b'd',
+        other => other,
+    }
+}
+
+pub fn reverse_complement(sequence: &[u8]) -> Vec<u8> {
+    sequence.iter().rev().map(|&b| complement(b)).collect()
+}
+
+#[cfg(test)]
+mod test_fasta {
+    use super::*;
+
+    #[test]
+    fn test_read_write_fasta() {
+        let fasta = ">chr1 first chromosome\nACGT\nAC\n\n>chr2\nnnRY\n";
+        let records = FastaReader::new(fasta.as_bytes())
+            .collect::<Result<Vec<_>>>()
+            .unwrap();
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0].description.as_deref(), Some("first chromosome"));
+        assert_eq!(records[0].sequence, b"ACGTAC");
+        assert_eq!(reverse_complement(&records[1].sequence), b"RYnn");
+
+        let mut out = Vec::new();
+        write_fasta(&mut out, &records[0], 4).unwrap();
+        assert_eq!(out, b">chr1 first chromosome\nACGT\nAC\n");
+    }
+
+    #[test]
+    fn test_in_memory_source() {
+        let sequences = HashMap::from([("chr1".to_string(), b"ACGTACGT".to_vec())]);
+        assert_eq!(sequences.fetch("chr1", 2, 4).unwrap(), b"CGT");
+        assert!(sequences.fetch("chr1", 5, 9).is_err());
+        assert!(sequences.fetch("chr2", 1, 1).is_err());
+    }
+
+    #[test]
+    fn test_sequence_before_header() {
+        let err = FastaReader::new("ACGT\n".as_bytes()).next().unwrap();
+        assert!(err.unwrap_err().to_string().starts_with("line 1:"));
+    }
+}
@@ -0,0 +1,32 @@
+//! Generates the `genomebase_genome.v1` messages with prost-build. The proto file is parsed in
+//! Rust by protobuf-parse, so building needs no `protoc`.
+
+use std::io::Result;
+use std::path::Path;
+
+use prost::Message as _;
+use protobuf::Message as _;
+
+const PROTO_ROOT: &str = "../../proto-def/genome";
+const GENOME_PROTO: &str = "genomebase_genome/v1/genome.proto";
+
+fn main() -> Result<()> {
+    let root = Path::new(PROTO_ROOT);
+    let proto = root.join(GENOME_PROTO);
+    println!("cargo:rerun-if-changed={}", proto.display());
+
+    let parsed = protobuf_parse::Parser::new()
+        .pure()
+        .include(root)
+        .input(&proto)
+        .parse_and_typecheck()
+        .map_err(std::io::Error::other)?;
+    let mut descriptors = prost_types::FileDescriptorSet::default();
+    for file in parsed.file_descriptors {
+        let bytes = file.write_to_bytes().map_err(std::io::Error::other)?;
+        descriptors
+            .file
+            .push(prost_types::FileDescriptorProto::decode(bytes.as_slice())?);
+    }
+    prost_build::Config::new().compile_fds(descriptors)
+}
@@ -0,0 +1,346 @@
+//! Genome assemblies: taxon, build and the sequences they are made of, with the names each
+//! sequence goes by in UCSC, Ensembl, GenBank and RefSeq.
+
+use std::collections::{BTreeMap, HashMap};
+use std::io::BufRead;
+
+use anyhow::{anyhow, bail, Result};
+use common::{Seqid, SeqidAliases, Topology};
+use fasta::index::FastaIndex;
+use gff::directive::{GenomeBuild, SequenceRegion, Species};
+
+use crate::proto;
+
+/// Value of missing fields in NCBI assembly reports.
+const NCBI_MISSING: &str = "na";
+const NCBI_SOURCE: &str = "NCBI";
+/// Molecule types of NCBI assembly reports whose sequences are circular.
+const CIRCULAR_MOLECULES: [&str; 4] = ["Mitochondrion", "Chloroplast", "Plastid", "Apicoplast"];
+
+/// Naming conventions for sequences: `1` in Ensembl, `CM000663.2` in GenBank, `NC_000001.11` in
+/// RefSeq and `chr1` in UCSC.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum NamingStyle {
+    Ensembl,
+    GenBank,
+    RefSeq,
+    Ucsc,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct AssemblySequence {
+    pub seqid: Seqid,
+    pub length: u64,
+    pub topology: Topology,
+    /// Names of the sequence by convention, when known.
+    pub names: BTreeMap<NamingStyle, Seqid>,
+}
+
+impl AssemblySequence {
+    pub fn new(seqid: Seqid, length: u64) -> Self {
+        Self {
+            seqid,
+            length,
+            topology: Topology::Linear,
+            names: BTreeMap::new(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct GenomeAssembly {
+    pub taxon_id: Option<u32>,
+    pub build: Option<GenomeBuild>,
+    /// GenBank or RefSeq assembly accession, such as `GCF_000001405.40`.
+    pub accession: Option<String>,
+    pub sequences: Vec<AssemblySequence>,
+    aliases: SeqidAliases,
+}
+
+impl GenomeAssembly {
+    /// Adds a sequence, with its seqid and conventional names as aliases.
+    pub fn add_sequence(&mut self, sequence: AssemblySequence) -> Result<()> {
+        if self.sequence(&sequence.seqid).is_some() {
+            bail!("duplicate sequence: {}", sequence.seqid);
+        }
+        let mut names = vec![sequence.seqid.clone()];
+        names.extend(sequence.names.values().cloned());
+        self.aliases.insert(&names).map_err(|e| anyhow!(e))?;
+        self.sequences.push(sequence);
+        Ok(())
+    }
+
+    /// The sequence with this seqid or one of its aliases.
+    pub fn sequence(&self, seqid: &Seqid) -> Option<&AssemblySequence> {
+        let seqid = self.aliases.canonical(seqid)?;
+        self.sequences.iter().find(|s| s.seqid == *seqid)
+    }
+
+    pub fn aliases(&self) -> &SeqidAliases {
+        &self.aliases
+    }
+
+    /// The name of a sequence in another naming convention.
+    pub fn rename(&self, seqid: &Seqid, style: NamingStyle) -> Option<&Seqid> {
+        self.sequence(seqid)?.names.get(&style)
+    }
+
+    pub fn total_length(&self) -> u64 {
+        self.sequences.iter().map(|s| s.length).sum()
+    }
+
+    /// Sets the topology of sequences, such as those read from `Is_circular` attributes.
+    pub fn set_topologies(&mut self, topologies: &BTreeMap<Seqid, Topology>) {
+        for (seqid, topology) in topologies {
+            let Some(canonical) = self.aliases.canonical(seqid) else {
+                continue;
+            };
+            if let Some(sequence) = self.sequences.iter_mut().find(|s| s.seqid == *canonical) {
+                sequence.topology = *topology;
+            }
+        }
+    }
+
+    pub fn sequence_regions(&self) -> Vec<SequenceRegion> {
+        self.sequences
+            .iter()
+            .map(|s| SequenceRegion::new(s.seqid.clone(), 1, s.length))
+            .collect()
+    }
+
+    /// Metadata identifying the assembly in service requests.
+    pub fn request_meta(&self) -> proto::RequestMeta {
+        proto::RequestMeta {
+            taxonomy_id: self.taxon_id.map(|t| t.to_string()).unwrap_or_default(),
+            genome_version: self
+                .build
+                .as_ref()
+                .map(|b| b.name.clone())
+                .unwrap_or_default(),
+        }
+    }
+
+    /// Reads the `##species`, `##genome-build` and `##sequence-region` directives of a GFF3
+    /// header.
+    pub fn from_directives(directives: &[String]) -> Result<Self> {
+        let mut assembly = Self::default();
+        for directive in directives {
+            let error = |e: String| anyhow!("{}: {}", directive, e);
+            match directive.split_whitespace().next() {
+                Some("##species") => {
+                    let species: Species = directive.parse().map_err(error)?;
+                    assembly.taxon_id = Some(species.taxon_id);
+                }
+                Some("##genome-build") => {
+                    let build = directive
+                        .parse::<GenomeBuild>()
+                        .map_err(|e| error(e.to_string()))?;
+                    assembly.build = Some(build);
+                }
+                Some("##sequence-region") => {
+                    let region: SequenceRegion = directive.parse().map_err(error)?;
+                    assembly.add_sequence(AssemblySequence::new(region.seqid, region.end))?;
+                }
+                _ => {}
+            }
+        }
+        Ok(assembly)
+    }
+
+    /// Reads sequence names and lengths from a FASTA index.
+    pub fn from_fai(index: &FastaIndex) -> Result<Self> {
+        let mut assembly = Self::default();
+        for entry in index.entries() {
+            let seqid = entry.name.parse().map_err(|e: String| anyhow!(e))?;
+            assembly.add_sequence(AssemblySequence::new(seqid, entry.length))?;
+        }
+        Ok(assembly)
+    }
+
+    /// Reads an NCBI `assembly_report.txt`, naming sequences in the given style when they have
+    /// a name in it and by their `Sequence-Name` otherwise. Mitochondrial and plastid
+    /// sequences are circular.
+    pub fn from_ncbi_report<R: BufRead>(reader: R, style: NamingStyle) -> Result<Self> {
+        let mut assembly = Self::default();
+        let mut columns: HashMap<String, usize> = HashMap::new();
+        for (i, line) in reader.lines().enumerate() {
+            let line = line?;
+            let error = |e: String| anyhow!("line {}: {}", i + 1, e);
+            if let Some(comment) = line.strip_prefix('#') {
+                let comment = comment.trim();
+                if comment.starts_with("Sequence-Name") {
+                    columns = comment
+                        .split('\t')
+                        .enumerate()
+                        .map(|(j, name)| (name.to_string(), j))
+                        .collect();
+                    continue;
+                }
+                let Some((key, value)) = comment.split_once(':') else {
+                    continue;
+                };
+                let value = value.trim();
+                match key.trim() {
+                    "Assembly name" => {
+                        assembly.build =
+                            Some(GenomeBuild::new(value.to_string(), NCBI_SOURCE.to_string()));
+                    }
+                    "Taxid" => {
+                        assembly.taxon_id = Some(
+                            value
+                                .parse()
+                                .map_err(|e| error(format!("invalid taxon ID: {}", e)))?,
+                        )
+                    }
+                    "RefSeq assembly accession" => {
+                        assembly.accession = Some(value.to_string());
+                    }
+                    "GenBank assembly accession" if assembly.accession.is_none() => {
+                        assembly.accession = Some(value.to_string());
+                    }
+                    _ => {}
+                }
+                continue;
+            }
+            if line.trim().is_empty() {
+                continue;
+            }
+            if columns.is_empty() {
+                return Err(error("sequence before the column header".to_string()));
+            }
+
+            let fields: Vec<&str> = line.split('\t').collect();
+            let field = |name: &str| {
+                columns
+                    .get(name)
+                    .and_then(|&j| fields.get(j))
+                    .map(|f| f.trim())
+                    .filter(|f| !f.is_empty() && *f != NCBI_MISSING)
+            };
+            let seqid = |value: &str| value.parse::<Seqid>().map_err(error);
+
+            let mut names = BTreeMap::new();
+            for (style, column) in [
+                (NamingStyle::Ensembl, "Sequence-Name"),
+                (NamingStyle::GenBank, "GenBank-Accn"),
+                (NamingStyle::RefSeq, "RefSeq-Accn"),
+                (NamingStyle::Ucsc, "UCSC-style-name"),
+            ] {
+                if let Some(value) = field(column) {
+                    names.insert(style, seqid(value)?);
+                }
+            }
+            let name =
+                field("Sequence-Name").ok_or_else(|| error("missing Sequence-Name".to_string()))?;
+            let length = field("Sequence-Length")
+                .ok_or_else(|| error("missing Sequence-Length".to_string()))?
+                .parse()
+                .map_err(|e| error(format!("invalid length: {}", e)))?;
+            let topology = match field("Assigned-Molecule-Location/Type") {
+                Some(molecule) if CIRCULAR_MOLECULES.contains(&molecule) => Topology::Circular,
+                _ => Topology::Linear,
+            };
+            let seqid = match names.get(&style) {
+                Some(seqid) => seqid.clone(),
+                None => seqid(name)?,
+            };
+            assembly.add_sequence(AssemblySequence {
+                seqid,
+                length,
+                topology,
+                names,
+            })?;
+        }
+        Ok(assembly)
+    }
+}
+
+#[cfg(test)]
+mod test_assembly {
+    use super::*;
+
+    fn seqid(s: &str) -> Seqid {
+        s.parse().unwrap()
+    }
+
+    const REPORT: &str = "# Assembly name:  GRCh38.p14\n\
+        # Organism name:  Homo sapiens (human)\n\
+        # Taxid:          9606\n\
+        # GenBank assembly accession: GCA_000001405.29\n\
+        # RefSeq assembly accession: GCF_000001405.40\n\
+        #\n\
+        # Sequence-Name\tSequence-Role\tAssigned-Molecule\tAssigned-Molecule-Location/Type\t\
+        GenBank-Accn\tRelationship\tRefSeq-Accn\tAssembly-Unit\tSequence-Length\tUCSC-style-name\n\
+        1\tassembled-molecule\t1\tChromosome\tCM000663.2\t=\tNC_000001.11\tPrimary Assembly\t\
+        248956422\tchr1\n\
+        MT\tassembled-molecule\tMT\tMitochondrion\tJ01415.2\t=\tNC_012920.1\tnon-nuclear\t\
+        16569\tchrM\n\
+        HSCHRUN_RANDOM_CTG29\tunplaced-scaffold\tna\tna\tKI270302.1\t=\tNT_187396.1\t\
+        Primary Assembly\t2274\tchrUn_KI270302v1\n";
+
+    #[test]
+    fn test_ncbi_report() {
+        let assembly =
+            GenomeAssembly::from_ncbi_report(REPORT.as_bytes(), NamingStyle::Ucsc).unwrap();
+        assert_eq!(assembly.taxon_id, Some(9606));
+        assert_eq!(assembly.build.as_ref().unwrap().name, "GRCh38.p14");
+        assert_eq!(assembly.accession.as_deref(), Some("GCF_000001405.40"));
+        assert_eq!(assembly.sequences.len(), 3);
+        assert_eq!(assembly.total_length(), 248956422 + 16569 + 2274);
+
+        let mito = assembly.sequence(&seqid("NC_012920.1")).unwrap();
+        assert_eq!(mito.seqid, "chrM");
+        assert_eq!(mito.topology, Topology::Circular);
+        assert_eq!(
+            assembly.rename(&seqid("chr1"), NamingStyle::RefSeq),
+            Some(&seqid("NC_000001.11"))
+        );
+        assert_eq!(
+            assembly.rename(&seqid("NT_187396.1"), NamingStyle::Ensembl),
+            Some(&seqid("HSCHRUN_RANDOM_CTG29"))
+        );
+        assert!(assembly
+            .aliases()
+            .same_sequence(&seqid("1"), &seqid("CM000663.2")));
+
+        let meta = assembly.request_meta();
+        assert_eq!(
+            (meta.taxonomy_id.as_str(), meta.genome_version.as_str()),
+            ("9606", "GRCh38.p14")
+        );
+    }
+
+    #[test]
+    fn test_directives_and_fai() {
+        let directives: Vec<String> = [
+            "##gff-version 3",
+            "##species http://www.ncbi.nlm.nih.gov/Taxonomy/Browser/wwwtax.cgi?id=3702",
+            "##genome-build TAIR 10",
+            "##sequence-region Chr1 1 30427671",
+            "##sequence-region ChrC 1 154478",
+        ]
+        .iter()
+        .map(|d| d.to_string())
+        .collect();
+        let mut assembly = GenomeAssembly::from_directives(&directives).unwrap();
+        let build = assembly.build.as_ref().unwrap();
+        assert_eq!((build.name.as_str(), build.source.as_str()), ("10", "TAIR"));
+        assert_eq!(assembly.request_meta().genome_version, "10");
+        assert_eq!(assembly.sequence(&seqid("Chr1")).unwrap().length, 30427671);
+
+        assembly.set_topologies(&BTreeMap::from([(seqid("ChrC"), Topology::Circular)]));
+        assert_eq!(
+            assembly.sequence(&seqid("ChrC")).unwrap().topology,
+            Topology::Circular
+        );
+        assert_eq!(
+            assembly.sequence_regions()[1].to_string(),
+            "##sequence-region ChrC 1 154478"
+        );
+
+        let index = FastaIndex::read("Chr1\t30427671\t6\t79\t80\n".as_bytes()).unwrap();
+        let assembly = GenomeAssembly::from_fai(&index).unwrap();
+        assert_eq!(assembly.sequences[0].seqid, "Chr1");
+        assert!(assembly.sequence(&seqid("chr1")).is_none());
+    }
+}
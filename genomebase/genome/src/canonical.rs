@@ -0,0 +1,218 @@
+//! Selection of the primary transcript of each gene, for reproducible primary proteomes.
+
+use std::collections::HashMap;
+
+use gff::attributes::Tag;
+
+use crate::genes::{Gene, GeneTranscript};
+
+/// Attribute listing transcript tags in Ensembl and GENCODE annotations.
+pub const TAG_ATTRIBUTE: &str = "tag";
+pub const MANE_SELECT_TAG: &str = "MANE_Select";
+pub const ENSEMBL_CANONICAL_TAG: &str = "Ensembl_canonical";
+
+/// Scores transcripts of a gene; higher scores are preferred.
+pub trait SelectionStrategy {
+    fn score(&self, gene: &Gene, transcript: &GeneTranscript) -> f64;
+}
+
+pub struct LongestCds;
+
+impl SelectionStrategy for LongestCds {
+    fn score(&self, _: &Gene, transcript: &GeneTranscript) -> f64 {
+        transcript.cds_length() as f64
+    }
+}
+
+pub struct LongestTranscript;
+
+impl SelectionStrategy for LongestTranscript {
+    fn score(&self, _: &Gene, transcript: &GeneTranscript) -> f64 {
+        transcript.spliced_length() as f64
+    }
+}
+
+/// Prefers transcripts carrying one of the tags in the `tag` attribute, earlier tags first.
+pub struct Tagged {
+    pub tags: Vec<String>,
+}
+
+impl Tagged {
+    pub fn new(tags: &[&str]) -> Self {
+        Self {
+            tags: tags.iter().map(|t| t.to_string()).collect(),
+        }
+    }
+}
+
+impl Default for Tagged {
+    fn default() -> Self {
+        Self::new(&[MANE_SELECT_TAG, ENSEMBL_CANONICAL_TAG])
+    }
+}
+
+impl SelectionStrategy for Tagged {
+    fn score(&self, _: &Gene, transcript: &GeneTranscript) -> f64 {
+        let Some(values) = transcript.attributes.get(&Tag::from(TAG_ATTRIBUTE)) else {
+            return 0.0;
+        };
+        self.tags
+            .iter()
+            .position(|tag| values.iter().any(|v| v == tag))
+            .map_or(0.0, |i| (self.tags.len() - i) as f64)
+    }
+}
+
+/// Prefers the most expressed transcript, from levels such as TPM by transcript ID.
+pub struct Expression {
+    pub levels: HashMap<String, f64>,
+}
+
+impl SelectionStrategy for Expression {
+    fn score(&self, _: &Gene, transcript: &GeneTranscript) -> f64 {
+        self.levels.get(&transcript.id).copied().unwrap_or(0.0)
+    }
+}
+
+/// Primary transcripts chosen by curators, by gene ID.
+pub struct CuratorOverride {
+    pub primary: HashMap<String, String>,
+}
+
+impl SelectionStrategy for CuratorOverride {
+    fn score(&self, gene: &Gene, transcript: &GeneTranscript) -> f64 {
+        match self.primary.get(&gene.id) == Some(&transcript.id) {
+            true => 1.0,
+            false => 0.0,
+        }
+    }
+}
+
+/// Applies strategies in order, each breaking the ties of the previous ones. Remaining ties
+/// go to the lowest transcript ID, so the selection does not depend on input order.
+pub struct TranscriptSelector {
+    strategies: Vec<Box<dyn SelectionStrategy>>,
+}
+
+impl Default for TranscriptSelector {
+    /// Tagged transcripts, then the longest CDS, then the longest transcript.
+    fn default() -> Self {
+        Self::new()
+            .then(Tagged::default())
+            .then(LongestCds)
+            .then(LongestTranscript)
+    }
+}
+
+impl TranscriptSelector {
+    pub fn new() -> Self {
+        Self {
+            strategies: Vec::new(),
+        }
+    }
+
+    pub fn then<S: SelectionStrategy + 'static>(mut self, strategy: S) -> Self {
+        self.strategies.push(Box::new(strategy));
+        self
+    }
+
+    pub fn select<'a>(&self, gene: &'a Gene) -> Option<&'a GeneTranscript> {
+        let scores = |transcript: &GeneTranscript| -> Vec<f64> {
+            self.strategies
+                .iter()
+                .map(|s| s.score(gene, transcript))
+                .collect()
+        };
+        gene.transcripts
+            .iter()
+            .map(|t| (scores(t), t))
+            .max_by(|(a_scores, a), (b_scores, b)| {
+                a_scores
+                    .iter()
+                    .zip(b_scores)
+                    .map(|(a, b)| a.total_cmp(b))
+                    .find(|o| o.is_ne())
+                    .unwrap_or_else(|| b.id.cmp(&a.id))
+            })
+            .map(|(_, t)| t)
+    }
+
+    /// Sets `is_primary` on the selected transcript and clears it on the others.
+    pub fn mark_primary(&self, gene: &mut Gene) {
+        let primary = self.select(gene).map(|t| t.id.clone());
+        for transcript in &mut gene.transcripts {
+            transcript.is_primary = Some(&transcript.id) == primary.as_ref();
+        }
+    }
+}
+
+#[cfg(test)]
+mod test_canonical {
+    use gff::gene_model::assemble_gene_models;
+    use gff::parse_line;
+
+    use super::*;
+
+    fn gene() -> Gene {
+        let records = [
+            "1\t.\tgene\t1\t1000\t.\t+\t.\tID=gene1;Name=ABC1",
+            "1\t.\tmRNA\t1\t1000\t.\t+\t.\tID=tx.b;Parent=gene1;product=ABC transporter",
+            "1\t.\texon\t1\t1000\t.\t+\t.\tParent=tx.b",
+            "1\t.\tCDS\t101\t400\t.\t+\t0\tParent=tx.b",
+            "1\t.\tmRNA\t1\t600\t.\t+\t.\tID=tx.a;Parent=gene1",
+            "1\t.\texon\t1\t600\t.\t+\t.\tParent=tx.a",
+            "1\t.\tCDS\t101\t400\t.\t+\t0\tParent=tx.a",
+            "1\t.\tmRNA\t1\t300\t.\t+\t.\tID=tx.c;Parent=gene1;tag=basic,Ensembl_canonical",
+            "1\t.\texon\t1\t300\t.\t+\t.\tParent=tx.c",
+            "1\t.\tCDS\t101\t250\t.\t+\t0\tParent=tx.c",
+        ];
+        let models =
+            assemble_gene_models(records.iter().map(|line| parse_line(line).unwrap())).unwrap();
+        Gene::from(&models[0])
+    }
+
+    fn selected(selector: &TranscriptSelector, gene: &Gene) -> String {
+        selector.select(gene).unwrap().id.clone()
+    }
+
+    #[test]
+    fn test_strategies() {
+        let gene = gene();
+        assert_eq!(gene.nomenclature().unwrap().product, "ABC transporter");
+
+        assert_eq!(selected(&TranscriptSelector::default(), &gene), "tx.c");
+        let longest_cds = TranscriptSelector::new().then(LongestCds);
+        assert_eq!(selected(&longest_cds, &gene), "tx.a");
+        let longest = TranscriptSelector::new()
+            .then(LongestCds)
+            .then(LongestTranscript);
+        assert_eq!(selected(&longest, &gene), "tx.b");
+
+        let expression = Expression {
+            levels: HashMap::from([("tx.a".to_string(), 2.0), ("tx.c".to_string(), 8.5)]),
+        };
+        assert_eq!(
+            selected(&TranscriptSelector::new().then(expression), &gene),
+            "tx.c"
+        );
+    }
+
+    #[test]
+    fn test_curator_override() {
+        let mut gene = gene();
+        let curated = CuratorOverride {
+            primary: HashMap::from([("gene1".to_string(), "tx.b".to_string())]),
+        };
+        let selector = TranscriptSelector::new()
+            .then(curated)
+            .then(Tagged::default());
+        selector.mark_primary(&mut gene);
+        let primary: Vec<&str> = gene
+            .transcripts
+            .iter()
+            .filter(|t| t.is_primary)
+            .map(|t| t.id.as_str())
+            .collect();
+        assert_eq!(primary, vec!["tx.b"]);
+    }
+}
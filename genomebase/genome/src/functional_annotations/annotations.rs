@@ -0,0 +1,232 @@
+//! Functional annotations assigned to transcripts, as carried by the proto `Transcript`.
+
+use anyhow::{anyhow, Result};
+use common::User;
+use serde::{Deserialize, Serialize};
+
+use super::go_term::{EvidenceCode, GoTermNamespace};
+use crate::proto;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Analysis {
+    Pfam,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Domain {
+    pub accession: String,
+    pub description: String,
+    /// 1-based protein coordinates.
+    pub start: u32,
+    pub end: u32,
+    pub analysis: Option<Analysis>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Kog {
+    pub accession: String,
+    pub description: String,
+    pub category: String,
+}
+
+/// A KEGG orthology, pathway or reaction.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct KeggEntry {
+    pub id: String,
+    pub name: String,
+}
+
+impl KeggEntry {
+    pub fn new(id: String, name: String) -> Self {
+        Self { id, name }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Kegg {
+    pub orthology: Option<KeggEntry>,
+    pub related_pathways: Vec<KeggEntry>,
+    pub related_reactions: Vec<KeggEntry>,
+}
+
+/// A GO term assigned to a transcript, with the evidence for it.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GoAnnotation {
+    pub accession: String,
+    pub description: String,
+    pub namespace: GoTermNamespace,
+    pub evidence_code: EvidenceCode,
+    pub assigned_by: Option<User>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FunctionalAnnotations {
+    pub kog: Option<Kog>,
+    pub go_terms: Vec<GoAnnotation>,
+    pub domains: Vec<Domain>,
+    pub kegg: Option<Kegg>,
+}
+
+pub(crate) fn user_from_proto(author: proto::Author) -> User {
+    User {
+        name: author.name,
+        email: author.email,
+        orc_id: author.orc_id,
+    }
+}
+
+pub(crate) fn user_to_proto(user: User) -> proto::Author {
+    proto::Author {
+        name: user.name,
+        email: user.email,
+        orc_id: user.orc_id,
+    }
+}
+
+/// The namespace may be a GO namespace or its abbreviation, and the evidence code must be
+/// one of GO's codes.
+impl TryFrom<proto::GoTerm> for GoAnnotation {
+    type Error = anyhow::Error;
+
+    fn try_from(term: proto::GoTerm) -> Result<Self> {
+        let error = |e: String| anyhow!("{}: {}", term.accession, e);
+        Ok(Self {
+            namespace: term.namespace.parse().map_err(error)?,
+            evidence_code: term.evidence_code.parse().map_err(error)?,
+            accession: term.accession,
+            description: term.description,
+            assigned_by: term.assigned_by.map(user_from_proto),
+        })
+    }
+}
+
+impl TryFrom<proto::Domain> for Domain {
+    type Error = anyhow::Error;
+
+    fn try_from(domain: proto::Domain) -> Result<Self> {
+        let position = |value: i32| {
+            u32::try_from(value)
+                .map_err(|_| anyhow!("{}: invalid position {}", domain.accession, value))
+        };
+        let analysis = match proto::Analysis::try_from(domain.anlysis) {
+            Ok(proto::Analysis::Unspecified) => None,
+            Ok(proto::Analysis::Pfam) => Some(Analysis::Pfam),
+            Err(_) => return Err(anyhow!("unsupported analysis: {}", domain.anlysis)),
+        };
+        Ok(Self {
+            start: position(domain.start)?,
+            end: position(domain.end)?,
+            accession: domain.accession,
+            description: domain.description,
+            analysis,
+        })
+    }
+}
+
+impl TryFrom<Domain> for proto::Domain {
+    type Error = anyhow::Error;
+
+    fn try_from(domain: Domain) -> Result<Self> {
+        let position = |value: u32| {
+            i32::try_from(value)
+                .map_err(|_| anyhow!("{}: invalid position {}", domain.accession, value))
+        };
+        let analysis = match domain.analysis {
+            None => proto::Analysis::Unspecified,
+            Some(Analysis::Pfam) => proto::Analysis::Pfam,
+        };
+        Ok(Self {
+            start: position(domain.start)?,
+            end: position(domain.end)?,
+            accession: domain.accession,
+            description: domain.description,
+            anlysis: analysis as i32,
+        })
+    }
+}
+
+impl FunctionalAnnotations {
+    /// Reads the annotation fields of a transcript message.
+    pub fn from_proto(transcript: &proto::Transcript) -> Result<Self> {
+        Ok(Self {
+            kog: transcript.kog.clone().map(|kog| Kog {
+                accession: kog.accession,
+                description: kog.description,
+                category: kog.category,
+            }),
+            go_terms: transcript
+                .go_terms
+                .iter()
+                .cloned()
+                .map(GoAnnotation::try_from)
+                .collect::<Result<_>>()?,
+            domains: transcript
+                .domains
+                .iter()
+                .cloned()
+                .map(Domain::try_from)
+                .collect::<Result<_>>()?,
+            kegg: transcript.kegg.clone().map(|kegg| Kegg {
+                orthology: kegg.orthology.map(|e| KeggEntry::new(e.id, e.name)),
+                related_pathways: kegg
+                    .related_pathways
+                    .into_iter()
+                    .map(|e| KeggEntry::new(e.id, e.name))
+                    .collect(),
+                related_reactions: kegg
+                    .related_reactions
+                    .into_iter()
+                    .map(|e| KeggEntry::new(e.id, e.name))
+                    .collect(),
+            }),
+        })
+    }
+
+    /// Fills the annotation fields of a transcript message.
+    pub fn into_proto(self, transcript: &mut proto::Transcript) -> Result<()> {
+        transcript.kog = self.kog.map(|kog| proto::Kog {
+            accession: kog.accession,
+            description: kog.description,
+            category: kog.category,
+        });
+        transcript.go_terms = self
+            .go_terms
+            .into_iter()
+            .map(|term| proto::GoTerm {
+                accession: term.accession,
+                description: term.description,
+                namespace: term.namespace.to_string(),
+                evidence_code: term.evidence_code.to_string(),
+                assigned_by: term.assigned_by.map(user_to_proto),
+            })
+            .collect();
+        transcript.domains = self
+            .domains
+            .into_iter()
+            .map(proto::Domain::try_from)
+            .collect::<Result<_>>()?;
+        transcript.kegg = self.kegg.map(|kegg| proto::Kegg {
+            orthology: kegg.orthology.map(|e| proto::KeggOrthology {
+                id: e.id,
+                name: e.name,
+            }),
+            related_pathways: kegg
+                .related_pathways
+                .into_iter()
+                .map(|e| proto::KeggPathway {
+                    id: e.id,
+                    name: e.name,
+                })
+                .collect(),
+            related_reactions: kegg
+                .related_reactions
+                .into_iter()
+                .map(|e| proto::KeggReaction {
+                    id: e.id,
+                    name: e.name,
+                })
+                .collect(),
+        });
+        Ok(())
+    }
+}
@@ -0,0 +1,458 @@
+//! Over-representation analysis of GO terms in a study gene list against a background, with
+//! the classic, parent-child and elim methods of topGO, a simplified weight method, and
+//! multiple testing correction per namespace.
+
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+
+use serde::{Deserialize, Serialize};
+
+use super::go_term::{GoTermID, GoTermNamespace};
+use super::ontology::GoOntology;
+use super::propagation::GeneAnnotations;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum EnrichmentTest {
+    /// Upper tail of the hypergeometric distribution, the one-sided Fisher exact test.
+    #[default]
+    Hypergeometric,
+    /// Two-sided Fisher exact test, summing tables at most as likely as the observed one.
+    FisherTwoSided,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Correction {
+    None,
+    Bonferroni,
+    #[default]
+    BenjaminiHochberg,
+}
+
+/// How the annotations of related terms are taken into account.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub enum EnrichmentMethod {
+    /// Each term tested on its own.
+    #[default]
+    Classic,
+    /// Each term tested against the genes of its parents (Grossmann et al. 2007, union).
+    ParentChild,
+    /// Terms tested from the most specific up; the genes of terms below the cutoff are removed
+    /// from their ancestors (Alexa et al. 2006).
+    Elim { cutoff: f64 },
+    /// Terms tested from the most specific up; genes of children more significant than the
+    /// term are down-weighted by the ratio of p-values, and the weighted counts are rounded.
+    /// A single pass, unlike topGO's weight method: children are never revisited, so its
+    /// p-values differ.
+    WeightApprox,
+}
+
+/// Settings of an enrichment analysis. Terms with fewer or more annotated background genes
+/// than the size limits are not tested.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Enrichment {
+    pub test: EnrichmentTest,
+    pub method: EnrichmentMethod,
+    pub correction: Correction,
+    pub min_size: usize,
+    pub max_size: Option<usize>,
+}
+
+impl Default for Enrichment {
+    fn default() -> Self {
+        Self {
+            test: EnrichmentTest::default(),
+            method: EnrichmentMethod::default(),
+            correction: Correction::default(),
+            min_size: 1,
+            max_size: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TermEnrichment {
+    pub go_id: GoTermID,
+    pub namespace: GoTermNamespace,
+    /// Background genes annotated to the term.
+    pub annotated: usize,
+    /// Study genes annotated to the term.
+    pub significant: usize,
+    pub expected: f64,
+    pub p_value: f64,
+    pub adjusted_p_value: f64,
+}
+
+/// Genes of the terms of one namespace, restricted to the background.
+struct Namespace<'a> {
+    term_genes: BTreeMap<&'a str, BTreeSet<&'a str>>,
+    universe: usize,
+    study: HashSet<&'a str>,
+}
+
+impl Enrichment {
+    /// Tests the terms of each namespace, sorted by p-value. The annotations are propagated
+    /// here; an empty background stands for all annotated genes.
+    pub fn run(
+        &self,
+        ontology: &GoOntology,
+        annotations: &GeneAnnotations,
+        study: &[&str],
+        background: &[&str],
+    ) -> BTreeMap<GoTermNamespace, Vec<TermEnrichment>> {
+        let propagated = annotations.propagate(ontology);
+        let background: HashSet<&str> = background.iter().copied().collect();
+        let mut namespaces: HashMap<GoTermNamespace, BTreeMap<&str, BTreeSet<&str>>> =
+            HashMap::new();
+        for (go_id, genes) in propagated.term_genes() {
+            let Some(term) = ontology.term(go_id) else {
+                continue;
+            };
+            let genes: BTreeSet<&str> = genes
+                .into_iter()
+                .filter(|gene| background.is_empty() || background.contains(gene))
+                .collect();
+            if !genes.is_empty() {
+                namespaces
+                    .entry(term.namespace())
+                    .or_default()
+                    .insert(term.id(), genes);
+            }
+        }
+
+        let mut results = BTreeMap::new();
+        for (namespace, term_genes) in namespaces {
+            let universe: HashSet<&str> = term_genes.values().flatten().copied().collect();
+            let study = study
+                .iter()
+                .copied()
+                .filter(|gene| universe.contains(gene))
+                .collect();
+            let genes = Namespace {
+                universe: universe.len(),
+                term_genes,
+                study,
+            };
+            let mut terms = self.test_terms(ontology, &genes, namespace);
+            let p_values: Vec<f64> = terms.iter().map(|t| t.p_value).collect();
+            let adjusted = match self.correction {
+                Correction::None => p_values,
+                Correction::Bonferroni => bonferroni(&p_values),
+                Correction::BenjaminiHochberg => benjamini_hochberg(&p_values),
+            };
+            for (term, adjusted) in terms.iter_mut().zip(adjusted) {
+                term.adjusted_p_value = adjusted;
+            }
+            terms.sort_by(|a, b| a.p_value.total_cmp(&b.p_value));
+            results.insert(namespace, terms);
+        }
+        results
+    }
+
+    fn in_size_range(&self, size: usize) -> bool {
+        size >= self.min_size && self.max_size.is_none_or(|max| size <= max)
+    }
+
+    fn test_terms(
+        &self,
+        ontology: &GoOntology,
+        genes: &Namespace,
+        namespace: GoTermNamespace,
+    ) -> Vec<TermEnrichment> {
+        let table = LnFactorials::new(genes.universe);
+        let test = |k: usize, big_k: usize, n: usize, big_n: usize| match self.test {
+            EnrichmentTest::Hypergeometric => table.hypergeometric_upper(k, big_k, n, big_n),
+            EnrichmentTest::FisherTwoSided => table.fisher_two_sided(k, big_k, n, big_n),
+        };
+        let n = genes.study.len();
+        let hits = |term_genes: &BTreeSet<&str>| {
+            term_genes
+                .iter()
+                .filter(|g| genes.study.contains(*g))
+                .count()
+        };
+
+        // Most specific terms first, so that children are done before their parents.
+        let mut order: Vec<&str> = genes.term_genes.keys().copied().collect();
+        order.sort_by_key(|id| std::cmp::Reverse(ontology.depth(id).unwrap_or(0)));
+
+        let mut p_values: HashMap<&str, f64> = HashMap::new();
+        let mut eliminated: HashMap<&str, BTreeSet<&str>> = HashMap::new();
+        for &id in &order {
+            let term_genes = &genes.term_genes[id];
+            let classic = test(hits(term_genes), term_genes.len(), n, genes.universe);
+            let p_value = match self.method {
+                EnrichmentMethod::Classic => classic,
+                EnrichmentMethod::ParentChild => {
+                    let parents: BTreeSet<&str> = ontology
+                        .parents(id)
+                        .into_iter()
+                        .filter_map(|parent| genes.term_genes.get(parent))
+                        .flatten()
+                        .copied()
+                        .collect();
+                    // Genes reaching the term through relations to other namespaces may be
+                    // missing from its parents, and the test needs a subset of them.
+                    let within: BTreeSet<&str> =
+                        term_genes.intersection(&parents).copied().collect();
+                    if parents.is_empty() {
+                        classic
+                    } else {
+                        test(hits(&within), within.len(), hits(&parents), parents.len())
+                    }
+                }
+                EnrichmentMethod::Elim { cutoff } => {
+                    let removed = eliminated.remove(id).unwrap_or_default();
+                    let remaining: BTreeSet<&str> =
+                        term_genes.difference(&removed).copied().collect();
+                    let p_value = test(hits(&remaining), remaining.len(), n, genes.universe);
+                    if p_value < cutoff {
+                        for ancestor in ontology.ancestors(id) {
+                            eliminated
+                                .entry(ancestor)
+                                .or_default()
+                                .extend(remaining.iter().copied());
+                        }
+                    }
+                    p_value
+                }
+                EnrichmentMethod::WeightApprox => {
+                    let mut weights: HashMap<&str, f64> = HashMap::new();
+                    for child in ontology.children(id) {
+                        let Some(&child_p) = p_values.get(child) else {
+                            continue;
+                        };
+                        if child_p < classic {
+                            for gene in &genes.term_genes[child] {
+                                let weight = weights.entry(gene).or_insert(1.0);
+                                *weight = weight.min(child_p / classic);
+                            }
+                        }
+                    }
+                    let weight = |gene: &&str| weights.get(gene).copied().unwrap_or(1.0);
+                    let big_k: f64 = term_genes.iter().map(weight).sum();
+                    let k: f64 = term_genes
+                        .iter()
+                        .filter(|g| genes.study.contains(*g))
+                        .map(weight)
+                        .sum();
+                    test(
+                        k.round() as usize,
+                        big_k.round() as usize,
+                        n,
+                        genes.universe,
+                    )
+                }
+            };
+            p_values.insert(id, p_value);
+        }
+
+        genes
+            .term_genes
+            .iter()
+            .filter(|(_, term_genes)| self.in_size_range(term_genes.len()))
+            .map(|(&id, term_genes)| TermEnrichment {
+                go_id: id.to_string(),
+                namespace,
+                annotated: term_genes.len(),
+                significant: hits(term_genes),
+                expected: (n * term_genes.len()) as f64 / genes.universe as f64,
+                p_value: p_values[id],
+                adjusted_p_value: p_values[id],
+            })
+            .collect()
+    }
+}
+
+/// Natural logarithms of `0!` to `n!`.
+struct LnFactorials(Vec<f64>);
+
+impl LnFactorials {
+    fn new(n: usize) -> Self {
+        let mut values = Vec::with_capacity(n + 1);
+        values.push(0.0);
+        for i in 1..=n {
+            values.push(values[i - 1] + (i as f64).ln());
+        }
+        Self(values)
+    }
+
+    fn ln_choose(&self, n: usize, k: usize) -> f64 {
+        self.0[n] - self.0[k] - self.0[n - k]
+    }
+
+    /// Probability of drawing `k` of `big_k` marked items in `n` draws from `big_n` items.
+    fn hypergeometric(&self, k: usize, big_k: usize, n: usize, big_n: usize) -> f64 {
+        (self.ln_choose(big_k, k) + self.ln_choose(big_n - big_k, n - k) - self.ln_choose(big_n, n))
+            .exp()
+    }
+
+    /// Possible values of `k`.
+    fn support(big_k: usize, n: usize, big_n: usize) -> std::ops::RangeInclusive<usize> {
+        (n + big_k).saturating_sub(big_n)..=big_k.min(n)
+    }
+
+    fn hypergeometric_upper(&self, k: usize, big_k: usize, n: usize, big_n: usize) -> f64 {
+        let support = Self::support(big_k, n, big_n);
+        let p: f64 = (k.max(*support.start())..=*support.end())
+            .map(|x| self.hypergeometric(x, big_k, n, big_n))
+            .sum();
+        p.min(1.0)
+    }
+
+    fn fisher_two_sided(&self, k: usize, big_k: usize, n: usize, big_n: usize) -> f64 {
+        let observed = self.hypergeometric(k, big_k, n, big_n);
+        // Relative tolerance for tables as likely as the observed one.
+        let threshold = observed * (1.0 + 1e-7);
+        let p: f64 = Self::support(big_k, n, big_n)
+            .map(|x| self.hypergeometric(x, big_k, n, big_n))
+            .filter(|&p| p <= threshold)
+            .sum();
+        p.min(1.0)
+    }
+}
+
+pub fn bonferroni(p_values: &[f64]) -> Vec<f64> {
+    let m = p_values.len() as f64;
+    p_values.iter().map(|p| (p * m).min(1.0)).collect()
+}
+
+/// Benjamini-Hochberg false discovery rate adjusted p-values, in the order given.
+pub fn benjamini_hochberg(p_values: &[f64]) -> Vec<f64> {
+    let m = p_values.len();
+    let mut order: Vec<usize> = (0..m).collect();
+    order.sort_by(|&a, &b| p_values[a].total_cmp(&p_values[b]));
+    let mut adjusted = vec![0.0; m];
+    let mut min = 1.0_f64;
+    for (rank, &i) in order.iter().enumerate().rev() {
+        min = min.min(p_values[i] * m as f64 / (rank + 1) as f64);
+        adjusted[i] = min;
+    }
+    adjusted
+}
+
+#[cfg(test)]
+mod test_enrichment {
+    use super::*;
+    use crate::functional_annotations::obo::read_obo;
+    use crate::functional_annotations::ontology::Relations;
+
+    const OBO: &str = "[Term]
+id: GO:0008150
+name: biological_process
+namespace: biological_process
+
+[Term]
+id: GO:0008152
+name: metabolic process
+namespace: biological_process
+is_a: GO:0008150
+
+[Term]
+id: GO:0006096
+name: glycolytic process
+namespace: biological_process
+is_a: GO:0008152
+";
+
+    fn close(a: f64, b: f64) -> bool {
+        (a - b).abs() < 1e-9
+    }
+
+    #[test]
+    fn test_statistics() {
+        let table = LnFactorials::new(10);
+        assert!(close(table.hypergeometric_upper(2, 4, 3, 10), 1.0 / 3.0));
+        assert!(close(table.fisher_two_sided(5, 5, 5, 10), 2.0 / 252.0));
+        assert!(close(table.hypergeometric_upper(0, 4, 3, 10), 1.0));
+
+        let p_values = [0.01, 0.04, 0.03, 0.2];
+        let expected = [0.04, 0.16 / 3.0, 0.16 / 3.0, 0.2];
+        for (a, b) in benjamini_hochberg(&p_values).into_iter().zip(expected) {
+            assert!(close(a, b));
+        }
+        for (a, b) in bonferroni(&p_values)
+            .into_iter()
+            .zip([0.04, 0.16, 0.12, 0.8])
+        {
+            assert!(close(a, b));
+        }
+    }
+
+    #[test]
+    fn test_enrichment_methods() {
+        let ontology =
+            GoOntology::new(read_obo(OBO.as_bytes()).unwrap().terms, Relations::is_a()).unwrap();
+        let mut annotations = GeneAnnotations::new();
+        let genes: Vec<String> = (1..=10).map(|i| format!("g{}", i)).collect();
+        for (i, gene) in genes.iter().enumerate() {
+            let term = match i {
+                0..=3 => "GO:0006096",
+                4..=5 => "GO:0008152",
+                _ => "GO:0008150",
+            };
+            annotations.insert(gene, term, false);
+        }
+        let study = ["g1", "g2", "g3", "g4", "unannotated"];
+        let p_value = |enrichment: &Enrichment, id: &str| {
+            enrichment.run(&ontology, &annotations, &study, &[])
+                [&GoTermNamespace::BiologicalProcess]
+                .iter()
+                .find(|t| t.go_id == id)
+                .unwrap()
+                .p_value
+        };
+
+        let classic = Enrichment::default();
+        let results = classic.run(&ontology, &annotations, &study, &[]);
+        let terms = &results[&GoTermNamespace::BiologicalProcess];
+        assert_eq!(terms[0].go_id, "GO:0006096");
+        assert_eq!((terms[0].annotated, terms[0].significant), (4, 4));
+        assert!(close(terms[0].p_value, 1.0 / 210.0));
+        assert!(close(terms[0].adjusted_p_value, 3.0 / 210.0));
+        assert!(close(p_value(&classic, "GO:0008152"), 15.0 / 210.0));
+
+        let parent_child = Enrichment {
+            method: EnrichmentMethod::ParentChild,
+            ..Enrichment::default()
+        };
+        assert!(close(p_value(&parent_child, "GO:0006096"), 1.0 / 15.0));
+
+        // A term with genes its parents lack, as through a relation to another namespace.
+        let mut term_genes = BTreeMap::new();
+        term_genes.insert("GO:0008150", BTreeSet::from(["g1", "g5"]));
+        term_genes.insert("GO:0008152", BTreeSet::from(["g1", "g2", "g3"]));
+        let genes = Namespace {
+            term_genes,
+            universe: 5,
+            study: HashSet::from(["g1", "g2"]),
+        };
+        let terms = parent_child.test_terms(&ontology, &genes, GoTermNamespace::BiologicalProcess);
+        let metabolic = terms.iter().find(|t| t.go_id == "GO:0008152").unwrap();
+        assert!(close(metabolic.p_value, 0.5));
+
+        let elim = Enrichment {
+            method: EnrichmentMethod::Elim { cutoff: 0.01 },
+            ..Enrichment::default()
+        };
+        assert!(close(p_value(&elim, "GO:0008152"), 1.0));
+        // The glycolysis genes weigh 1/15 in the metabolic process term, leaving no study
+        // genes after rounding.
+        let weight = Enrichment {
+            method: EnrichmentMethod::WeightApprox,
+            ..Enrichment::default()
+        };
+        assert!(close(p_value(&weight, "GO:0006096"), 1.0 / 210.0));
+        assert!(close(p_value(&weight, "GO:0008152"), 1.0));
+
+        let filtered = Enrichment {
+            min_size: 5,
+            max_size: Some(9),
+            ..Enrichment::default()
+        };
+        let results = filtered.run(&ontology, &annotations, &study, &[]);
+        let ids: Vec<&str> = results[&GoTermNamespace::BiologicalProcess]
+            .iter()
+            .map(|t| t.go_id.as_str())
+            .collect();
+        assert_eq!(ids, ["GO:0008152"]);
+    }
+}
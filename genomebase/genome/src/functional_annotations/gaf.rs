@@ -0,0 +1,707 @@
+//! GO associations between gene products and terms, read from and written to GAF 2.1/2.2,
+//! GPAD 1.1/2.0 and GPI 1.2/2.0 files.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::io::{self, BufRead, Write};
+
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+
+use super::go_term::{EvidenceCode, GoTermID, GoTermNamespace};
+use super::ontology::GoOntology;
+
+const GAF_COLUMNS: usize = 17;
+const GPAD_COLUMNS: usize = 12;
+const NEGATION: &str = "NOT";
+
+/// Relation between a gene product and a term, the GAF qualifier without `NOT`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Qualifier {
+    Enables,
+    ContributesTo,
+    IsActiveIn,
+    LocatedIn,
+    ColocalizesWith,
+    PartOf,
+    InvolvedIn,
+    ActsUpstreamOf,
+    ActsUpstreamOfOrWithin,
+    ActsUpstreamOfPositiveEffect,
+    ActsUpstreamOfNegativeEffect,
+    ActsUpstreamOfOrWithinPositiveEffect,
+    ActsUpstreamOfOrWithinNegativeEffect,
+    Other(String),
+}
+
+/// Qualifiers with their Relation Ontology classes, used by GPAD 2.0.
+const RELATIONS: [(Qualifier, &str); 13] = [
+    (Qualifier::Enables, "RO:0002327"),
+    (Qualifier::ContributesTo, "RO:0002326"),
+    (Qualifier::IsActiveIn, "RO:0002432"),
+    (Qualifier::LocatedIn, "RO:0001025"),
+    (Qualifier::ColocalizesWith, "RO:0002325"),
+    (Qualifier::PartOf, "BFO:0000050"),
+    (Qualifier::InvolvedIn, "RO:0002331"),
+    (Qualifier::ActsUpstreamOf, "RO:0002263"),
+    (Qualifier::ActsUpstreamOfOrWithin, "RO:0002264"),
+    (Qualifier::ActsUpstreamOfPositiveEffect, "RO:0004034"),
+    (Qualifier::ActsUpstreamOfNegativeEffect, "RO:0004035"),
+    (
+        Qualifier::ActsUpstreamOfOrWithinPositiveEffect,
+        "RO:0004032",
+    ),
+    (
+        Qualifier::ActsUpstreamOfOrWithinNegativeEffect,
+        "RO:0004033",
+    ),
+];
+
+impl Qualifier {
+    /// The qualifier GO assumes for unqualified GAF 2.1 lines of an aspect.
+    pub fn default_for(aspect: GoTermNamespace) -> Self {
+        match aspect {
+            GoTermNamespace::MolecularFunction => Qualifier::Enables,
+            GoTermNamespace::BiologicalProcess => Qualifier::InvolvedIn,
+            GoTermNamespace::CellerComponent => Qualifier::LocatedIn,
+        }
+    }
+
+    /// The Relation Ontology class, such as `RO:0002327` for enables.
+    pub fn relation(&self) -> Option<&str> {
+        RELATIONS
+            .iter()
+            .find(|(qualifier, _)| qualifier == self)
+            .map(|(_, relation)| *relation)
+    }
+
+    pub fn from_relation(relation: &str) -> Option<Self> {
+        RELATIONS
+            .iter()
+            .find(|(_, r)| *r == relation)
+            .map(|(qualifier, _)| qualifier.clone())
+    }
+}
+
+impl From<&str> for Qualifier {
+    fn from(s: &str) -> Self {
+        match s {
+            "enables" => Qualifier::Enables,
+            "contributes_to" => Qualifier::ContributesTo,
+            "is_active_in" => Qualifier::IsActiveIn,
+            "located_in" => Qualifier::LocatedIn,
+            "colocalizes_with" => Qualifier::ColocalizesWith,
+            "part_of" => Qualifier::PartOf,
+            "involved_in" => Qualifier::InvolvedIn,
+            "acts_upstream_of" => Qualifier::ActsUpstreamOf,
+            "acts_upstream_of_or_within" => Qualifier::ActsUpstreamOfOrWithin,
+            "acts_upstream_of_positive_effect" => Qualifier::ActsUpstreamOfPositiveEffect,
+            "acts_upstream_of_negative_effect" => Qualifier::ActsUpstreamOfNegativeEffect,
+            "acts_upstream_of_or_within_positive_effect" => {
+                Qualifier::ActsUpstreamOfOrWithinPositiveEffect
+            }
+            "acts_upstream_of_or_within_negative_effect" => {
+                Qualifier::ActsUpstreamOfOrWithinNegativeEffect
+            }
+            _ => Qualifier::Other(s.to_string()),
+        }
+    }
+}
+
+impl AsRef<str> for Qualifier {
+    fn as_ref(&self) -> &str {
+        match self {
+            Qualifier::Enables => "enables",
+            Qualifier::ContributesTo => "contributes_to",
+            Qualifier::IsActiveIn => "is_active_in",
+            Qualifier::LocatedIn => "located_in",
+            Qualifier::ColocalizesWith => "colocalizes_with",
+            Qualifier::PartOf => "part_of",
+            Qualifier::InvolvedIn => "involved_in",
+            Qualifier::ActsUpstreamOf => "acts_upstream_of",
+            Qualifier::ActsUpstreamOfOrWithin => "acts_upstream_of_or_within",
+            Qualifier::ActsUpstreamOfPositiveEffect => "acts_upstream_of_positive_effect",
+            Qualifier::ActsUpstreamOfNegativeEffect => "acts_upstream_of_negative_effect",
+            Qualifier::ActsUpstreamOfOrWithinPositiveEffect => {
+                "acts_upstream_of_or_within_positive_effect"
+            }
+            Qualifier::ActsUpstreamOfOrWithinNegativeEffect => {
+                "acts_upstream_of_or_within_negative_effect"
+            }
+            Qualifier::Other(s) => s,
+        }
+    }
+}
+
+impl fmt::Display for Qualifier {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.as_ref())
+    }
+}
+
+impl Serialize for Qualifier {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.as_ref())
+    }
+}
+
+impl<'de> Deserialize<'de> for Qualifier {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        Ok(Qualifier::from(s.as_str()))
+    }
+}
+
+/// A gene product annotated to a GO term. GPAD lines leave the gene product columns empty
+/// until merged with their GPI entries.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GoAssociation {
+    pub db: String,
+    pub object_id: String,
+    pub symbol: String,
+    /// Set by the `NOT` qualifier: the gene product is not associated with the term.
+    pub negated: bool,
+    /// `None` for GAF 2.1 lines without qualifier.
+    pub qualifier: Option<Qualifier>,
+    pub go_id: GoTermID,
+    pub references: Vec<String>,
+    /// The GO evidence code; `None` for GPAD classes outside GO's gaf-eco-mapping.
+    pub evidence_code: Option<EvidenceCode>,
+    /// The ECO class of GPAD lines as given, written back in place of the default class of
+    /// the code.
+    pub eco_id: Option<String>,
+    /// Alternatives of the with/from column; each may list several ids joined by commas.
+    pub with_from: Vec<String>,
+    pub aspect: Option<GoTermNamespace>,
+    pub object_name: String,
+    pub synonyms: Vec<String>,
+    pub object_type: String,
+    pub taxon_id: Option<u32>,
+    pub interacting_taxon_id: Option<u32>,
+    /// Date of the annotation as `YYYYMMDD`.
+    pub date: String,
+    pub assigned_by: String,
+    pub extensions: String,
+    pub gene_product_form: String,
+    /// GPAD annotation properties, such as `creation-date=2021-06-24`.
+    pub properties: String,
+}
+
+impl GoAssociation {
+    /// The qualifier, or the default of the aspect for unqualified associations.
+    pub fn relation(&self) -> Option<Qualifier> {
+        self.qualifier
+            .clone()
+            .or_else(|| self.aspect.map(Qualifier::default_for))
+    }
+
+    /// The `db:object_id` curie identifying the gene product.
+    pub fn object_curie(&self) -> String {
+        format!("{}:{}", self.db, self.object_id)
+    }
+
+    fn qualifier_column(&self) -> String {
+        let mut values = Vec::new();
+        if self.negated {
+            values.push(NEGATION.to_string());
+        }
+        values.extend(self.qualifier.as_ref().map(|q| q.to_string()));
+        values.join("|")
+    }
+
+    /// Fills the gene product columns from the GPI entry of its object.
+    pub fn set_gene_product(&mut self, product: &GeneProduct) {
+        self.symbol = product.symbol.clone();
+        self.object_name = product.name.clone();
+        self.synonyms = product.synonyms.clone();
+        self.object_type = product.object_type.clone();
+        self.taxon_id = product.taxon_id;
+    }
+}
+
+/// A gene product of a GPI file.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GeneProduct {
+    pub db: String,
+    pub object_id: String,
+    pub symbol: String,
+    pub name: String,
+    pub synonyms: Vec<String>,
+    pub object_type: String,
+    pub taxon_id: Option<u32>,
+    pub parent_id: String,
+    pub xrefs: Vec<String>,
+    pub properties: String,
+}
+
+/// Fills the gene product columns of GPAD associations from their GPI entries.
+pub fn merge_gene_products(associations: &mut [GoAssociation], products: &[GeneProduct]) {
+    let products: HashMap<(&str, &str), &GeneProduct> = products
+        .iter()
+        .map(|p| ((p.db.as_str(), p.object_id.as_str()), p))
+        .collect();
+    for association in associations {
+        let key = (association.db.as_str(), association.object_id.as_str());
+        if let Some(&product) = products.get(&key) {
+            association.set_gene_product(product);
+        }
+    }
+}
+
+/// Version of an annotation file from its `!gaf-version`, `!gpa-version`, `!gpad-version`
+/// or `!gpi-version` header line.
+fn version(line: &str) -> Option<&str> {
+    let (tag, value) = line.trim_start_matches('!').split_once(':')?;
+    tag.trim().ends_with("-version").then(|| value.trim())
+}
+
+/// Data lines with their line numbers.
+type Lines = Vec<(usize, String)>;
+
+/// Lines of an annotation file, and the format version.
+fn data_lines<R: BufRead>(reader: R) -> Result<(Option<String>, Lines)> {
+    let mut file_version = None;
+    let mut lines = Vec::new();
+    for (i, line) in reader.lines().enumerate() {
+        let line = line?;
+        if let Some(header) = line.strip_prefix('!') {
+            if file_version.is_none() {
+                file_version = version(header).map(str::to_string);
+            }
+        } else if !line.trim().is_empty() {
+            lines.push((i + 1, line));
+        }
+    }
+    Ok((file_version, lines))
+}
+
+fn split(value: &str, separator: char) -> Vec<String> {
+    value
+        .split(separator)
+        .filter(|v| !v.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+/// Parses a `taxon:9606` or `NCBITaxon:9606` id; empty values are `None`.
+fn taxon(value: &str) -> Result<Option<u32>, String> {
+    if value.is_empty() {
+        return Ok(None);
+    }
+    let id = value
+        .strip_prefix("taxon:")
+        .or_else(|| value.strip_prefix("NCBITaxon:"))
+        .unwrap_or(value);
+    id.parse()
+        .map(Some)
+        .map_err(|_| format!("invalid taxon: {}", value))
+}
+
+/// Splits the GAF and GPAD 1.1 qualifier column into the negation and the qualifier.
+fn qualifier(value: &str) -> (bool, Option<Qualifier>) {
+    let mut negated = false;
+    let mut qualifier = None;
+    for part in value.split('|').filter(|p| !p.is_empty()) {
+        if part == NEGATION {
+            negated = true;
+        } else {
+            qualifier = Some(Qualifier::from(part));
+        }
+    }
+    (negated, qualifier)
+}
+
+/// Accepts dates as `YYYYMMDD` and `YYYY-MM-DD`, returning `YYYYMMDD`.
+fn compact_date(value: &str) -> Result<String, String> {
+    let date: String = value.chars().filter(|&c| c != '-').collect();
+    if date.len() == 8 && date.chars().all(|c| c.is_ascii_digit()) {
+        Ok(date)
+    } else {
+        Err(format!("invalid date: {}", value))
+    }
+}
+
+fn iso_date(date: &str) -> String {
+    if date.len() == 8 {
+        format!("{}-{}-{}", &date[..4], &date[4..6], &date[6..])
+    } else {
+        date.to_string()
+    }
+}
+
+fn columns(line: &str, expected: usize, i: usize) -> Result<Vec<&str>> {
+    let mut columns: Vec<&str> = line.split('\t').collect();
+    // Older files may omit the trailing optional columns.
+    if columns.len() > expected || columns.len() < expected - 2 {
+        return Err(anyhow!(
+            "line {}: expected {} columns, found {}",
+            i,
+            expected,
+            columns.len()
+        ));
+    }
+    columns.resize(expected, "");
+    Ok(columns)
+}
+
+pub fn read_gaf<R: BufRead>(reader: R) -> Result<Vec<GoAssociation>> {
+    let (_, lines) = data_lines(reader)?;
+    lines
+        .iter()
+        .map(|(i, line)| gaf_association(line, *i))
+        .collect()
+}
+
+fn gaf_association(line: &str, i: usize) -> Result<GoAssociation> {
+    let c = columns(line, GAF_COLUMNS, i)?;
+    let error = |e: String| anyhow!("line {}: {}", i, e);
+    let (negated, qualifier) = qualifier(c[3]);
+    let aspect = Some(c[8].parse().map_err(error)?);
+    let mut taxa = c[12].split('|');
+    Ok(GoAssociation {
+        db: c[0].to_string(),
+        object_id: c[1].to_string(),
+        symbol: c[2].to_string(),
+        negated,
+        qualifier,
+        go_id: c[4].to_string(),
+        references: split(c[5], '|'),
+        evidence_code: Some(c[6].parse().map_err(error)?),
+        eco_id: None,
+        with_from: split(c[7], '|'),
+        aspect,
+        object_name: c[9].to_string(),
+        synonyms: split(c[10], '|'),
+        object_type: c[11].to_string(),
+        taxon_id: taxon(taxa.next().unwrap_or_default()).map_err(error)?,
+        interacting_taxon_id: taxon(taxa.next().unwrap_or_default()).map_err(error)?,
+        date: compact_date(c[13]).map_err(error)?,
+        assigned_by: c[14].to_string(),
+        extensions: c[15].to_string(),
+        gene_product_form: c[16].to_string(),
+        properties: String::new(),
+    })
+}
+
+/// Fills the missing aspects, as of associations read from GPAD, from the namespaces of their
+/// terms in the ontology.
+pub fn set_aspects(associations: &mut [GoAssociation], ontology: &GoOntology) {
+    for association in associations.iter_mut().filter(|a| a.aspect.is_none()) {
+        association.aspect = ontology
+            .term(&association.go_id)
+            .map(|term| term.namespace());
+    }
+}
+
+/// Writes GAF 2.2. Associations need a GO evidence code and an aspect, which GPAD lacks; see
+/// [`set_aspects`].
+pub fn write_gaf<W: Write>(mut writer: W, associations: &[GoAssociation]) -> io::Result<()> {
+    writeln!(writer, "!gaf-version: 2.2")?;
+    for a in associations {
+        let aspect = a.aspect.ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("{}: no aspect for {}", a.object_curie(), a.go_id),
+            )
+        })?;
+        let evidence_code = a.evidence_code.ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!(
+                    "{}: no evidence code for {}",
+                    a.object_curie(),
+                    a.eco_id.as_deref().unwrap_or_default()
+                ),
+            )
+        })?;
+        let mut taxa: Vec<String> = a.taxon_id.iter().map(|t| format!("taxon:{}", t)).collect();
+        taxa.extend(a.interacting_taxon_id.map(|t| format!("taxon:{}", t)));
+        let columns = [
+            a.db.as_str(),
+            &a.object_id,
+            &a.symbol,
+            &a.qualifier_column(),
+            &a.go_id,
+            &a.references.join("|"),
+            &evidence_code.to_string(),
+            &a.with_from.join("|"),
+            aspect.aspect(),
+            &a.object_name,
+            &a.synonyms.join("|"),
+            &a.object_type,
+            &taxa.join("|"),
+            &a.date,
+            &a.assigned_by,
+            &a.extensions,
+            &a.gene_product_form,
+        ];
+        writeln!(writer, "{}", columns.join("\t"))?;
+    }
+    Ok(())
+}
+
+/// Reads GPAD 2.0 files, or GPAD 1.1 files when their header says so.
+pub fn read_gpad<R: BufRead>(reader: R) -> Result<Vec<GoAssociation>> {
+    let (version, lines) = data_lines(reader)?;
+    let legacy = version.is_some_and(|v| v.starts_with('1'));
+    lines
+        .iter()
+        .map(|(i, line)| gpad_association(line, *i, legacy))
+        .collect()
+}
+
+fn gpad_association(line: &str, i: usize, legacy: bool) -> Result<GoAssociation> {
+    let c = columns(line, GPAD_COLUMNS, i)?;
+    let error = |e: String| anyhow!("line {}: {}", i, e);
+    // GPAD 2.0 joins db and object id, and has its own negation and relation columns.
+    let (db, object_id, negated, qualifier, rest) = if legacy {
+        let (negated, qualifier) = qualifier(c[2]);
+        (c[0], c[1], negated, qualifier, &c[3..])
+    } else {
+        let (db, object_id) = c[0]
+            .split_once(':')
+            .ok_or_else(|| error(format!("invalid object id: {}", c[0])))?;
+        let qualifier = Qualifier::from_relation(c[2])
+            .ok_or_else(|| error(format!("unknown relation: {}", c[2])))?;
+        (db, object_id, c[1] == NEGATION, Some(qualifier), &c[3..])
+    };
+    Ok(GoAssociation {
+        db: db.to_string(),
+        object_id: object_id.to_string(),
+        symbol: String::new(),
+        negated,
+        qualifier,
+        go_id: rest[0].to_string(),
+        references: split(rest[1], '|'),
+        evidence_code: EvidenceCode::from_eco(rest[2]),
+        eco_id: Some(rest[2].to_string()),
+        with_from: split(rest[3], '|'),
+        aspect: None,
+        object_name: String::new(),
+        synonyms: Vec::new(),
+        object_type: String::new(),
+        taxon_id: None,
+        interacting_taxon_id: taxon(rest[4]).map_err(error)?,
+        date: compact_date(rest[5]).map_err(error)?,
+        assigned_by: rest[6].to_string(),
+        extensions: rest[7].to_string(),
+        gene_product_form: String::new(),
+        properties: rest[8].to_string(),
+    })
+}
+
+/// Writes GPAD 2.0. Unqualified associations take the default relation of their aspect.
+pub fn write_gpad<W: Write>(mut writer: W, associations: &[GoAssociation]) -> io::Result<()> {
+    writeln!(writer, "!gpad-version: 2.0")?;
+    for a in associations {
+        let relation = a.relation();
+        let relation = relation
+            .as_ref()
+            .and_then(|q| q.relation())
+            .ok_or_else(|| {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("{}: no relation for {}", a.object_curie(), a.go_id),
+                )
+            })?;
+        let interacting_taxon = a
+            .interacting_taxon_id
+            .map_or(String::new(), |t| format!("NCBITaxon:{}", t));
+        let eco_id = match (&a.eco_id, a.evidence_code) {
+            (Some(eco_id), _) => eco_id.clone(),
+            (None, Some(code)) => code.eco_id(),
+            (None, None) => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("{}: no evidence for {}", a.object_curie(), a.go_id),
+                ))
+            }
+        };
+        let curie = a.object_curie();
+        let columns = [
+            curie.as_str(),
+            if a.negated { NEGATION } else { "" },
+            relation,
+            &a.go_id,
+            &a.references.join("|"),
+            &eco_id,
+            &a.with_from.join("|"),
+            &interacting_taxon,
+            &iso_date(&a.date),
+            &a.assigned_by,
+            &a.extensions,
+            &a.properties,
+        ];
+        writeln!(writer, "{}", columns.join("\t"))?;
+    }
+    Ok(())
+}
+
+/// Reads GPI 2.0 files, or GPI 1.2 files when their header says so.
+pub fn read_gpi<R: BufRead>(reader: R) -> Result<Vec<GeneProduct>> {
+    let (version, lines) = data_lines(reader)?;
+    let legacy = version.is_some_and(|v| v.starts_with('1'));
+    let mut products = Vec::new();
+    for (i, line) in &lines {
+        let error = |e: String| anyhow!("line {}: {}", i, e);
+        let product = if legacy {
+            let c = columns(line, 10, *i)?;
+            GeneProduct {
+                db: c[0].to_string(),
+                object_id: c[1].to_string(),
+                symbol: c[2].to_string(),
+                name: c[3].to_string(),
+                synonyms: split(c[4], '|'),
+                object_type: c[5].to_string(),
+                taxon_id: taxon(c[6]).map_err(error)?,
+                parent_id: c[7].to_string(),
+                xrefs: split(c[8], '|'),
+                properties: c[9].to_string(),
+            }
+        } else {
+            let c = columns(line, 11, *i)?;
+            let (db, object_id) = c[0]
+                .split_once(':')
+                .ok_or_else(|| error(format!("invalid object id: {}", c[0])))?;
+            GeneProduct {
+                db: db.to_string(),
+                object_id: object_id.to_string(),
+                symbol: c[1].to_string(),
+                name: c[2].to_string(),
+                synonyms: split(c[3], '|'),
+                object_type: c[4].to_string(),
+                taxon_id: taxon(c[5]).map_err(error)?,
+                parent_id: c[7].to_string(),
+                xrefs: split(c[9], '|'),
+                properties: c[10].to_string(),
+            }
+        };
+        products.push(product);
+    }
+    Ok(products)
+}
+
+/// Writes GPI 2.0. The encoded-by and complex members columns are left empty.
+pub fn write_gpi<W: Write>(mut writer: W, products: &[GeneProduct]) -> io::Result<()> {
+    writeln!(writer, "!gpi-version: 2.0")?;
+    for p in products {
+        let taxon = p
+            .taxon_id
+            .map_or(String::new(), |t| format!("NCBITaxon:{}", t));
+        let curie = format!("{}:{}", p.db, p.object_id);
+        let columns = [
+            curie.as_str(),
+            &p.symbol,
+            &p.name,
+            &p.synonyms.join("|"),
+            &p.object_type,
+            &taxon,
+            "",
+            &p.parent_id,
+            "",
+            &p.xrefs.join("|"),
+            &p.properties,
+        ];
+        writeln!(writer, "{}", columns.join("\t"))?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test_gaf {
+    use super::*;
+    use crate::functional_annotations::obo::read_obo;
+    use crate::functional_annotations::ontology::Relations;
+
+    const GAF: &str = "!gaf-version: 2.2
+!generated-by: GOC
+UniProtKB\tP12345\tAATM\tenables\tGO:0004069\tPMID:2731362\tIDA\t\tF\tAspartate aminotransferase\tGOT2|AAT2\tprotein\ttaxon:9986\t20230101\tUniProt\t\t
+UniProtKB\tP12345\tAATM\tNOT|located_in\tGO:0005634\tGO_REF:0000002|PMID:1\tIEA\tInterPro:IPR004839\tC\tAspartate aminotransferase\t\tprotein\ttaxon:9986|taxon:9606\t20230101\tInterPro\t\t
+";
+
+    #[test]
+    fn test_gaf() {
+        let associations = read_gaf(GAF.as_bytes()).unwrap();
+        assert_eq!(associations.len(), 2);
+        let first = &associations[0];
+        assert_eq!(first.evidence_code, Some(EvidenceCode::IDA));
+        assert_eq!(first.qualifier, Some(Qualifier::Enables));
+        assert_eq!(first.synonyms, ["GOT2", "AAT2"]);
+        assert_eq!(first.taxon_id, Some(9986));
+        let second = &associations[1];
+        assert!(second.negated);
+        assert_eq!(second.qualifier, Some(Qualifier::LocatedIn));
+        assert_eq!(second.aspect, Some(GoTermNamespace::CellerComponent));
+        assert_eq!(second.references, ["GO_REF:0000002", "PMID:1"]);
+        assert_eq!(second.interacting_taxon_id, Some(9606));
+
+        let mut written = Vec::new();
+        write_gaf(&mut written, &associations).unwrap();
+        let lines: Vec<&str> = GAF.lines().filter(|l| !l.starts_with("!gen")).collect();
+        assert_eq!(String::from_utf8(written).unwrap(), lines.join("\n") + "\n");
+
+        let invalid = GAF.replace("IDA", "XYZ");
+        let err = read_gaf(invalid.as_bytes()).unwrap_err();
+        assert_eq!(err.to_string(), "line 3: invalid evidence code: XYZ");
+    }
+
+    #[test]
+    fn test_gpad_gpi() {
+        let gpad = "!gpad-version: 2.0
+UniProtKB:P12345\t\tRO:0002327\tGO:0004069\tPMID:2731362\tECO:0000314\t\t\t2023-01-01\tUniProt\t\tcreation-date=2023-01-01
+UniProtKB:P12345\tNOT\tRO:0001025\tGO:0005634\tGO_REF:0000002\tECO:0000256\t\tNCBITaxon:9606\t2023-01-01\tInterPro\t\t
+UniProtKB:P12345\t\tRO:0002331\tGO:0006520\tGO_REF:0000120\tECO:0007322\t\t\t2023-01-01\tEnsembl\t\t
+UniProtKB:P12345\t\tRO:0002331\tGO:0006520\tPMID:1\tECO:0006000\t\t\t2023-01-01\tUniProt\t\t
+";
+        let gpi = "!gpi-version: 2.0
+UniProtKB:P12345\tAATM\tAspartate aminotransferase\tGOT2|AAT2\tPR:000000001\tNCBITaxon:9986\t\t\t\t\t
+";
+        let mut associations = read_gpad(gpad.as_bytes()).unwrap();
+        let products = read_gpi(gpi.as_bytes()).unwrap();
+        merge_gene_products(&mut associations, &products);
+        assert_eq!(associations[0].symbol, "AATM");
+        assert_eq!(associations[0].date, "20230101");
+        assert_eq!(associations[1].evidence_code, Some(EvidenceCode::IEA));
+        assert!(associations[1].negated);
+        assert_eq!(associations[1].taxon_id, Some(9986));
+        assert_eq!(associations[2].evidence_code, Some(EvidenceCode::IEA));
+        assert_eq!(associations[3].evidence_code, None);
+        assert!(write_gaf(Vec::new(), &associations).is_err());
+        let mut mapped = associations[..3].to_vec();
+        let err = write_gaf(Vec::new(), &mapped).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "UniProtKB:P12345: no aspect for GO:0004069"
+        );
+        let obo = "[Term]\nid: GO:0004069\nname: a\nnamespace: molecular_function\n\n\
+                   [Term]\nid: GO:0005634\nname: b\nnamespace: cellular_component\n\n\
+                   [Term]\nid: GO:0006520\nname: c\nnamespace: biological_process\n";
+        let ontology =
+            GoOntology::new(read_obo(obo.as_bytes()).unwrap().terms, Relations::is_a()).unwrap();
+        set_aspects(&mut mapped, &ontology);
+        let mut written = Vec::new();
+        write_gaf(&mut written, &mapped).unwrap();
+        let aspects: Vec<&str> = std::str::from_utf8(&written)
+            .unwrap()
+            .lines()
+            .skip(1)
+            .map(|line| line.split('\t').nth(8).unwrap())
+            .collect();
+        assert_eq!(aspects, ["F", "C", "P"]);
+
+        let mut written = Vec::new();
+        write_gpad(&mut written, &associations).unwrap();
+        assert_eq!(String::from_utf8(written).unwrap(), gpad);
+        let mut written = Vec::new();
+        write_gpi(&mut written, &products).unwrap();
+        assert_eq!(String::from_utf8(written).unwrap(), gpi);
+
+        let legacy = "!gpa-version: 1.1
+UniProtKB\tP12345\tNOT|enables\tGO:0004069\tPMID:1\tECO:0000314\t\ttaxon:9606\t20230101\tUniProt\t\t
+";
+        let legacy = read_gpad(legacy.as_bytes()).unwrap();
+        assert!(legacy[0].negated);
+        assert_eq!(legacy[0].qualifier, Some(Qualifier::Enables));
+        assert_eq!(legacy[0].interacting_taxon_id, Some(9606));
+    }
+}
@@ -1,8 +1,10 @@
 use std::collections::HashMap;
+use std::fmt;
+use std::str::FromStr;
 
 use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
 pub enum EvidenceCode {
     EXP,
     IDA,
@@ -94,34 +96,294 @@ impl EvidenceCode {
     }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+impl EvidenceCode {
+    pub const ALL: [EvidenceCode; 27] = [
+        EvidenceCode::EXP,
+        EvidenceCode::IDA,
+        EvidenceCode::IPI,
+        EvidenceCode::IMP,
+        EvidenceCode::IGI,
+        EvidenceCode::IEP,
+        EvidenceCode::ISS,
+        EvidenceCode::ISO,
+        EvidenceCode::ISA,
+        EvidenceCode::ISM,
+        EvidenceCode::IGC,
+        EvidenceCode::IBA,
+        EvidenceCode::IBD,
+        EvidenceCode::IKR,
+        EvidenceCode::IRD,
+        EvidenceCode::IMR,
+        EvidenceCode::RCA,
+        EvidenceCode::HTP,
+        EvidenceCode::HDA,
+        EvidenceCode::HMP,
+        EvidenceCode::HGI,
+        EvidenceCode::HEP,
+        EvidenceCode::TAS,
+        EvidenceCode::NAS,
+        EvidenceCode::IC,
+        EvidenceCode::ND,
+        EvidenceCode::IEA,
+    ];
+
+    /// The ECO class of the code, such as `ECO:0000314` for IDA.
+    pub fn eco_id(&self) -> String {
+        self.meta().id
+    }
+
+    /// The code of an ECO class, from its default class or the subclasses of GO's
+    /// gaf-eco-mapping. IKR and IMR share their class, which maps to IKR.
+    pub fn from_eco(id: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|code| code.meta().id == id)
+            .or_else(|| {
+                ECO_SUBCLASSES
+                    .iter()
+                    .find(|(eco, _)| *eco == id)
+                    .map(|(_, code)| *code)
+            })
+    }
+
+    /// Category of the code, such as `Experimental` or `Automatic`.
+    pub fn category(&self) -> String {
+        self.meta().category
+    }
+}
+
+/// ECO classes that GO annotations use besides the default class of their code, such as
+/// those of InterPro2GO or Ensembl Compara IEA annotations.
+const ECO_SUBCLASSES: [(&str, EvidenceCode); 10] = [
+    ("ECO:0000203", EvidenceCode::IEA),
+    ("ECO:0000256", EvidenceCode::IEA),
+    ("ECO:0000265", EvidenceCode::IEA),
+    ("ECO:0000322", EvidenceCode::IEA),
+    ("ECO:0000323", EvidenceCode::IEA),
+    ("ECO:0000363", EvidenceCode::IEA),
+    ("ECO:0000366", EvidenceCode::IEA),
+    ("ECO:0007322", EvidenceCode::IEA),
+    ("ECO:0007669", EvidenceCode::IEA),
+    ("ECO:0000031", EvidenceCode::ISS),
+];
+
+impl fmt::Display for EvidenceCode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.meta().code)
+    }
+}
+
+impl FromStr for EvidenceCode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .into_iter()
+            .find(|code| code.meta().code == s)
+            .ok_or_else(|| format!("invalid evidence code: {}", s))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
 pub enum GoTermNamespace {
     BiologicalProcess,
     MolecularFunction,
     CellerComponent,
 }
 
-impl ToString for GoTermNamespace {
-    fn to_string(&self) -> String {
+impl fmt::Display for GoTermNamespace {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GoTermNamespace::BiologicalProcess => write!(f, "BP"),
+            GoTermNamespace::CellerComponent => write!(f, "CC"),
+            GoTermNamespace::MolecularFunction => write!(f, "MF"),
+        }
+    }
+}
+
+impl GoTermNamespace {
+    /// The aspect column of GAF files: `P`, `F` or `C`.
+    pub fn aspect(&self) -> &str {
         match self {
-            GoTermNamespace::BiologicalProcess => "BP".to_string(),
-            GoTermNamespace::CellerComponent => "CC".to_string(),
-            GoTermNamespace::MolecularFunction => "MF".to_string(),
+            GoTermNamespace::BiologicalProcess => "P",
+            GoTermNamespace::MolecularFunction => "F",
+            GoTermNamespace::CellerComponent => "C",
+        }
+    }
+}
+
+/// Parses OBO namespaces, such as `biological_process`, and their abbreviations.
+impl FromStr for GoTermNamespace {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "biological_process" | "BP" | "P" => Ok(Self::BiologicalProcess),
+            "molecular_function" | "MF" | "F" => Ok(Self::MolecularFunction),
+            "cellular_component" | "CC" | "C" => Ok(Self::CellerComponent),
+            _ => Err(format!("invalid GO namespace: {}", s)),
         }
     }
 }
 
 pub type GoTermID = String;
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+/// Relations between GO terms other than `is_a`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum RelationshipType {
+    PartOf,
+    HasPart,
+    Regulates,
+    PositivelyRegulates,
+    NegativelyRegulates,
+    OccursIn,
+    Other(String),
+}
+
+impl From<&str> for RelationshipType {
+    fn from(relationship: &str) -> Self {
+        match relationship {
+            "part_of" => Self::PartOf,
+            "has_part" => Self::HasPart,
+            "regulates" => Self::Regulates,
+            "positively_regulates" => Self::PositivelyRegulates,
+            "negatively_regulates" => Self::NegativelyRegulates,
+            "occurs_in" => Self::OccursIn,
+            _ => Self::Other(relationship.to_string()),
+        }
+    }
+}
+
+impl AsRef<str> for RelationshipType {
+    fn as_ref(&self) -> &str {
+        match self {
+            Self::PartOf => "part_of",
+            Self::HasPart => "has_part",
+            Self::Regulates => "regulates",
+            Self::PositivelyRegulates => "positively_regulates",
+            Self::NegativelyRegulates => "negatively_regulates",
+            Self::OccursIn => "occurs_in",
+            Self::Other(relationship) => relationship,
+        }
+    }
+}
+
+impl fmt::Display for RelationshipType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.as_ref())
+    }
+}
+
+impl Serialize for RelationshipType {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.as_ref())
+    }
+}
+
+impl<'de> Deserialize<'de> for RelationshipType {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let relationship = String::deserialize(deserializer)?;
+        Ok(Self::from(relationship.as_str()))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct GoTerm {
-    id: GoTermID,
-    name: String,
-    namespace: GoTermNamespace,
-    def: String,
-    is_obsolate: bool,
-    xrefs: Vec<String>,
-    synonyms: Vec<String>,
-    is_a: Vec<GoTermID>,
-    relationships: HashMap<String, Vec<GoTermID>>,
+    pub(crate) id: GoTermID,
+    pub(crate) name: String,
+    pub(crate) namespace: GoTermNamespace,
+    pub(crate) def: String,
+    pub(crate) is_obsolete: bool,
+    pub(crate) xrefs: Vec<String>,
+    pub(crate) synonyms: Vec<String>,
+    pub(crate) is_a: Vec<GoTermID>,
+    pub(crate) relationships: HashMap<RelationshipType, Vec<GoTermID>>,
+    pub(crate) alt_ids: Vec<GoTermID>,
+    pub(crate) replaced_by: Vec<GoTermID>,
+    pub(crate) consider: Vec<GoTermID>,
+    pub(crate) subsets: Vec<String>,
+}
+
+impl GoTerm {
+    pub fn new(id: &str, name: &str, namespace: GoTermNamespace) -> Self {
+        Self {
+            id: id.to_string(),
+            name: name.to_string(),
+            namespace,
+            def: String::new(),
+            is_obsolete: false,
+            xrefs: Vec::new(),
+            synonyms: Vec::new(),
+            is_a: Vec::new(),
+            relationships: HashMap::new(),
+            alt_ids: Vec::new(),
+            replaced_by: Vec::new(),
+            consider: Vec::new(),
+            subsets: Vec::new(),
+        }
+    }
+
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn namespace(&self) -> GoTermNamespace {
+        self.namespace
+    }
+
+    /// The definition text, without its references.
+    pub fn definition(&self) -> &str {
+        &self.def
+    }
+
+    pub fn is_obsolete(&self) -> bool {
+        self.is_obsolete
+    }
+
+    pub fn xrefs(&self) -> &[String] {
+        &self.xrefs
+    }
+
+    pub fn synonyms(&self) -> &[String] {
+        &self.synonyms
+    }
+
+    pub fn is_a(&self) -> &[GoTermID] {
+        &self.is_a
+    }
+
+    pub fn relationships(&self) -> &HashMap<RelationshipType, Vec<GoTermID>> {
+        &self.relationships
+    }
+
+    /// Targets of one type of relationship.
+    pub fn related(&self, relationship: &RelationshipType) -> &[GoTermID] {
+        self.relationships
+            .get(relationship)
+            .map_or(&[], |targets| targets.as_slice())
+    }
+
+    /// Secondary IDs, from terms merged into this one.
+    pub fn alt_ids(&self) -> &[GoTermID] {
+        &self.alt_ids
+    }
+
+    /// Terms replacing an obsolete term.
+    pub fn replaced_by(&self) -> &[GoTermID] {
+        &self.replaced_by
+    }
+
+    /// Terms to consider instead of an obsolete term.
+    pub fn consider(&self) -> &[GoTermID] {
+        &self.consider
+    }
+
+    /// Subsets the term belongs to, such as `goslim_generic`.
+    pub fn subsets(&self) -> &[String] {
+        &self.subsets
+    }
 }
@@ -1 +1,7 @@
+pub mod annotations;
+pub mod enrichment;
+pub mod gaf;
 pub mod go_term;
+pub mod obo;
+pub mod ontology;
+pub mod propagation;
@@ -0,0 +1,254 @@
+//! Reading of OBO 1.4 ontologies, such as `go-basic.obo` and `go.obo`, into GO terms.
+
+use std::collections::HashMap;
+use std::io::BufRead;
+
+use anyhow::{anyhow, Result};
+
+use super::go_term::{GoTerm, GoTermNamespace, RelationshipType};
+
+const TERM_STANZA: &str = "[Term]";
+
+/// An ontology file: its header and the terms of its `[Term]` stanzas. `[Typedef]` and
+/// `[Instance]` stanzas are skipped.
+#[derive(Debug, Clone, Default)]
+pub struct Obo {
+    pub format_version: Option<String>,
+    /// Release of the ontology, such as `releases/2024-01-17`.
+    pub data_version: Option<String>,
+    pub terms: Vec<GoTerm>,
+}
+
+/// Tag-value pairs of one stanza, with their line numbers.
+type Clauses = Vec<(usize, String, String)>;
+
+pub fn read_obo<R: BufRead>(reader: R) -> Result<Obo> {
+    let mut obo = Obo::default();
+    // The stanza being read, or `None` in the header.
+    let mut stanza: Option<(String, Clauses)> = None;
+    for (i, line) in reader.lines().enumerate() {
+        let line = line?;
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('!') {
+            continue;
+        }
+        if line.starts_with('[') {
+            if let Some((name, clauses)) = stanza.take() {
+                obo.add_stanza(&name, clauses)?;
+            }
+            stanza = Some((line.to_string(), Vec::new()));
+            continue;
+        }
+        let (tag, value) = line
+            .split_once(':')
+            .ok_or_else(|| anyhow!("line {}: expected tag: value", i + 1))?;
+        let value = strip_trailing(value.trim()).to_string();
+        match stanza.as_mut() {
+            Some((_, clauses)) => clauses.push((i + 1, tag.to_string(), value)),
+            None => match tag {
+                "format-version" => obo.format_version = Some(value),
+                "data-version" => obo.data_version = Some(value),
+                _ => {}
+            },
+        }
+    }
+    if let Some((name, clauses)) = stanza {
+        obo.add_stanza(&name, clauses)?;
+    }
+    Ok(obo)
+}
+
+impl Obo {
+    fn add_stanza(&mut self, name: &str, clauses: Clauses) -> Result<()> {
+        if name == TERM_STANZA {
+            self.terms.push(term(clauses)?);
+        }
+        Ok(())
+    }
+}
+
+fn term(clauses: Clauses) -> Result<GoTerm> {
+    let line = clauses.first().map_or(0, |(line, _, _)| *line);
+    let value = |tag: &str| {
+        clauses
+            .iter()
+            .find(|(_, t, _)| t == tag)
+            .map(|(_, _, v)| v.as_str())
+    };
+    let id = value("id").ok_or_else(|| anyhow!("line {}: term without id", line))?;
+    let error = |e: String| anyhow!("{}: {}", id, e);
+    let name = value("name").ok_or_else(|| error("missing name".to_string()))?;
+    let namespace: GoTermNamespace = value("namespace")
+        .ok_or_else(|| error("missing namespace".to_string()))?
+        .parse()
+        .map_err(error)?;
+
+    let mut term = GoTerm::new(id, name, namespace);
+    let mut relationships: HashMap<RelationshipType, Vec<String>> = HashMap::new();
+    for (line, tag, value) in &clauses {
+        let error = |e: &str| anyhow!("line {}: {}: {}", line, e, value);
+        let first = || {
+            value
+                .split_whitespace()
+                .next()
+                .unwrap_or_default()
+                .to_string()
+        };
+        match tag.as_str() {
+            "def" => term.def = quoted(value).ok_or_else(|| error("invalid def"))?,
+            "is_obsolete" => term.is_obsolete = value == "true",
+            "xref" => term.xrefs.push(first()),
+            "synonym" => {
+                let text = quoted(value).ok_or_else(|| error("invalid synonym"))?;
+                term.synonyms.push(text);
+            }
+            "is_a" => term.is_a.push(first()),
+            "relationship" => {
+                let mut parts = value.split_whitespace();
+                let (Some(relationship), Some(target)) = (parts.next(), parts.next()) else {
+                    return Err(error("invalid relationship"));
+                };
+                relationships
+                    .entry(RelationshipType::from(relationship))
+                    .or_default()
+                    .push(target.to_string());
+            }
+            "alt_id" => term.alt_ids.push(first()),
+            "replaced_by" => term.replaced_by.push(first()),
+            "consider" => term.consider.push(first()),
+            "subset" => term.subsets.push(first()),
+            _ => {}
+        }
+    }
+    term.relationships = relationships;
+    Ok(term)
+}
+
+/// Removes the `! comment` and `{modifiers}` that may end a value, outside quoted text.
+fn strip_trailing(value: &str) -> &str {
+    let (mut in_quotes, mut escaped) = (false, false);
+    let mut end = value.len();
+    let mut modifiers = None;
+    for (i, c) in value.char_indices() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' => escaped = true,
+            '"' => in_quotes = !in_quotes,
+            '!' if !in_quotes => {
+                end = i;
+                break;
+            }
+            '{' if !in_quotes => modifiers = Some(i),
+            _ => {}
+        }
+    }
+    let value = value[..end].trim_end();
+    match modifiers {
+        Some(i) if i < value.len() && value.ends_with('}') => value[..i].trim_end(),
+        _ => value,
+    }
+}
+
+/// The unescaped text of a value starting with a quoted string.
+fn quoted(value: &str) -> Option<String> {
+    let rest = value.strip_prefix('"')?;
+    let mut text = String::new();
+    let mut chars = rest.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => text.push(chars.next()?),
+            '"' => return Some(text),
+            _ => text.push(c),
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod test_obo {
+    use super::*;
+
+    const OBO: &str = r#"format-version: 1.2
+data-version: releases/2024-01-17
+subsetdef: goslim_generic "Generic GO slim"
+ontology: go
+
+[Term]
+id: GO:0005739
+name: mitochondrion
+namespace: cellular_component
+alt_id: GO:0005738
+def: "A semiautonomous, self replicating organelle \"mitochondrion\"." [GOC:giardia, ISBN:9780198506732]
+subset: goslim_generic
+synonym: "mitochondria" EXACT []
+xref: Wikipedia:Mitochondrion
+is_a: GO:0043231 ! intracellular membrane-bounded organelle
+relationship: part_of GO:0005737 ! cytoplasm {source="GOC:mah"}
+
+[Term]
+id: GO:0000001
+name: mitochondrion inheritance
+namespace: biological_process
+is_obsolete: true
+replaced_by: GO:0048308
+consider: GO:0048311
+
+[Term]
+id: GO:0010628
+name: positive regulation of gene expression
+namespace: biological_process
+is_a: GO:0010604 ! positive regulation of macromolecule metabolic process
+relationship: positively_regulates GO:0010467 ! gene expression
+
+[Typedef]
+id: part_of
+name: part of
+"#;
+
+    #[test]
+    fn test_read_obo() {
+        let obo = read_obo(OBO.as_bytes()).unwrap();
+        assert_eq!(obo.data_version.as_deref(), Some("releases/2024-01-17"));
+        assert_eq!(obo.terms.len(), 3);
+
+        let mitochondrion = &obo.terms[0];
+        assert_eq!(mitochondrion.id(), "GO:0005739");
+        assert_eq!(mitochondrion.namespace(), GoTermNamespace::CellerComponent);
+        assert_eq!(
+            mitochondrion.definition(),
+            "A semiautonomous, self replicating organelle \"mitochondrion\"."
+        );
+        assert_eq!(mitochondrion.alt_ids(), ["GO:0005738"]);
+        assert_eq!(mitochondrion.subsets(), ["goslim_generic"]);
+        assert_eq!(mitochondrion.synonyms(), ["mitochondria"]);
+        assert_eq!(mitochondrion.xrefs(), ["Wikipedia:Mitochondrion"]);
+        assert_eq!(mitochondrion.is_a(), ["GO:0043231"]);
+        assert_eq!(
+            mitochondrion.related(&RelationshipType::PartOf),
+            ["GO:0005737"]
+        );
+
+        let obsolete = &obo.terms[1];
+        assert!(obsolete.is_obsolete());
+        assert_eq!(obsolete.replaced_by(), ["GO:0048308"]);
+        assert_eq!(obsolete.consider(), ["GO:0048311"]);
+
+        assert_eq!(
+            obo.terms[2].related(&RelationshipType::PositivelyRegulates),
+            ["GO:0010467"]
+        );
+    }
+
+    #[test]
+    fn test_invalid_terms() {
+        let missing_namespace = "[Term]\nid: GO:0000001\nname: test\n";
+        let err = read_obo(missing_namespace.as_bytes()).unwrap_err();
+        assert_eq!(err.to_string(), "GO:0000001: missing namespace");
+        assert!(read_obo("[Term]\nname: test\n".as_bytes()).is_err());
+        assert_eq!(strip_trailing("GO:1 ! comment {x=y}"), "GO:1");
+        assert_eq!(
+            strip_trailing(r#""a ! b" EXACT {x="y"}"#),
+            r#""a ! b" EXACT"#
+        );
+    }
+}
@@ -0,0 +1,423 @@
+//! The GO graph: terms linked by `is_a` and the relationships chosen when it is built, with
+//! ancestor closures computed once so that lookups are fast.
+
+use std::collections::{BTreeSet, HashMap, VecDeque};
+use std::io::{Read, Write};
+
+use anyhow::{anyhow, bail, Result};
+use serde::{Deserialize, Serialize};
+
+use super::go_term::{GoTerm, GoTermID, GoTermNamespace, RelationshipType};
+
+/// Version of the binary cache layout, checked when reading a cache.
+const CACHE_VERSION: u32 = 1;
+
+/// Relationships followed between terms in addition to `is_a`.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Relations {
+    pub relationships: Vec<RelationshipType>,
+}
+
+impl Relations {
+    pub fn is_a() -> Self {
+        Self::default()
+    }
+
+    /// `is_a` and `part_of`, as used by most enrichment tools.
+    pub fn is_a_part_of() -> Self {
+        Self::is_a().with(RelationshipType::PartOf)
+    }
+
+    pub fn with(mut self, relationship: RelationshipType) -> Self {
+        self.relationships.push(relationship);
+        self
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GoOntology {
+    relations: Relations,
+    terms: Vec<GoTerm>,
+    /// Term index by ID and alternative ID.
+    index: HashMap<GoTermID, u32>,
+    parents: Vec<Vec<u32>>,
+    children: Vec<Vec<u32>>,
+    /// Sorted ancestors of each term, excluding itself.
+    ancestors: Vec<Vec<u32>>,
+    /// Shortest and longest path from each term to a root.
+    levels: Vec<u32>,
+    depths: Vec<u32>,
+}
+
+impl GoOntology {
+    /// Builds the graph. Obsolete terms are kept for lookups but have no edges, and edges to
+    /// unknown terms or cycles are errors.
+    pub fn new(terms: Vec<GoTerm>, relations: Relations) -> Result<Self> {
+        let mut index = HashMap::new();
+        for (i, term) in terms.iter().enumerate() {
+            if index.insert(term.id().to_string(), i as u32).is_some() {
+                bail!("duplicate term: {}", term.id());
+            }
+        }
+        for (i, term) in terms.iter().enumerate() {
+            for alt_id in term.alt_ids() {
+                index.entry(alt_id.clone()).or_insert(i as u32);
+            }
+        }
+
+        let mut parents = vec![Vec::new(); terms.len()];
+        let mut children = vec![Vec::new(); terms.len()];
+        for (i, term) in terms.iter().enumerate().filter(|(_, t)| !t.is_obsolete()) {
+            let targets = term.is_a().iter().chain(
+                relations
+                    .relationships
+                    .iter()
+                    .flat_map(|relationship| term.related(relationship)),
+            );
+            for target in targets {
+                let &parent = index
+                    .get(target)
+                    .ok_or_else(|| anyhow!("{}: unknown parent {}", term.id(), target))?;
+                if !parents[i].contains(&parent) {
+                    parents[i].push(parent);
+                    children[parent as usize].push(i as u32);
+                }
+            }
+        }
+
+        let mut ontology = Self {
+            relations,
+            terms,
+            index,
+            parents,
+            children,
+            ancestors: Vec::new(),
+            levels: Vec::new(),
+            depths: Vec::new(),
+        };
+        ontology.compute_closures()?;
+        Ok(ontology)
+    }
+
+    /// Fills ancestors, levels and depths, visiting parents before their children.
+    fn compute_closures(&mut self) -> Result<()> {
+        let n = self.terms.len();
+        let mut pending: Vec<usize> = self.parents.iter().map(|p| p.len()).collect();
+        let mut queue: VecDeque<usize> = (0..n).filter(|&i| pending[i] == 0).collect();
+        self.ancestors = vec![Vec::new(); n];
+        self.levels = vec![0; n];
+        self.depths = vec![0; n];
+        let mut visited = 0;
+        while let Some(i) = queue.pop_front() {
+            visited += 1;
+            let mut ancestors = BTreeSet::new();
+            for &parent in &self.parents[i] {
+                let parent = parent as usize;
+                ancestors.insert(parent as u32);
+                ancestors.extend(&self.ancestors[parent]);
+            }
+            if let Some(level) = self.parents[i]
+                .iter()
+                .map(|&p| self.levels[p as usize])
+                .min()
+            {
+                self.levels[i] = level + 1;
+            }
+            if let Some(depth) = self.parents[i]
+                .iter()
+                .map(|&p| self.depths[p as usize])
+                .max()
+            {
+                self.depths[i] = depth + 1;
+            }
+            self.ancestors[i] = ancestors.into_iter().collect();
+            for &child in &self.children[i] {
+                pending[child as usize] -= 1;
+                if pending[child as usize] == 0 {
+                    queue.push_back(child as usize);
+                }
+            }
+        }
+        if visited < n {
+            let term = (0..n).find(|&i| pending[i] > 0).unwrap();
+            bail!("cycle through {}", self.terms[term].id());
+        }
+        Ok(())
+    }
+
+    pub fn relations(&self) -> &Relations {
+        &self.relations
+    }
+
+    pub fn len(&self) -> usize {
+        self.terms.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.terms.is_empty()
+    }
+
+    pub fn terms(&self) -> &[GoTerm] {
+        &self.terms
+    }
+
+    /// The term with this ID or alternative ID.
+    pub fn term(&self, id: &str) -> Option<&GoTerm> {
+        self.index.get(id).map(|&i| &self.terms[i as usize])
+    }
+
+    fn ids(&self, indices: impl IntoIterator<Item = u32>) -> Vec<&str> {
+        indices
+            .into_iter()
+            .map(|i| self.terms[i as usize].id())
+            .collect()
+    }
+
+    fn lookup(&self, id: &str) -> Option<usize> {
+        self.index.get(id).map(|&i| i as usize)
+    }
+
+    pub fn parents(&self, id: &str) -> Vec<&str> {
+        self.lookup(id)
+            .map_or_else(Vec::new, |i| self.ids(self.parents[i].iter().copied()))
+    }
+
+    pub fn children(&self, id: &str) -> Vec<&str> {
+        self.lookup(id)
+            .map_or_else(Vec::new, |i| self.ids(self.children[i].iter().copied()))
+    }
+
+    /// All ancestors of a term, excluding itself.
+    pub fn ancestors(&self, id: &str) -> Vec<&str> {
+        self.lookup(id)
+            .map_or_else(Vec::new, |i| self.ids(self.ancestors[i].iter().copied()))
+    }
+
+    /// All descendants of a term, excluding itself.
+    pub fn descendants(&self, id: &str) -> Vec<&str> {
+        let Some(start) = self.lookup(id) else {
+            return Vec::new();
+        };
+        let mut seen = BTreeSet::new();
+        let mut queue = VecDeque::from([start]);
+        while let Some(i) = queue.pop_front() {
+            for &child in &self.children[i] {
+                if seen.insert(child) {
+                    queue.push_back(child as usize);
+                }
+            }
+        }
+        self.ids(seen)
+    }
+
+    pub fn is_ancestor(&self, ancestor: &str, id: &str) -> bool {
+        match (self.lookup(ancestor), self.lookup(id)) {
+            (Some(a), Some(i)) => self.ancestors[i].binary_search(&(a as u32)).is_ok(),
+            _ => false,
+        }
+    }
+
+    /// Length of the shortest path to a root; roots are at level 0.
+    pub fn level(&self, id: &str) -> Option<u32> {
+        self.lookup(id).map(|i| self.levels[i])
+    }
+
+    /// Length of the longest path to a root; roots are at depth 0.
+    pub fn depth(&self, id: &str) -> Option<u32> {
+        self.lookup(id).map(|i| self.depths[i])
+    }
+
+    /// Common ancestors of the terms, counting each term as its own ancestor, that are not
+    /// ancestors of another common ancestor.
+    pub fn lowest_common_ancestors(&self, ids: &[&str]) -> Vec<&str> {
+        let Some(indices) = ids
+            .iter()
+            .map(|id| self.lookup(id))
+            .collect::<Option<Vec<_>>>()
+        else {
+            return Vec::new();
+        };
+        let with_self = |i: usize| -> BTreeSet<u32> {
+            let mut set: BTreeSet<u32> = self.ancestors[i].iter().copied().collect();
+            set.insert(i as u32);
+            set
+        };
+        let Some(mut common) = indices
+            .iter()
+            .map(|&i| with_self(i))
+            .reduce(|a, b| a.intersection(&b).copied().collect())
+        else {
+            return Vec::new();
+        };
+        let redundant: BTreeSet<u32> = common
+            .iter()
+            .flat_map(|&i| self.ancestors[i as usize].iter().copied())
+            .collect();
+        common.retain(|i| !redundant.contains(i));
+        self.ids(common)
+    }
+
+    /// Terms without parents that are not obsolete, one per namespace in GO.
+    pub fn roots(&self) -> Vec<&str> {
+        self.ids((0..self.terms.len() as u32).filter(|&i| {
+            self.parents[i as usize].is_empty() && !self.terms[i as usize].is_obsolete()
+        }))
+    }
+
+    pub fn namespace_terms(
+        &self,
+        namespace: GoTermNamespace,
+    ) -> impl Iterator<Item = &GoTerm> + '_ {
+        self.terms
+            .iter()
+            .filter(move |t| t.namespace() == namespace)
+    }
+
+    /// Terms by namespace.
+    pub fn partition(&self) -> HashMap<GoTermNamespace, Vec<&GoTerm>> {
+        let mut partition: HashMap<GoTermNamespace, Vec<&GoTerm>> = HashMap::new();
+        for term in &self.terms {
+            partition.entry(term.namespace()).or_default().push(term);
+        }
+        partition
+    }
+
+    /// Writes the graph as a binary cache, read back with [`GoOntology::read_cache`].
+    pub fn write_cache<W: Write>(&self, mut writer: W) -> Result<()> {
+        bincode::serialize_into(&mut writer, &CACHE_VERSION)?;
+        bincode::serialize_into(&mut writer, self)?;
+        Ok(())
+    }
+
+    pub fn read_cache<R: Read>(mut reader: R) -> Result<Self> {
+        let version: u32 = bincode::deserialize_from(&mut reader)?;
+        if version != CACHE_VERSION {
+            bail!(
+                "unsupported cache version {}, expected {}",
+                version,
+                CACHE_VERSION
+            );
+        }
+        Ok(bincode::deserialize_from(reader)?)
+    }
+}
+
+#[cfg(test)]
+mod test_ontology {
+    use super::*;
+    use crate::functional_annotations::obo::read_obo;
+
+    const OBO: &str = "[Term]
+id: GO:0008150
+name: biological_process
+namespace: biological_process
+
+[Term]
+id: GO:0009987
+name: cellular process
+namespace: biological_process
+is_a: GO:0008150
+
+[Term]
+id: GO:0008152
+name: metabolic process
+namespace: biological_process
+is_a: GO:0008150
+
+[Term]
+id: GO:0044237
+name: cellular metabolic process
+namespace: biological_process
+alt_id: GO:0044236
+is_a: GO:0008152
+is_a: GO:0009987
+
+[Term]
+id: GO:0006096
+name: glycolytic process
+namespace: biological_process
+is_a: GO:0044237
+
+[Term]
+id: GO:0005575
+name: cellular_component
+namespace: cellular_component
+
+[Term]
+id: GO:0005737
+name: cytoplasm
+namespace: cellular_component
+is_a: GO:0005575
+
+[Term]
+id: GO:0005829
+name: cytosol
+namespace: cellular_component
+is_a: GO:0005575
+relationship: part_of GO:0005737
+";
+
+    fn ontology(relations: Relations) -> GoOntology {
+        GoOntology::new(read_obo(OBO.as_bytes()).unwrap().terms, relations).unwrap()
+    }
+
+    #[test]
+    fn test_closures() {
+        let go = ontology(Relations::is_a());
+        assert_eq!(
+            go.ancestors("GO:0006096"),
+            vec!["GO:0008150", "GO:0009987", "GO:0008152", "GO:0044237"]
+        );
+        assert_eq!(
+            go.descendants("GO:0009987"),
+            vec!["GO:0044237", "GO:0006096"]
+        );
+        assert!(go.is_ancestor("GO:0008152", "GO:0044236"));
+        assert!(!go.is_ancestor("GO:0005737", "GO:0005829"));
+        assert_eq!(
+            (go.level("GO:0006096"), go.depth("GO:0006096")),
+            (Some(3), Some(3))
+        );
+        assert_eq!(go.depth("GO:0005829"), Some(1));
+        assert_eq!(go.roots(), vec!["GO:0008150", "GO:0005575"]);
+        assert_eq!(
+            go.lowest_common_ancestors(&["GO:0006096", "GO:0009987"]),
+            vec!["GO:0009987"]
+        );
+        assert_eq!(
+            go.lowest_common_ancestors(&["GO:0009987", "GO:0008152"]),
+            vec!["GO:0008150"]
+        );
+        assert_eq!(go.partition()[&GoTermNamespace::CellerComponent].len(), 3);
+
+        let go = ontology(Relations::is_a_part_of());
+        assert!(go.is_ancestor("GO:0005737", "GO:0005829"));
+        assert_eq!(
+            (go.level("GO:0005829"), go.depth("GO:0005829")),
+            (Some(1), Some(2))
+        );
+    }
+
+    #[test]
+    fn test_cache_and_errors() {
+        let go = ontology(Relations::is_a_part_of());
+        let mut cache = Vec::new();
+        go.write_cache(&mut cache).unwrap();
+        let cached = GoOntology::read_cache(cache.as_slice()).unwrap();
+        assert_eq!(cached.relations(), &Relations::is_a_part_of());
+        assert_eq!(cached.ancestors("GO:0005829"), go.ancestors("GO:0005829"));
+        assert_eq!(
+            cached.term("GO:0044236").unwrap().name(),
+            "cellular metabolic process"
+        );
+
+        let mut a = GoTerm::new("GO:1", "a", GoTermNamespace::BiologicalProcess);
+        let mut b = GoTerm::new("GO:2", "b", GoTermNamespace::BiologicalProcess);
+        a.is_a.push("GO:2".to_string());
+        b.is_a.push("GO:1".to_string());
+        let err = GoOntology::new(vec![a.clone(), b], Relations::is_a()).unwrap_err();
+        assert!(err.to_string().starts_with("cycle through"));
+        let err = GoOntology::new(vec![a], Relations::is_a()).unwrap_err();
+        assert_eq!(err.to_string(), "GO:1: unknown parent GO:2");
+    }
+}
@@ -0,0 +1,261 @@
+//! Gene to GO term annotations, propagated up the ontology by the true path rule or reduced
+//! to their most specific terms.
+
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+
+use super::gaf::GoAssociation;
+use super::go_term::{EvidenceCode, GoTermID};
+use super::ontology::GoOntology;
+
+/// Evidence codes whose associations are kept.
+#[derive(Debug, Clone)]
+pub struct EvidenceFilter {
+    codes: HashSet<EvidenceCode>,
+}
+
+impl Default for EvidenceFilter {
+    fn default() -> Self {
+        Self::all()
+    }
+}
+
+impl EvidenceFilter {
+    pub fn all() -> Self {
+        Self {
+            codes: EvidenceCode::ALL.into_iter().collect(),
+        }
+    }
+
+    /// Codes of the categories, such as `Experimental` or `High_Throughput`.
+    pub fn categories(categories: &[&str]) -> Self {
+        let codes = EvidenceCode::ALL
+            .into_iter()
+            .filter(|code| categories.contains(&code.category().as_str()))
+            .collect();
+        Self { codes }
+    }
+
+    /// Experimental codes: EXP, IDA, IPI, IMP, IGI and IEP.
+    pub fn experimental() -> Self {
+        Self::categories(&["Experimental"])
+    }
+
+    /// All codes but IEA, leaving out electronic annotations such as InterProScan's.
+    pub fn manual() -> Self {
+        Self::all().exclude(EvidenceCode::IEA)
+    }
+
+    pub fn include(mut self, code: EvidenceCode) -> Self {
+        self.codes.insert(code);
+        self
+    }
+
+    pub fn exclude(mut self, code: EvidenceCode) -> Self {
+        self.codes.remove(&code);
+        self
+    }
+
+    pub fn accepts(&self, code: EvidenceCode) -> bool {
+        self.codes.contains(&code)
+    }
+}
+
+/// Terms annotated to each gene, and the terms the gene is annotated `NOT` to.
+#[derive(Debug, Clone, Default)]
+pub struct GeneAnnotations {
+    terms: BTreeMap<String, BTreeSet<GoTermID>>,
+    negated: BTreeMap<String, BTreeSet<GoTermID>>,
+}
+
+impl GeneAnnotations {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Annotations of the associations accepted by the filter, keyed by object id. Alternative
+    /// term ids resolve to their primary ids; obsolete and unknown terms, and associations
+    /// without a GO evidence code, are skipped.
+    pub fn from_associations(
+        associations: &[GoAssociation],
+        filter: &EvidenceFilter,
+        ontology: &GoOntology,
+    ) -> Self {
+        let mut annotations = Self::new();
+        for association in associations {
+            if !association
+                .evidence_code
+                .is_some_and(|code| filter.accepts(code))
+            {
+                continue;
+            }
+            match ontology.term(&association.go_id) {
+                Some(term) if !term.is_obsolete() => {
+                    annotations.insert(&association.object_id, term.id(), association.negated)
+                }
+                _ => {}
+            }
+        }
+        annotations
+    }
+
+    pub fn insert(&mut self, gene: &str, go_id: &str, negated: bool) {
+        let map = if negated {
+            &mut self.negated
+        } else {
+            &mut self.terms
+        };
+        map.entry(gene.to_string())
+            .or_default()
+            .insert(go_id.to_string());
+    }
+
+    /// Genes with at least one positive annotation.
+    pub fn genes(&self) -> impl Iterator<Item = &str> {
+        self.terms.keys().map(String::as_str)
+    }
+
+    pub fn len(&self) -> usize {
+        self.terms.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.terms.is_empty()
+    }
+
+    pub fn terms(&self, gene: &str) -> Option<&BTreeSet<GoTermID>> {
+        self.terms.get(gene)
+    }
+
+    pub fn negated(&self, gene: &str) -> Option<&BTreeSet<GoTermID>> {
+        self.negated.get(gene)
+    }
+
+    /// Terms of a gene and all their ancestors. A `NOT` annotation removes its term and its
+    /// descendants, even where a positive annotation implies them.
+    pub fn propagated<'a>(&'a self, gene: &str, ontology: &'a GoOntology) -> BTreeSet<&'a str> {
+        let mut terms = BTreeSet::new();
+        for id in self.terms.get(gene).into_iter().flatten() {
+            terms.insert(id.as_str());
+            terms.extend(ontology.ancestors(id));
+        }
+        for id in self.negated.get(gene).into_iter().flatten() {
+            terms.remove(id.as_str());
+            for descendant in ontology.descendants(id) {
+                terms.remove(descendant);
+            }
+        }
+        terms
+    }
+
+    /// Annotations of every gene propagated to the ancestors of its terms.
+    pub fn propagate(&self, ontology: &GoOntology) -> Self {
+        let terms = self
+            .terms
+            .keys()
+            .map(|gene| {
+                let terms = self.propagated(gene, ontology);
+                (
+                    gene.clone(),
+                    terms.into_iter().map(str::to_string).collect(),
+                )
+            })
+            .collect();
+        Self {
+            terms,
+            negated: self.negated.clone(),
+        }
+    }
+
+    /// The most specific terms of a gene: its terms that are no ancestor of another of them.
+    pub fn non_redundant<'a>(&'a self, gene: &str, ontology: &GoOntology) -> Vec<&'a str> {
+        let Some(terms) = self.terms.get(gene) else {
+            return Vec::new();
+        };
+        terms
+            .iter()
+            .filter(|&id| !terms.iter().any(|other| ontology.is_ancestor(id, other)))
+            .map(String::as_str)
+            .collect()
+    }
+
+    /// Genes annotated to each term.
+    pub fn term_genes(&self) -> BTreeMap<&str, BTreeSet<&str>> {
+        let mut genes: BTreeMap<&str, BTreeSet<&str>> = BTreeMap::new();
+        for (gene, terms) in &self.terms {
+            for term in terms {
+                genes.entry(term).or_default().insert(gene);
+            }
+        }
+        genes
+    }
+}
+
+#[cfg(test)]
+mod test_propagation {
+    use super::*;
+    use crate::functional_annotations::gaf::read_gaf;
+    use crate::functional_annotations::obo::read_obo;
+    use crate::functional_annotations::ontology::Relations;
+
+    const OBO: &str = "[Term]
+id: GO:0008150
+name: biological_process
+namespace: biological_process
+
+[Term]
+id: GO:0008152
+name: metabolic process
+namespace: biological_process
+is_a: GO:0008150
+
+[Term]
+id: GO:0044237
+name: cellular metabolic process
+namespace: biological_process
+alt_id: GO:0044236
+is_a: GO:0008152
+
+[Term]
+id: GO:0006096
+name: glycolytic process
+namespace: biological_process
+is_a: GO:0044237
+";
+
+    const GAF: &str = "g1\tg1\tG1\tinvolved_in\tGO:0006096\tPMID:1\tIDA\t\tP\t\t\tgene\ttaxon:9606\t20230101\tX\t\t
+g1\tg1\tG1\tinvolved_in\tGO:0008152\tPMID:1\tIEA\t\tP\t\t\tgene\ttaxon:9606\t20230101\tX\t\t
+g2\tg2\tG2\tinvolved_in\tGO:0006096\tPMID:1\tIEA\t\tP\t\t\tgene\ttaxon:9606\t20230101\tX\t\t
+g2\tg2\tG2\tNOT|involved_in\tGO:0044236\tPMID:1\tIMP\t\tP\t\t\tgene\ttaxon:9606\t20230101\tX\t\t
+";
+
+    #[test]
+    fn test_propagation() {
+        let ontology =
+            GoOntology::new(read_obo(OBO.as_bytes()).unwrap().terms, Relations::is_a()).unwrap();
+        let associations = read_gaf(GAF.as_bytes()).unwrap();
+
+        let annotations =
+            GeneAnnotations::from_associations(&associations, &EvidenceFilter::all(), &ontology);
+        assert_eq!(
+            annotations.propagated("g1", &ontology),
+            BTreeSet::from(["GO:0006096", "GO:0008150", "GO:0008152", "GO:0044237"])
+        );
+        assert_eq!(annotations.non_redundant("g1", &ontology), ["GO:0006096"]);
+        // The NOT annotation, by alternative id, removes the glycolysis annotation too.
+        assert_eq!(
+            annotations.propagated("g2", &ontology),
+            BTreeSet::from(["GO:0008150", "GO:0008152"])
+        );
+        let propagated = annotations.propagate(&ontology);
+        assert_eq!(propagated.term_genes()["GO:0008150"].len(), 2);
+
+        let experimental = GeneAnnotations::from_associations(
+            &associations,
+            &EvidenceFilter::experimental(),
+            &ontology,
+        );
+        assert_eq!(experimental.genes().collect::<Vec<_>>(), ["g1"]);
+        assert!(EvidenceFilter::manual().accepts(EvidenceCode::IBA));
+        assert!(!EvidenceFilter::manual().accepts(EvidenceCode::IEA));
+    }
+}
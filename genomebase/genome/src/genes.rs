@@ -0,0 +1,542 @@
+//! Genes with their transcripts, nomenclature history and functional annotations, mirroring
+//! the proto `Gene`.
+
+use std::collections::HashSet;
+use std::error::Error;
+use std::fmt;
+
+use anyhow::{anyhow, Result};
+use common::User;
+use gff::attributes::{Attributes, Tag};
+use gff::gene_model::{GeneModel, TranscriptModel, CDS_TYPE, EXON_TYPE};
+use gff::{GffRecord, Phase, Strand};
+use serde::{Deserialize, Serialize};
+
+use crate::functional_annotations::annotations::{
+    user_from_proto, user_to_proto, FunctionalAnnotations,
+};
+use crate::proto;
+use crate::transcripts::{Transcript, TranscriptBuilder};
+
+const PRODUCT_TAG: &str = "product";
+
+/// A name given to a gene. A gene keeps every name it has had, the latest last.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Nomenclature {
+    pub name: String,
+    pub product: String,
+    pub doi: Option<String>,
+    pub assigned_by: Option<User>,
+}
+
+/// A transcript of a gene with its child features, as served to clients.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GeneTranscript {
+    pub id: String,
+    pub is_primary: bool,
+    pub transcript_type: String,
+    pub strand: Option<Strand>,
+    pub start: u64,
+    pub end: u64,
+    /// Exons, CDS and other child features such as UTRs.
+    pub features: Vec<GffRecord>,
+    /// Attributes of the transcript feature, which the proto message does not carry.
+    pub attributes: Attributes,
+    pub annotations: FunctionalAnnotations,
+}
+
+fn total_length<'a>(features: impl Iterator<Item = &'a GffRecord>) -> u64 {
+    features.map(|f| f.len() as u64).sum()
+}
+
+impl GeneTranscript {
+    pub fn cds_length(&self) -> u64 {
+        total_length(self.features.iter().filter(|f| f.r#type == CDS_TYPE))
+    }
+
+    /// Length of the exons, or of the CDS for transcripts annotated without exons.
+    pub fn spliced_length(&self) -> u64 {
+        match self.features.iter().any(|f| f.r#type == EXON_TYPE) {
+            true => total_length(self.features.iter().filter(|f| f.r#type == EXON_TYPE)),
+            false => self.cds_length(),
+        }
+    }
+
+    /// The transcript model built from exon and CDS features.
+    pub fn model(&self, gene_id: &str) -> Result<Transcript> {
+        let strand = self
+            .strand
+            .ok_or_else(|| anyhow!("{} has no strand", self.id))?;
+        let seqid = self
+            .features
+            .first()
+            .map(|f| f.seqid.clone())
+            .ok_or_else(|| anyhow!("{} has no features", self.id))?;
+
+        let mut features: Vec<&GffRecord> = self.features.iter().collect();
+        features.sort_by_key(|f| f.start);
+        let builder =
+            TranscriptBuilder::new(&self.id, gene_id, seqid, strand).span(self.start, self.end);
+        let builder = features
+            .iter()
+            .fold(builder, |builder, f| match f.r#type.as_str() {
+                EXON_TYPE => builder.exon(f.start as u64, f.end as u64),
+                CDS_TYPE => builder.cds(f.start as u64, f.end as u64),
+                _ => builder,
+            });
+        builder.build().map_err(|e| anyhow!("{}: {}", self.id, e))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum GeneError {
+    MixedStrand { transcript: String },
+    DuplicateTranscript(String),
+    MultiplePrimaryTranscripts,
+}
+
+impl fmt::Display for GeneError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::MixedStrand { transcript } => {
+                write!(f, "transcript {} is on another strand", transcript)
+            }
+            Self::DuplicateTranscript(id) => write!(f, "duplicate transcript: {}", id),
+            Self::MultiplePrimaryTranscripts => write!(f, "more than one primary transcript"),
+        }
+    }
+}
+
+impl Error for GeneError {}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Gene {
+    pub id: String,
+    pub nomenclatures: Vec<Nomenclature>,
+    pub transcripts: Vec<GeneTranscript>,
+}
+
+impl Gene {
+    pub fn new(id: &str) -> Self {
+        Self {
+            id: id.to_string(),
+            nomenclatures: Vec::new(),
+            transcripts: Vec::new(),
+        }
+    }
+
+    /// The current name of the gene.
+    pub fn nomenclature(&self) -> Option<&Nomenclature> {
+        self.nomenclatures.last()
+    }
+
+    pub fn primary_transcript(&self) -> Option<&GeneTranscript> {
+        self.transcripts.iter().find(|t| t.is_primary)
+    }
+
+    /// Lowest start and highest end of the transcripts.
+    pub fn span(&self) -> Option<(u64, u64)> {
+        let start = self.transcripts.iter().map(|t| t.start).min()?;
+        let end = self.transcripts.iter().map(|t| t.end).max()?;
+        Some((start, end))
+    }
+
+    /// The strand shared by all transcripts with a strand.
+    pub fn strand(&self) -> Result<Option<Strand>, GeneError> {
+        let mut strand = None;
+        for transcript in &self.transcripts {
+            match (strand, transcript.strand) {
+                (Some(expected), Some(found)) if expected != found => {
+                    return Err(GeneError::MixedStrand {
+                        transcript: transcript.id.clone(),
+                    })
+                }
+                (None, found) => strand = found,
+                _ => {}
+            }
+        }
+        Ok(strand)
+    }
+
+    pub fn check(&self) -> Vec<GeneError> {
+        let mut errors = Vec::new();
+        if let Err(e) = self.strand() {
+            errors.push(e);
+        }
+        let mut ids = HashSet::new();
+        for transcript in &self.transcripts {
+            if !ids.insert(transcript.id.as_str()) {
+                errors.push(GeneError::DuplicateTranscript(transcript.id.clone()));
+            }
+        }
+        if self.transcripts.iter().filter(|t| t.is_primary).count() > 1 {
+            errors.push(GeneError::MultiplePrimaryTranscripts);
+        }
+        errors
+    }
+
+    /// Transcript models of all transcripts.
+    pub fn models(&self) -> Result<Vec<Transcript>> {
+        self.transcripts.iter().map(|t| t.model(&self.id)).collect()
+    }
+}
+
+fn strand_from_proto(strand: i32) -> Result<Option<Strand>> {
+    match proto::Strand::try_from(strand) {
+        Ok(proto::Strand::Unspecified) => Ok(None),
+        Ok(proto::Strand::Plus) => Ok(Some(Strand::Forward)),
+        Ok(proto::Strand::Minus) => Ok(Some(Strand::Reverse)),
+        Err(_) => Err(anyhow!("unsupported strand: {}", strand)),
+    }
+}
+
+fn strand_to_proto(strand: Option<Strand>) -> i32 {
+    let strand = match strand {
+        None => proto::Strand::Unspecified,
+        Some(Strand::Forward) => proto::Strand::Plus,
+        Some(Strand::Reverse) => proto::Strand::Minus,
+    };
+    strand as i32
+}
+
+fn coordinate(value: i32) -> Result<u64> {
+    u64::try_from(value).map_err(|_| anyhow!("invalid coordinate: {}", value))
+}
+
+/// Proto coordinates are `int32`, so positions past `i32::MAX` cannot be written.
+fn proto_coordinate(value: u64) -> Result<i32> {
+    i32::try_from(value).map_err(|_| anyhow!("coordinate out of the proto range: {}", value))
+}
+
+/// Empty optional fields are written as empty strings, the proto default.
+fn optional_field(value: &str) -> Option<&str> {
+    Some(value).filter(|v| !v.is_empty())
+}
+
+impl TryFrom<proto::GffRecord> for GffRecord {
+    type Error = anyhow::Error;
+
+    fn try_from(record: proto::GffRecord) -> Result<Self> {
+        Ok(Self {
+            strand: strand_from_proto(record.strand)?,
+            score: optional_field(&record.score)
+                .map(|s| s.parse().map_err(|_| anyhow!("invalid score: {}", s)))
+                .transpose()?,
+            phase: optional_field(&record.phase)
+                .map(|p| p.parse::<Phase>().map_err(|e| anyhow!(e)))
+                .transpose()?,
+            start: u32::try_from(record.start)?,
+            end: u32::try_from(record.end)?,
+            seqid: record.seqname.parse().map_err(|e: String| anyhow!(e))?,
+            source: record.source,
+            r#type: record.r#type,
+            attributes: Attributes::default(),
+        })
+    }
+}
+
+/// Attributes are not part of the proto message and are dropped.
+impl TryFrom<GffRecord> for proto::GffRecord {
+    type Error = anyhow::Error;
+
+    fn try_from(record: GffRecord) -> Result<Self> {
+        Ok(Self {
+            start: proto_coordinate(record.start.into())?,
+            end: proto_coordinate(record.end.into())?,
+            seqname: record.seqid.into(),
+            source: record.source,
+            r#type: record.r#type,
+            score: record.score.map(|s| s.to_string()).unwrap_or_default(),
+            strand: strand_to_proto(record.strand),
+            phase: record
+                .phase
+                .map(|p| p.as_ref().to_string())
+                .unwrap_or_default(),
+        })
+    }
+}
+
+impl TryFrom<proto::Transcript> for GeneTranscript {
+    type Error = anyhow::Error;
+
+    fn try_from(transcript: proto::Transcript) -> Result<Self> {
+        let annotations = FunctionalAnnotations::from_proto(&transcript)?;
+        Ok(Self {
+            strand: strand_from_proto(transcript.strand)?,
+            start: coordinate(transcript.start)?,
+            end: coordinate(transcript.end)?,
+            features: transcript
+                .child_structure
+                .into_iter()
+                .map(GffRecord::try_from)
+                .collect::<Result<_>>()?,
+            id: transcript.id,
+            is_primary: transcript.is_primary,
+            transcript_type: transcript.transcript_type,
+            attributes: Attributes::default(),
+            annotations,
+        })
+    }
+}
+
+impl TryFrom<GeneTranscript> for proto::Transcript {
+    type Error = anyhow::Error;
+
+    fn try_from(transcript: GeneTranscript) -> Result<Self> {
+        let mut message = Self {
+            start: proto_coordinate(transcript.start)?,
+            end: proto_coordinate(transcript.end)?,
+            child_structure: transcript
+                .features
+                .into_iter()
+                .map(proto::GffRecord::try_from)
+                .collect::<Result<_>>()?,
+            id: transcript.id,
+            is_primary: transcript.is_primary,
+            transcript_type: transcript.transcript_type,
+            strand: strand_to_proto(transcript.strand),
+            ..Default::default()
+        };
+        transcript.annotations.into_proto(&mut message)?;
+        Ok(message)
+    }
+}
+
+impl From<&TranscriptModel> for GeneTranscript {
+    fn from(model: &TranscriptModel) -> Self {
+        let transcript = &model.transcript;
+        let mut features: Vec<GffRecord> = model.records().skip(1).cloned().collect();
+        features.sort_by_key(|f| (f.start, f.end));
+        Self {
+            id: model.id().to_string(),
+            is_primary: false,
+            transcript_type: transcript.r#type.clone(),
+            strand: transcript.strand,
+            start: transcript.start as u64,
+            end: transcript.end as u64,
+            features,
+            attributes: transcript.attributes.clone(),
+            annotations: FunctionalAnnotations::default(),
+        }
+    }
+}
+
+/// The gene `Name` becomes its nomenclature, with the `product` of the first transcript
+/// that has one.
+impl From<&GeneModel> for Gene {
+    fn from(model: &GeneModel) -> Self {
+        let product = model.transcripts.iter().find_map(|t| {
+            t.transcript
+                .attribute(&Tag::from(PRODUCT_TAG))
+                .and_then(|v| v.first())
+        });
+        let nomenclatures = model
+            .gene
+            .name()
+            .map(|name| Nomenclature {
+                name: name.to_string(),
+                product: product.unwrap_or_default().to_string(),
+                doi: None,
+                assigned_by: None,
+            })
+            .into_iter()
+            .collect();
+        Self {
+            id: model.id().to_string(),
+            nomenclatures,
+            transcripts: model.transcripts.iter().map(GeneTranscript::from).collect(),
+        }
+    }
+}
+
+impl From<proto::Nomenclature> for Nomenclature {
+    fn from(nomenclature: proto::Nomenclature) -> Self {
+        Self {
+            name: nomenclature.name,
+            product: nomenclature.product,
+            doi: optional_field(&nomenclature.doi).map(|d| d.to_string()),
+            assigned_by: nomenclature.assigned_by.map(user_from_proto),
+        }
+    }
+}
+
+impl From<Nomenclature> for proto::Nomenclature {
+    fn from(nomenclature: Nomenclature) -> Self {
+        Self {
+            name: nomenclature.name,
+            product: nomenclature.product,
+            doi: nomenclature.doi.unwrap_or_default(),
+            assigned_by: nomenclature.assigned_by.map(user_to_proto),
+        }
+    }
+}
+
+impl TryFrom<proto::Gene> for Gene {
+    type Error = anyhow::Error;
+
+    fn try_from(gene: proto::Gene) -> Result<Self> {
+        Ok(Self {
+            id: gene.id,
+            nomenclatures: gene
+                .nomenclatures
+                .into_iter()
+                .map(Nomenclature::from)
+                .collect(),
+            transcripts: gene
+                .transcripts
+                .into_iter()
+                .map(GeneTranscript::try_from)
+                .collect::<Result<_>>()?,
+        })
+    }
+}
+
+impl TryFrom<Gene> for proto::Gene {
+    type Error = anyhow::Error;
+
+    fn try_from(gene: Gene) -> Result<Self> {
+        Ok(Self {
+            id: gene.id,
+            nomenclatures: gene
+                .nomenclatures
+                .into_iter()
+                .map(proto::Nomenclature::from)
+                .collect(),
+            transcripts: gene
+                .transcripts
+                .into_iter()
+                .map(proto::Transcript::try_from)
+                .collect::<Result<_>>()?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test_genes {
+    use prost::Message;
+
+    use super::*;
+    use crate::functional_annotations::go_term::{EvidenceCode, GoTermNamespace};
+
+    fn feature(r#type: &str, start: i32, end: i32, phase: &str) -> proto::GffRecord {
+        proto::GffRecord {
+            seqname: "1".to_string(),
+            source: "maker".to_string(),
+            r#type: r#type.to_string(),
+            start,
+            end,
+            score: String::new(),
+            strand: proto::Strand::Minus as i32,
+            phase: phase.to_string(),
+        }
+    }
+
+    fn proto_gene() -> proto::Gene {
+        let author = proto::Author {
+            name: "A. Curator".to_string(),
+            email: "curator@example.org".to_string(),
+            orc_id: "0000-0002-1825-0097".to_string(),
+        };
+        let mut transcript = proto::Transcript {
+            id: "tx1".to_string(),
+            is_primary: true,
+            transcript_type: "mRNA".to_string(),
+            strand: proto::Strand::Minus as i32,
+            start: 100,
+            end: 400,
+            child_structure: vec![
+                feature("exon", 100, 200, ""),
+                feature("CDS", 150, 200, "2"),
+                feature("three_prime_UTR", 100, 149, ""),
+                feature("exon", 301, 400, ""),
+                feature("CDS", 301, 350, "0"),
+            ],
+            kog: Some(proto::Kog {
+                accession: "KOG0001".to_string(),
+                description: "Ubiquitin".to_string(),
+                category: "O".to_string(),
+            }),
+            ..Default::default()
+        };
+        transcript.go_terms.push(proto::GoTerm {
+            accession: "GO:0005515".to_string(),
+            description: "protein binding".to_string(),
+            namespace: "MF".to_string(),
+            evidence_code: "IPI".to_string(),
+            assigned_by: Some(author.clone()),
+        });
+        transcript.domains.push(proto::Domain {
+            accession: "PF00240".to_string(),
+            description: "Ubiquitin family".to_string(),
+            start: 1,
+            end: 30,
+            anlysis: proto::Analysis::Pfam as i32,
+        });
+        let alternative = proto::Transcript {
+            id: "tx2".to_string(),
+            strand: proto::Strand::Minus as i32,
+            start: 90,
+            end: 380,
+            ..Default::default()
+        };
+        proto::Gene {
+            id: "gene1".to_string(),
+            nomenclatures: vec![proto::Nomenclature {
+                name: "UBQ1".to_string(),
+                product: "ubiquitin".to_string(),
+                doi: "10.1000/182".to_string(),
+                assigned_by: Some(author),
+            }],
+            transcripts: vec![transcript, alternative],
+        }
+    }
+
+    #[test]
+    fn test_proto_round_trip() {
+        let message = proto_gene();
+        let decoded = proto::Gene::decode(message.encode_to_vec().as_slice()).unwrap();
+        let gene = Gene::try_from(decoded).unwrap();
+        assert_eq!(gene.transcripts[0].features[1].phase, Some(Phase::Two));
+        assert_eq!(proto::Gene::try_from(gene).unwrap(), message);
+    }
+
+    #[test]
+    fn test_invalid_go_annotation() {
+        let mut message = proto_gene();
+        message.transcripts[0].go_terms[0].evidence_code = "XYZ".to_string();
+        let err = Gene::try_from(message).unwrap_err();
+        assert_eq!(err.to_string(), "GO:0005515: invalid evidence code: XYZ");
+
+        let mut gene = Gene::try_from(proto_gene()).unwrap();
+        let annotation = &gene.transcripts[0].annotations.go_terms[0];
+        assert_eq!(annotation.namespace, GoTermNamespace::MolecularFunction);
+        assert_eq!(annotation.evidence_code, EvidenceCode::IPI);
+        gene.transcripts[0].end = u64::MAX;
+        assert!(proto::Gene::try_from(gene).is_err());
+    }
+
+    #[test]
+    fn test_gene_views() {
+        let mut gene = Gene::try_from(proto_gene()).unwrap();
+        assert_eq!(gene.span(), Some((90, 400)));
+        assert_eq!(gene.strand(), Ok(Some(Strand::Reverse)));
+        assert_eq!(gene.nomenclature().unwrap().name, "UBQ1");
+        assert_eq!(gene.primary_transcript().unwrap().id, "tx1");
+
+        let model = gene.transcripts[0].model(&gene.id).unwrap();
+        assert_eq!(model.cds_start(), Some(350));
+        assert!(gene.models().is_err());
+
+        gene.transcripts[1].strand = Some(Strand::Forward);
+        gene.transcripts[1].id = "tx1".to_string();
+        assert_eq!(
+            gene.check(),
+            vec![
+                GeneError::MixedStrand {
+                    transcript: "tx1".to_string()
+                },
+                GeneError::DuplicateTranscript("tx1".to_string()),
+            ]
+        );
+    }
+}
@@ -0,0 +1,469 @@
+//! NCBI genetic codes, used to translate CDS on nuclear, mitochondrial and plastid sequences.
+
+use std::collections::HashMap;
+
+use anyhow::{anyhow, Result};
+use gff::attributes::Tag;
+use gff::GffRecord;
+
+pub const STANDARD_CODE_ID: u8 = 1;
+/// Attribute giving the genetic code of a feature, as in NCBI annotations.
+pub const TRANSL_TABLE_TAG: &str = "transl_table";
+pub(crate) const STOP: u8 = b'*';
+const START: u8 = b'M';
+const UNKNOWN_AMINO_ACID: u8 = b'X';
+
+/// Amino acids for ambiguous codons that can only code for two similar residues.
+const AMBIGUOUS_AMINO_ACIDS: [(u8, u8, u8); 3] =
+    [(b'D', b'N', b'B'), (b'E', b'Q', b'Z'), (b'I', b'L', b'J')];
+
+/// A translation table. Amino acids are in NCBI order, codons enumerated over `TCAG` at each
+/// position.
+#[derive(Debug, PartialEq, Eq)]
+pub struct GeneticCode {
+    pub id: u8,
+    pub name: &'static str,
+    amino_acids: &'static [u8; 64],
+    start_codons: &'static [&'static [u8; 3]],
+}
+
+const fn code(
+    id: u8,
+    name: &'static str,
+    amino_acids: &'static [u8; 64],
+    start_codons: &'static [&'static [u8; 3]],
+) -> GeneticCode {
+    GeneticCode {
+        id,
+        name,
+        amino_acids,
+        start_codons,
+    }
+}
+
+/// Tables 1 to 33 of the NCBI taxonomy. Numbers 7, 8 and 17 to 20 are not assigned.
+static GENETIC_CODES: [GeneticCode; 27] = [
+    code(
+        1,
+        "Standard",
+        b"FFLLSSSSYY**CC*WLLLLPPPPHHQQRRRRIIIMTTTTNNKKSSRRVVVVAAAADDEEGGGG",
+        &[b"TTG", b"CTG", b"ATG"],
+    ),
+    code(
+        2,
+        "Vertebrate Mitochondrial",
+        b"FFLLSSSSYY**CCWWLLLLPPPPHHQQRRRRIIMMTTTTNNKKSS**VVVVAAAADDEEGGGG",
+        &[b"ATT", b"ATC", b"ATA", b"ATG", b"GTG"],
+    ),
+    code(
+        3,
+        "Yeast Mitochondrial",
+        b"FFLLSSSSYY**CCWWTTTTPPPPHHQQRRRRIIMMTTTTNNKKSSRRVVVVAAAADDEEGGGG",
+        &[b"ATA", b"ATG", b"GTG"],
+    ),
+    code(
+        4,
+        "Mold, Protozoan, and Coelenterate Mitochondrial and Mycoplasma/Spiroplasma",
+        b"FFLLSSSSYY**CCWWLLLLPPPPHHQQRRRRIIIMTTTTNNKKSSRRVVVVAAAADDEEGGGG",
+        &[
+            b"TTA", b"TTG", b"CTG", b"ATT", b"ATC", b"ATA", b"ATG", b"GTG",
+        ],
+    ),
+    code(
+        5,
+        "Invertebrate Mitochondrial",
+        b"FFLLSSSSYY**CCWWLLLLPPPPHHQQRRRRIIMMTTTTNNKKSSSSVVVVAAAADDEEGGGG",
+        &[b"TTG", b"ATT", b"ATC", b"ATA", b"ATG", b"GTG"],
+    ),
+    code(
+        6,
+        "Ciliate, Dasycladacean and Hexamita Nuclear",
+        b"FFLLSSSSYYQQCC*WLLLLPPPPHHQQRRRRIIIMTTTTNNKKSSRRVVVVAAAADDEEGGGG",
+        &[b"ATG"],
+    ),
+    code(
+        9,
+        "Echinoderm and Flatworm Mitochondrial",
+        b"FFLLSSSSYY**CCWWLLLLPPPPHHQQRRRRIIIMTTTTNNNKSSSSVVVVAAAADDEEGGGG",
+        &[b"ATG", b"GTG"],
+    ),
+    code(
+        10,
+        "Euplotid Nuclear",
+        b"FFLLSSSSYY**CCCWLLLLPPPPHHQQRRRRIIIMTTTTNNKKSSRRVVVVAAAADDEEGGGG",
+        &[b"ATG"],
+    ),
+    code(
+        11,
+        "Bacterial, Archaeal and Plant Plastid",
+        b"FFLLSSSSYY**CC*WLLLLPPPPHHQQRRRRIIIMTTTTNNKKSSRRVVVVAAAADDEEGGGG",
+        &[b"TTG", b"CTG", b"ATT", b"ATC", b"ATA", b"ATG", b"GTG"],
+    ),
+    code(
+        12,
+        "Alternative Yeast Nuclear",
+        b"FFLLSSSSYY**CC*WLLLSPPPPHHQQRRRRIIIMTTTTNNKKSSRRVVVVAAAADDEEGGGG",
+        &[b"CTG", b"ATG"],
+    ),
+    code(
+        13,
+        "Ascidian Mitochondrial",
+        b"FFLLSSSSYY**CCWWLLLLPPPPHHQQRRRRIIMMTTTTNNKKSSGGVVVVAAAADDEEGGGG",
+        &[b"TTG", b"ATA", b"ATG", b"GTG"],
+    ),
+    code(
+        14,
+        "Alternative Flatworm Mitochondrial",
+        b"FFLLSSSSYYY*CCWWLLLLPPPPHHQQRRRRIIIMTTTTNNNKSSSSVVVVAAAADDEEGGGG",
+        &[b"ATG"],
+    ),
+    code(
+        15,
+        "Blepharisma Macronuclear",
+        b"FFLLSSSSYY*QCC*WLLLLPPPPHHQQRRRRIIIMTTTTNNKKSSRRVVVVAAAADDEEGGGG",
+        &[b"ATG"],
+    ),
+    code(
+        16,
+        "Chlorophycean Mitochondrial",
+        b"FFLLSSSSYY*LCC*WLLLLPPPPHHQQRRRRIIIMTTTTNNKKSSRRVVVVAAAADDEEGGGG",
+        &[b"ATG"],
+    ),
+    code(
+        21,
+        "Trematode Mitochondrial",
+        b"FFLLSSSSYY**CCWWLLLLPPPPHHQQRRRRIIMMTTTTNNNKSSSSVVVVAAAADDEEGGGG",
+        &[b"ATG", b"GTG"],
+    ),
+    code(
+        22,
+        "Scenedesmus obliquus Mitochondrial",
+        b"FFLLSS*SYY*LCC*WLLLLPPPPHHQQRRRRIIIMTTTTNNKKSSRRVVVVAAAADDEEGGGG",
+        &[b"ATG"],
+    ),
+    code(
+        23,
+        "Thraustochytrium Mitochondrial",
+        b"FF*LSSSSYY**CC*WLLLLPPPPHHQQRRRRIIIMTTTTNNKKSSRRVVVVAAAADDEEGGGG",
+        &[b"ATT", b"ATG", b"GTG"],
+    ),
+    code(
+        24,
+        "Rhabdopleuridae Mitochondrial",
+        b"FFLLSSSSYY**CCWWLLLLPPPPHHQQRRRRIIIMTTTTNNKKSSSKVVVVAAAADDEEGGGG",
+        &[b"TTG", b"CTG", b"ATG", b"GTG"],
+    ),
+    code(
+        25,
+        "Candidate Division SR1 and Gracilibacteria",
+        b"FFLLSSSSYY**CCGWLLLLPPPPHHQQRRRRIIIMTTTTNNKKSSRRVVVVAAAADDEEGGGG",
+        &[b"TTG", b"ATG", b"GTG"],
+    ),
+    code(
+        26,
+        "Pachysolen tannophilus Nuclear",
+        b"FFLLSSSSYY**CC*WLLLAPPPPHHQQRRRRIIIMTTTTNNKKSSRRVVVVAAAADDEEGGGG",
+        &[b"CTG", b"ATG"],
+    ),
+    code(
+        27,
+        "Karyorelict Nuclear",
+        b"FFLLSSSSYYQQCCWWLLLLPPPPHHQQRRRRIIIMTTTTNNKKSSRRVVVVAAAADDEEGGGG",
+        &[b"ATG"],
+    ),
+    code(
+        28,
+        "Condylostoma Nuclear",
+        b"FFLLSSSSYYQQCCWWLLLLPPPPHHQQRRRRIIIMTTTTNNKKSSRRVVVVAAAADDEEGGGG",
+        &[b"ATG"],
+    ),
+    code(
+        29,
+        "Mesodinium Nuclear",
+        b"FFLLSSSSYYYYCC*WLLLLPPPPHHQQRRRRIIIMTTTTNNKKSSRRVVVVAAAADDEEGGGG",
+        &[b"ATG"],
+    ),
+    code(
+        30,
+        "Peritrich Nuclear",
+        b"FFLLSSSSYYEECC*WLLLLPPPPHHQQRRRRIIIMTTTTNNKKSSRRVVVVAAAADDEEGGGG",
+        &[b"ATG"],
+    ),
+    code(
+        31,
+        "Blastocrithidia Nuclear",
+        b"FFLLSSSSYYEECCWWLLLLPPPPHHQQRRRRIIIMTTTTNNKKSSRRVVVVAAAADDEEGGGG",
+        &[b"ATG"],
+    ),
+    code(
+        32,
+        "Balanophoraceae Plastid",
+        b"FFLLSSSSYY*WCC*WLLLLPPPPHHQQRRRRIIIMTTTTNNKKSSRRVVVVAAAADDEEGGGG",
+        &[b"TTG", b"CTG", b"ATT", b"ATC", b"ATA", b"ATG", b"GTG"],
+    ),
+    code(
+        33,
+        "Cephalodiscidae Mitochondrial",
+        b"FFLLSSSSYYY*CCWWLLLLPPPPHHQQRRRRIIIMTTTTNNKKSSSKVVVVAAAADDEEGGGG",
+        &[b"TTG", b"CTG", b"ATG", b"GTG"],
+    ),
+];
+
+/// Bases an IUPAC code stands for, as a mask over `TCAG`.
+fn base_mask(base: u8) -> Option<u8> {
+    let (t, c, a, g) = (1, 2, 4, 8);
+    match base.to_ascii_uppercase() {
+        b'T' | b'U' => Some(t),
+        b'C' => Some(c),
+        b'A' => Some(a),
+        b'G' => Some(g),
+        b'R' => Some(a | g),
+        b'Y' => Some(c | t),
+        b'S' => Some(c | g),
+        b'W' => Some(a | t),
+        b'K' => Some(g | t),
+        b'M' => Some(a | c),
+        b'B' => Some(c | g | t),
+        b'D' => Some(a | g | t),
+        b'H' => Some(a | c | t),
+        b'V' => Some(a | c | g),
+        b'N' => Some(t | c | a | g),
+        _ => None,
+    }
+}
+
+/// Table indexes of every codon an ambiguous codon stands for. Missing bases count as `N`.
+fn codon_indexes(codon: &[u8]) -> Option<Vec<usize>> {
+    let mut indexes = vec![0];
+    for i in 0..3 {
+        let mask = match codon.get(i) {
+            Some(&base) => base_mask(base)?,
+            None => 0b1111,
+        };
+        indexes = indexes
+            .iter()
+            .flat_map(|index| {
+                (0..4)
+                    .filter(move |bit| mask & (1 << bit) != 0)
+                    .map(move |bit| index * 4 + bit)
+            })
+            .collect();
+    }
+    Some(indexes)
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TranslationOptions {
+    /// Translate the first codon as `M` when it is a start codon of the code.
+    pub initiator: bool,
+    /// Translate a trailing partial codon when its bases determine the amino acid.
+    pub partial_codon: bool,
+}
+
+impl GeneticCode {
+    pub fn get(id: u8) -> Option<&'static Self> {
+        GENETIC_CODES.iter().find(|code| code.id == id)
+    }
+
+    pub fn standard() -> &'static Self {
+        &GENETIC_CODES[0]
+    }
+
+    pub fn all() -> &'static [Self] {
+        &GENETIC_CODES
+    }
+
+    /// Translates a codon, resolving IUPAC ambiguity codes when every codon they stand for
+    /// gives the same amino acid, or one of the pairs written `B`, `Z` and `J`. Other codons
+    /// become `X`.
+    pub fn translate_codon(&self, codon: &[u8]) -> u8 {
+        let Some(indexes) = codon_indexes(codon) else {
+            return UNKNOWN_AMINO_ACID;
+        };
+        let mut amino_acids: Vec<u8> = indexes.iter().map(|&i| self.amino_acids[i]).collect();
+        amino_acids.sort_unstable();
+        amino_acids.dedup();
+        match amino_acids[..] {
+            [amino_acid] => amino_acid,
+            [first, second] => AMBIGUOUS_AMINO_ACIDS
+                .iter()
+                .find(|&&(a, b, _)| (a, b) == (first, second))
+                .map_or(UNKNOWN_AMINO_ACID, |&(_, _, ambiguous)| ambiguous),
+            _ => UNKNOWN_AMINO_ACID,
+        }
+    }
+
+    /// Whether every codon an ambiguous codon stands for is a start codon.
+    pub fn is_start(&self, codon: &[u8]) -> bool {
+        codon.len() == 3
+            && codon_indexes(codon).is_some_and(|indexes| {
+                indexes.iter().all(|&i| {
+                    self.start_codons
+                        .iter()
+                        .any(|start| codon_indexes(*start) == Some(vec![i]))
+                })
+            })
+    }
+
+    pub fn is_stop(&self, codon: &[u8]) -> bool {
+        codon.len() == 3 && self.translate_codon(codon) == STOP
+    }
+
+    pub fn translate(&self, cds: &[u8], options: TranslationOptions) -> Vec<u8> {
+        let mut protein: Vec<u8> = cds
+            .chunks_exact(3)
+            .map(|codon| self.translate_codon(codon))
+            .collect();
+        if options.initiator && self.is_start(&cds[..cds.len().min(3)]) {
+            protein[0] = START;
+        }
+        let partial = cds.chunks_exact(3).remainder();
+        if options.partial_codon && !partial.is_empty() {
+            let amino_acid = self.translate_codon(partial);
+            if amino_acid != UNKNOWN_AMINO_ACID {
+                protein.push(amino_acid);
+            }
+        }
+        protein
+    }
+}
+
+/// Genetic codes of sequences, for genomes whose organelles use another code than the
+/// nuclear sequences.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GeneticCodes {
+    default: &'static GeneticCode,
+    seqids: HashMap<String, &'static GeneticCode>,
+}
+
+impl Default for GeneticCodes {
+    fn default() -> Self {
+        Self {
+            default: GeneticCode::standard(),
+            seqids: HashMap::new(),
+        }
+    }
+}
+
+fn genetic_code(id: u8) -> Result<&'static GeneticCode> {
+    GeneticCode::get(id).ok_or_else(|| anyhow!("unknown genetic code: {}", id))
+}
+
+impl GeneticCodes {
+    pub fn new(default_id: u8) -> Result<Self> {
+        Ok(Self {
+            default: genetic_code(default_id)?,
+            seqids: HashMap::new(),
+        })
+    }
+
+    pub fn set(&mut self, seqid: &str, id: u8) -> Result<()> {
+        self.seqids.insert(seqid.to_string(), genetic_code(id)?);
+        Ok(())
+    }
+
+    pub fn get(&self, seqid: &str) -> &'static GeneticCode {
+        self.seqids.get(seqid).copied().unwrap_or(self.default)
+    }
+
+    /// Reads `transl_table` attributes, which must agree for all features of a sequence.
+    pub fn from_records(records: &[GffRecord], default_id: u8) -> Result<Self> {
+        let mut codes = Self::new(default_id)?;
+        let tag = Tag::from(TRANSL_TABLE_TAG);
+        for record in records {
+            let Some(value) = record.attribute(&tag).and_then(|v| v.first()) else {
+                continue;
+            };
+            let code = genetic_code(
+                value
+                    .parse()
+                    .map_err(|_| anyhow!("invalid {}: {}", TRANSL_TABLE_TAG, value))?,
+            )?;
+            match codes.seqids.get(record.seqid.as_str()) {
+                Some(existing) if existing.id != code.id => {
+                    return Err(anyhow!(
+                        "{} has features with {} {} and {}",
+                        record.seqid,
+                        TRANSL_TABLE_TAG,
+                        existing.id,
+                        code.id
+                    ))
+                }
+                _ => {
+                    codes.seqids.insert(record.seqid.to_string(), code);
+                }
+            }
+        }
+        Ok(codes)
+    }
+}
+
+#[cfg(test)]
+mod test_genetic_code {
+    use gff::parse_line;
+
+    use super::*;
+
+    #[test]
+    fn test_tables() {
+        assert_eq!(GeneticCode::all().len(), 27);
+        assert!(GeneticCode::get(7).is_none());
+        let mito = GeneticCode::get(2).unwrap();
+        assert_eq!(mito.translate(b"ATAAGATGA", Default::default()), b"M*W");
+        assert_eq!(
+            GeneticCode::standard().translate(b"ATAAGATGA", Default::default()),
+            b"IR*"
+        );
+        assert_eq!(GeneticCode::get(6).unwrap().translate_codon(b"TAA"), b'Q');
+    }
+
+    #[test]
+    fn test_start_codons() {
+        let plastid = GeneticCode::get(11).unwrap();
+        let options = TranslationOptions {
+            initiator: true,
+            ..Default::default()
+        };
+        assert_eq!(plastid.translate(b"GTGGTGTAA", options), b"MV*");
+        assert_eq!(plastid.translate(b"GTGGTGTAA", Default::default()), b"VV*");
+        assert_eq!(GeneticCode::standard().translate(b"GTGTAA", options), b"V*");
+        assert!(plastid.is_start(b"ATH"));
+        assert!(!plastid.is_start(b"NTA"));
+    }
+
+    #[test]
+    fn test_ambiguous_codons() {
+        let code = GeneticCode::standard();
+        assert_eq!(code.translate_codon(b"GCN"), b'A');
+        assert_eq!(code.translate_codon(b"TAR"), b'*');
+        assert_eq!(code.translate_codon(b"RAY"), b'B');
+        assert_eq!(code.translate_codon(b"NNN"), b'X');
+        assert_eq!(code.translate_codon(b"A-G"), b'X');
+        let options = TranslationOptions {
+            partial_codon: true,
+            ..Default::default()
+        };
+        assert_eq!(code.translate(b"ATGGG", options), b"MG");
+        assert_eq!(code.translate(b"ATGA", options), b"M");
+    }
+
+    #[test]
+    fn test_codes_from_records() {
+        let records: Vec<GffRecord> = [
+            "chrM\t.\tCDS\t1\t9\t.\t+\t0\tID=cds1;transl_table=2",
+            "chrC\t.\tregion\t1\t900\t.\t+\t.\tID=chrC;transl_table=11",
+            "chr1\t.\tCDS\t1\t9\t.\t+\t0\tID=cds2",
+        ]
+        .iter()
+        .map(|line| parse_line(line).unwrap())
+        .collect();
+        let codes = GeneticCodes::from_records(&records, STANDARD_CODE_ID).unwrap();
+        assert_eq!(codes.get("chrM").id, 2);
+        assert_eq!(codes.get("chrC").id, 11);
+        assert_eq!(codes.get("chr1").id, 1);
+
+        let conflicting = parse_line("chrM\t.\tCDS\t20\t29\t.\t+\t0\ttransl_table=4").unwrap();
+        let records = [records, vec![conflicting]].concat();
+        assert!(GeneticCodes::from_records(&records, STANDARD_CODE_ID).is_err());
+    }
+}
@@ -1,2 +1,10 @@
+pub mod assembly;
+pub mod canonical;
 pub mod functional_annotations;
+pub mod genes;
+pub mod genetic_code;
+pub mod proto;
+pub mod qc;
+pub mod sequences;
+pub mod splicing;
 pub mod transcripts;
@@ -0,0 +1,4 @@
+//! Messages of `genomebase_genome.v1`, generated by prost-build from
+//! `proto-def/genome/genomebase_genome/v1/genome.proto`.
+
+include!(concat!(env!("OUT_DIR"), "/genomebase_genome.v1.rs"));
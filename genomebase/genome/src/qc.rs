@@ -0,0 +1,427 @@
+//! Sequence-aware checks of transcript models: reading frame, start and stop codons, splice
+//! sites and feature lengths.
+
+use std::fmt;
+
+use anyhow::Result;
+use fasta::SequenceSource;
+use gff::tbl::Severity;
+use gff::{Phase, Strand};
+use serde::{Deserialize, Serialize};
+
+use crate::genetic_code::{GeneticCodes, TranslationOptions, STOP};
+use crate::sequences::{spliced_sequence, ExtractOptions, SequenceExtractor};
+use crate::transcripts::Transcript;
+
+pub const DEFAULT_MIN_INTRON_LENGTH: u64 = 20;
+pub const DEFAULT_MIN_EXON_LENGTH: u64 = 10;
+/// Donor and acceptor dinucleotides of GT-AG, GC-AG and AT-AC introns.
+const CANONICAL_SPLICE_SITES: [(&[u8; 2], &[u8; 2]); 3] =
+    [(b"GT", b"AG"), (b"GC", b"AG"), (b"AT", b"AC")];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct QcOptions {
+    pub min_intron_length: u64,
+    pub min_exon_length: u64,
+}
+
+impl Default for QcOptions {
+    fn default() -> Self {
+        Self {
+            min_intron_length: DEFAULT_MIN_INTRON_LENGTH,
+            min_exon_length: DEFAULT_MIN_EXON_LENGTH,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum QcIssueKind {
+    CdsLengthNotMultipleOfThree {
+        length: u64,
+    },
+    /// Phase of a CDS segment, numbered from 1 in transcription order.
+    InconsistentPhase {
+        segment: usize,
+        expected: u8,
+        found: u8,
+    },
+    MissingStartCodon {
+        codon: String,
+    },
+    MissingStopCodon {
+        codon: String,
+    },
+    /// Position of the stop in the protein, from 1.
+    InternalStopCodon {
+        position: usize,
+    },
+    NonCanonicalSpliceSite {
+        start: u64,
+        end: u64,
+        donor: String,
+        acceptor: String,
+    },
+    ShortIntron {
+        start: u64,
+        end: u64,
+    },
+    ShortExon {
+        start: u64,
+        end: u64,
+    },
+}
+
+impl fmt::Display for QcIssueKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::CdsLengthNotMultipleOfThree { length } => {
+                write!(f, "CDS length {} is not a multiple of 3", length)
+            }
+            Self::InconsistentPhase {
+                segment,
+                expected,
+                found,
+            } => write!(
+                f,
+                "CDS segment {} has phase {}, expected {}",
+                segment, found, expected
+            ),
+            Self::MissingStartCodon { codon } => {
+                write!(f, "CDS starts with {}, not a start codon", codon)
+            }
+            Self::MissingStopCodon { codon } => {
+                write!(f, "CDS ends with {}, not a stop codon", codon)
+            }
+            Self::InternalStopCodon { position } => {
+                write!(f, "internal stop codon at amino acid {}", position)
+            }
+            Self::NonCanonicalSpliceSite {
+                start,
+                end,
+                donor,
+                acceptor,
+            } => write!(
+                f,
+                "intron {}-{} has non-canonical splice sites {}-{}",
+                start, end, donor, acceptor
+            ),
+            Self::ShortIntron { start, end } => write!(f, "short intron {}-{}", start, end),
+            Self::ShortExon { start, end } => write!(f, "short exon {}-{}", start, end),
+        }
+    }
+}
+
+impl QcIssueKind {
+    /// Missing start or stop codons are warnings since partial models lack them.
+    pub fn severity(&self) -> Severity {
+        match self {
+            Self::CdsLengthNotMultipleOfThree { .. }
+            | Self::InconsistentPhase { .. }
+            | Self::InternalStopCodon { .. } => Severity::Error,
+            _ => Severity::Warning,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct QcIssue {
+    pub tx_id: String,
+    pub kind: QcIssueKind,
+}
+
+impl QcIssue {
+    pub fn severity(&self) -> Severity {
+        self.kind.severity()
+    }
+}
+
+impl fmt::Display for QcIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {}: {}", self.severity(), self.tx_id, self.kind)
+    }
+}
+
+fn phase_number(phase: Phase) -> u8 {
+    match phase {
+        Phase::Zero => 0,
+        Phase::One => 1,
+        Phase::Two => 2,
+    }
+}
+
+/// Checks transcripts against the genome sequence.
+pub struct TranscriptQc<'a, S> {
+    source: &'a S,
+    pub options: QcOptions,
+    genetic_codes: GeneticCodes,
+}
+
+impl<'a, S: SequenceSource> TranscriptQc<'a, S> {
+    pub fn new(source: &'a S, options: QcOptions) -> Self {
+        Self {
+            source,
+            options,
+            genetic_codes: GeneticCodes::default(),
+        }
+    }
+
+    pub fn with_genetic_codes(mut self, genetic_codes: GeneticCodes) -> Self {
+        self.genetic_codes = genetic_codes;
+        self
+    }
+
+    /// Checks a transcript. `cds_phases` are the phases of `transcript.cds` in the same order,
+    /// or empty when unknown, in which case the CDS is assumed to start with a whole codon.
+    pub fn check(&self, transcript: &Transcript, cds_phases: &[Phase]) -> Result<Vec<QcIssue>> {
+        let mut kinds = self.structure_issues(transcript)?;
+        if !transcript.cds.is_empty() {
+            kinds.extend(self.cds_issues(transcript, cds_phases)?);
+        }
+        Ok(kinds
+            .into_iter()
+            .map(|kind| QcIssue {
+                tx_id: transcript.tx_id.clone(),
+                kind,
+            })
+            .collect())
+    }
+
+    fn structure_issues(&self, transcript: &Transcript) -> Result<Vec<QcIssueKind>> {
+        let seqid = transcript.chromosome();
+        let strand = transcript.strand;
+        let exons: Vec<(u64, u64)> = transcript
+            .exon_positions()
+            .iter()
+            .map(|p| (p.start, p.end))
+            .collect();
+        let mut issues = Vec::new();
+        for &(start, end) in &exons {
+            if end + 1 - start < self.options.min_exon_length {
+                issues.push(QcIssueKind::ShortExon { start, end });
+            }
+        }
+
+        for intron in transcript.introns() {
+            let (start, end) = (intron.start, intron.end);
+            if end + 1 - start < self.options.min_intron_length {
+                issues.push(QcIssueKind::ShortIntron { start, end });
+            }
+            if end + 1 - start < 4 {
+                continue;
+            }
+            let first = spliced_sequence(self.source, seqid, &[(start, start + 1)], strand, 0, 0)?;
+            let last = spliced_sequence(self.source, seqid, &[(end - 1, end)], strand, 0, 0)?;
+            let (donor, acceptor) = match strand {
+                Strand::Forward => (first, last),
+                Strand::Reverse => (last, first),
+            };
+            let (donor, acceptor) = (donor.to_ascii_uppercase(), acceptor.to_ascii_uppercase());
+            if !CANONICAL_SPLICE_SITES
+                .iter()
+                .any(|(d, a)| donor == d.as_slice() && acceptor == a.as_slice())
+            {
+                issues.push(QcIssueKind::NonCanonicalSpliceSite {
+                    start,
+                    end,
+                    donor: String::from_utf8_lossy(&donor).into_owned(),
+                    acceptor: String::from_utf8_lossy(&acceptor).into_owned(),
+                });
+            }
+        }
+        Ok(issues)
+    }
+
+    fn cds_issues(
+        &self,
+        transcript: &Transcript,
+        cds_phases: &[Phase],
+    ) -> Result<Vec<QcIssueKind>> {
+        let mut issues = Vec::new();
+        // Segments and phases in transcription order.
+        let mut segments: Vec<(u64, u64, Option<u8>)> = transcript
+            .cds
+            .iter()
+            .enumerate()
+            .map(|(i, p)| (p.start, p.end, cds_phases.get(i).copied().map(phase_number)))
+            .collect();
+        segments.sort_unstable();
+        if transcript.strand == Strand::Reverse {
+            segments.reverse();
+        }
+
+        let first_phase = segments[0].2.unwrap_or(0);
+        let mut expected = first_phase;
+        for (i, &(start, end, phase)) in segments.iter().enumerate() {
+            if let Some(found) = phase.filter(|&found| found != expected) {
+                issues.push(QcIssueKind::InconsistentPhase {
+                    segment: i + 1,
+                    expected,
+                    found,
+                });
+            }
+            let length = end + 1 - start;
+            expected = ((3 - (length + 3 - expected as u64) % 3) % 3) as u8;
+        }
+
+        let options = ExtractOptions {
+            cds_phase: first_phase,
+            ..Default::default()
+        };
+        let cds = SequenceExtractor::new(self.source, options).cds(transcript)?;
+        if !(cds.len() as u64).is_multiple_of(3) {
+            issues.push(QcIssueKind::CdsLengthNotMultipleOfThree {
+                length: cds.len() as u64,
+            });
+        }
+
+        let code = self.genetic_codes.get(transcript.position.chromosome());
+        let codons: Vec<&[u8]> = cds.chunks_exact(3).collect();
+        let codon_string = |codon: &[u8]| String::from_utf8_lossy(codon).to_ascii_uppercase();
+        if let Some(first) = codons
+            .first()
+            .filter(|c| first_phase == 0 && !code.is_start(c))
+        {
+            issues.push(QcIssueKind::MissingStartCodon {
+                codon: codon_string(first),
+            });
+        }
+        if let Some(last) = codons
+            .last()
+            .filter(|c| cds.len() % 3 == 0 && !code.is_stop(c))
+        {
+            issues.push(QcIssueKind::MissingStopCodon {
+                codon: codon_string(last),
+            });
+        }
+        let protein = code.translate(&cds, TranslationOptions::default());
+        for (i, &amino_acid) in protein
+            .iter()
+            .enumerate()
+            .take(protein.len().saturating_sub(1))
+        {
+            if amino_acid == STOP {
+                issues.push(QcIssueKind::InternalStopCodon { position: i + 1 });
+            }
+        }
+        Ok(issues)
+    }
+}
+
+#[cfg(test)]
+mod test_qc {
+    use std::collections::HashMap;
+
+    use super::*;
+    use crate::transcripts::TranscriptBuilder;
+
+    fn transcript(
+        chromosome: &str,
+        strand: Strand,
+        exons: &[(u64, u64)],
+        cds: &[(u64, u64)],
+    ) -> Transcript {
+        let builder = TranscriptBuilder::new("tx1", "gene1", chromosome.parse().unwrap(), strand);
+        let builder = exons
+            .iter()
+            .fold(builder, |b, &(start, end)| b.exon(start, end));
+        cds.iter()
+            .fold(builder, |b, &(start, end)| b.cds(start, end))
+            .build()
+            .unwrap()
+    }
+
+    fn genome() -> HashMap<String, Vec<u8>> {
+        HashMap::from([
+            ("1".to_string(), b"ATGAAAGTCCCCAGCCCTAA".to_vec()),
+            ("2".to_string(), b"ATGTAAAAATAG".to_vec()),
+            ("3".to_string(), b"TTAGGGCTGGGGACTTTCAT".to_vec()),
+        ])
+    }
+
+    fn kinds(issues: Vec<QcIssue>) -> Vec<QcIssueKind> {
+        issues.into_iter().map(|issue| issue.kind).collect()
+    }
+
+    #[test]
+    fn test_valid_transcripts() {
+        let genome = genome();
+        let options = QcOptions {
+            min_intron_length: 5,
+            min_exon_length: 3,
+        };
+        let qc = TranscriptQc::new(&genome, options);
+        let exons = [(1, 6), (15, 20)];
+        let forward = transcript("1", Strand::Forward, &exons, &exons);
+        assert_eq!(
+            qc.check(&forward, &[Phase::Zero, Phase::Zero]).unwrap(),
+            vec![]
+        );
+        let reverse = transcript("3", Strand::Reverse, &exons, &exons);
+        assert_eq!(qc.check(&reverse, &[]).unwrap(), vec![]);
+
+        let qc = TranscriptQc::new(&genome, QcOptions::default());
+        assert_eq!(
+            kinds(qc.check(&forward, &[]).unwrap()),
+            vec![
+                QcIssueKind::ShortExon { start: 1, end: 6 },
+                QcIssueKind::ShortExon { start: 15, end: 20 },
+                QcIssueKind::ShortIntron { start: 7, end: 14 },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_frame_issues() {
+        let genome = genome();
+        let options = QcOptions {
+            min_intron_length: 0,
+            min_exon_length: 0,
+        };
+        let qc = TranscriptQc::new(&genome, options);
+
+        let tx = transcript(
+            "1",
+            Strand::Forward,
+            &[(1, 6), (15, 20)],
+            &[(4, 6), (15, 20)],
+        );
+        assert_eq!(
+            kinds(qc.check(&tx, &[Phase::Zero, Phase::One]).unwrap()),
+            vec![
+                QcIssueKind::InconsistentPhase {
+                    segment: 2,
+                    expected: 0,
+                    found: 1
+                },
+                QcIssueKind::MissingStartCodon {
+                    codon: "AAA".to_string()
+                },
+            ]
+        );
+
+        let tx = transcript(
+            "1",
+            Strand::Forward,
+            &[(1, 3), (18, 20)],
+            &[(1, 3), (18, 19)],
+        );
+        assert_eq!(
+            kinds(qc.check(&tx, &[]).unwrap()),
+            vec![
+                QcIssueKind::NonCanonicalSpliceSite {
+                    start: 4,
+                    end: 17,
+                    donor: "AA".to_string(),
+                    acceptor: "CC".to_string()
+                },
+                QcIssueKind::CdsLengthNotMultipleOfThree { length: 5 },
+            ]
+        );
+
+        let tx = transcript("2", Strand::Forward, &[(1, 12)], &[(1, 12)]);
+        assert_eq!(
+            kinds(qc.check(&tx, &[]).unwrap()),
+            vec![QcIssueKind::InternalStopCodon { position: 2 }]
+        );
+    }
+}
@@ -0,0 +1,302 @@
+//! Transcript, CDS, exon and protein sequences of transcripts, served by
+//! `TranscriptService.GetSequence`.
+
+use anyhow::{anyhow, Result};
+use fasta::{reverse_complement, SequenceSource};
+use gff::Strand;
+use serde::{Deserialize, Serialize};
+
+use crate::genetic_code::{GeneticCode, GeneticCodes, TranslationOptions, STOP};
+use crate::transcripts::{GenomePosition, Transcript};
+
+/// Sequence kinds of the proto `SequenceType` enum, with the same numbering.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum SequenceType {
+    Protein = 1,
+    Cds = 2,
+    Transcript = 3,
+    Exon = 4,
+}
+
+impl TryFrom<i32> for SequenceType {
+    type Error = String;
+
+    fn try_from(value: i32) -> Result<Self, Self::Error> {
+        match value {
+            1 => Ok(Self::Protein),
+            2 => Ok(Self::Cds),
+            3 => Ok(Self::Transcript),
+            4 => Ok(Self::Exon),
+            _ => Err(format!("unsupported sequence type: {}", value)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ExtractOptions {
+    /// Genomic bases added before the transcript or CDS, on its strand.
+    pub upstream: u64,
+    /// Genomic bases added after the transcript or CDS, on its strand.
+    pub downstream: u64,
+    /// Phase of the 5' CDS segment, the bases to skip before the first complete codon.
+    pub cds_phase: u8,
+    /// Keep the terminal `*` of translated proteins.
+    pub keep_stop: bool,
+    /// Translate an alternative start codon at the start of a CDS with phase 0 as `M`.
+    pub initiator: bool,
+}
+
+/// Translates complete codons with the standard code. Ambiguous codons that do not determine
+/// the amino acid become `X`.
+pub fn translate(cds: &[u8]) -> Vec<u8> {
+    GeneticCode::standard().translate(cds, TranslationOptions::default())
+}
+
+/// Joins segments in transcription order, with flanks extending the outermost segments,
+/// and returns the length of the upstream flank. Flanks are clipped at the ends of the sequence.
+fn flanked_sequence<S: SequenceSource>(
+    source: &S,
+    seqid: &str,
+    segments: &[(u64, u64)],
+    strand: Strand,
+    upstream: u64,
+    downstream: u64,
+) -> Result<(Vec<u8>, usize)> {
+    let length = source
+        .sequence_length(seqid)
+        .ok_or_else(|| anyhow!("unknown sequence {}", seqid))?;
+    let mut segments = segments.to_vec();
+    segments.sort_unstable();
+    let (before, after) = match strand {
+        Strand::Forward => (upstream, downstream),
+        Strand::Reverse => (downstream, upstream),
+    };
+
+    let (mut added_before, mut added_after) = (0, 0);
+    if let Some(first) = segments.first_mut() {
+        added_before = before.min(first.0.saturating_sub(1));
+        first.0 -= added_before;
+    }
+    if let Some(last) = segments.last_mut() {
+        added_after = after.min(length.saturating_sub(last.1));
+        last.1 += added_after;
+    }
+
+    let mut sequence = Vec::new();
+    for (start, end) in segments {
+        sequence.extend(source.fetch(seqid, start, end)?);
+    }
+    Ok(match strand {
+        Strand::Forward => (sequence, added_before as usize),
+        Strand::Reverse => (reverse_complement(&sequence), added_after as usize),
+    })
+}
+
+/// Joins segments in transcription order, with flanks extending the outermost segments.
+/// Flanks are clipped at the ends of the sequence.
+pub fn spliced_sequence<S: SequenceSource>(
+    source: &S,
+    seqid: &str,
+    segments: &[(u64, u64)],
+    strand: Strand,
+    upstream: u64,
+    downstream: u64,
+) -> Result<Vec<u8>> {
+    flanked_sequence(source, seqid, segments, strand, upstream, downstream).map(|(s, _)| s)
+}
+
+fn intervals(positions: &[GenomePosition]) -> Vec<(u64, u64)> {
+    positions.iter().map(|p| (p.start, p.end)).collect()
+}
+
+/// Extracts sequences of transcripts from a genome.
+pub struct SequenceExtractor<'a, S> {
+    source: &'a S,
+    pub options: ExtractOptions,
+    genetic_codes: GeneticCodes,
+}
+
+impl<'a, S: SequenceSource> SequenceExtractor<'a, S> {
+    pub fn new(source: &'a S, options: ExtractOptions) -> Self {
+        Self {
+            source,
+            options,
+            genetic_codes: GeneticCodes::default(),
+        }
+    }
+
+    /// Translates proteins with the genetic code of their sequence instead of the standard
+    /// code.
+    pub fn with_genetic_codes(mut self, genetic_codes: GeneticCodes) -> Self {
+        self.genetic_codes = genetic_codes;
+        self
+    }
+
+    /// Spliced exons, with flanks.
+    pub fn transcript(&self, transcript: &Transcript) -> Result<Vec<u8>> {
+        spliced_sequence(
+            self.source,
+            transcript.chromosome(),
+            &intervals(&transcript.exon_positions()),
+            transcript.strand,
+            self.options.upstream,
+            self.options.downstream,
+        )
+    }
+
+    /// Spliced CDS from the first complete codon, with flanks.
+    pub fn cds(&self, transcript: &Transcript) -> Result<Vec<u8>> {
+        if transcript.cds.is_empty() {
+            return Err(anyhow!("{} has no CDS", transcript.tx_id));
+        }
+        let (mut cds, upstream) = flanked_sequence(
+            self.source,
+            transcript.chromosome(),
+            &intervals(&transcript.cds),
+            transcript.strand,
+            self.options.upstream,
+            self.options.downstream,
+        )?;
+        let phase_end = (upstream + self.options.cds_phase as usize).min(cds.len());
+        cds.drain(upstream..phase_end);
+        Ok(cds)
+    }
+
+    /// Each exon in transcription order, without flanks.
+    pub fn exons(&self, transcript: &Transcript) -> Result<Vec<Vec<u8>>> {
+        let seqid = transcript.chromosome();
+        let mut exons = intervals(&transcript.exon_positions());
+        if transcript.strand == Strand::Reverse {
+            exons.reverse();
+        }
+        exons
+            .iter()
+            .map(|&exon| spliced_sequence(self.source, seqid, &[exon], transcript.strand, 0, 0))
+            .collect()
+    }
+
+    /// Translation of the CDS, ignoring flanks.
+    pub fn protein(&self, transcript: &Transcript) -> Result<Vec<u8>> {
+        let options = ExtractOptions {
+            upstream: 0,
+            downstream: 0,
+            ..self.options
+        };
+        let cds = SequenceExtractor::new(self.source, options).cds(transcript)?;
+        let code = self.genetic_codes.get(transcript.chromosome());
+        let translation = TranslationOptions {
+            initiator: self.options.initiator && self.options.cds_phase == 0,
+            partial_codon: false,
+        };
+        let mut protein = code.translate(&cds, translation);
+        if !self.options.keep_stop && protein.last() == Some(&STOP) {
+            protein.pop();
+        }
+        Ok(protein)
+    }
+
+    /// Sequences of a kind: one for all kinds except [`SequenceType::Exon`], which gives one
+    /// per exon.
+    pub fn sequences(
+        &self,
+        transcript: &Transcript,
+        sequence_type: SequenceType,
+    ) -> Result<Vec<Vec<u8>>> {
+        match sequence_type {
+            SequenceType::Protein => self.protein(transcript).map(|s| vec![s]),
+            SequenceType::Cds => self.cds(transcript).map(|s| vec![s]),
+            SequenceType::Transcript => self.transcript(transcript).map(|s| vec![s]),
+            SequenceType::Exon => self.exons(transcript),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test_sequences {
+    use std::collections::HashMap;
+
+    use super::*;
+    use crate::transcripts::TranscriptBuilder;
+
+    fn transcript(exons: &[(u64, u64)], cds: &[(u64, u64)], strand: Strand) -> Transcript {
+        let builder = TranscriptBuilder::new("tx1", "gene1", "1".parse().unwrap(), strand);
+        let builder = exons
+            .iter()
+            .fold(builder, |b, &(start, end)| b.exon(start, end));
+        cds.iter()
+            .fold(builder, |b, &(start, end)| b.cds(start, end))
+            .build()
+            .unwrap()
+    }
+
+    fn genome() -> HashMap<String, Vec<u8>> {
+        HashMap::from([("1".to_string(), b"GGATGAAACCCTAAGTTTAGGGCCCATTTT".to_vec())])
+    }
+
+    #[test]
+    fn test_forward_sequences() {
+        let genome = genome();
+        let tx = transcript(&[(3, 8), (12, 20)], &[(3, 8), (12, 14)], Strand::Forward);
+        let extractor = SequenceExtractor::new(&genome, ExtractOptions::default());
+
+        let sequences = extractor.sequences(&tx, SequenceType::Transcript);
+        assert_eq!(sequences.unwrap(), vec![b"ATGAAATAAGTTTAG".to_vec()]);
+        assert_eq!(extractor.cds(&tx).unwrap(), b"ATGAAATAA");
+        assert_eq!(extractor.protein(&tx).unwrap(), b"MK");
+
+        let options = ExtractOptions {
+            upstream: 5,
+            downstream: 1,
+            ..Default::default()
+        };
+        let extractor = SequenceExtractor::new(&genome, options);
+        assert_eq!(extractor.transcript(&tx).unwrap(), b"GGATGAAATAAGTTTAGG");
+        assert_eq!(extractor.protein(&tx).unwrap(), b"MK");
+    }
+
+    #[test]
+    fn test_reverse_sequences() {
+        let genome = genome();
+        let tx = transcript(
+            &[(10, 15), (20, 27)],
+            &[(12, 15), (20, 25)],
+            Strand::Reverse,
+        );
+        let options = ExtractOptions {
+            upstream: 2,
+            cds_phase: 1,
+            ..Default::default()
+        };
+        let extractor = SequenceExtractor::new(&genome, options);
+
+        assert_eq!(extractor.cds(&tx).unwrap(), b"ATGGCCCCTTA");
+        assert_eq!(extractor.protein(&tx).unwrap(), b"GPL");
+        assert_eq!(
+            extractor.exons(&tx).unwrap(),
+            vec![b"ATGGGCCC".to_vec(), b"CTTAGG".to_vec()]
+        );
+    }
+
+    #[test]
+    fn test_translate_ambiguous_codon() {
+        assert_eq!(translate(b"ATGNNNtaaG"), b"MX*");
+    }
+
+    #[test]
+    fn test_genetic_codes() {
+        let genome = HashMap::from([("1".to_string(), b"ATATGATAA".to_vec())]);
+        let tx = transcript(&[(1, 9)], &[(1, 9)], Strand::Forward);
+        let options = ExtractOptions {
+            initiator: true,
+            ..Default::default()
+        };
+        let mut codes = GeneticCodes::default();
+        codes.set("1", 2).unwrap();
+
+        let extractor = SequenceExtractor::new(&genome, options);
+        assert_eq!(extractor.protein(&tx).unwrap(), b"I*");
+        let extractor = extractor.with_genetic_codes(codes);
+        assert_eq!(extractor.protein(&tx).unwrap(), b"MW");
+    }
+}
@@ -0,0 +1,430 @@
+//! Structural comparison of isoforms and classification of alternative splicing events between
+//! transcripts of the same gene, following the event types and IDs of SUPPA.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+use std::io::{self, Write};
+
+use gff::Strand;
+use serde::Serialize;
+
+use common::Seqid;
+
+use crate::transcripts::{GenomePosition, Transcript};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize)]
+pub enum EventKind {
+    /// Skipped exon.
+    SkippedExon,
+    /// Retained intron.
+    RetainedIntron,
+    /// Alternative 5' (donor) splice site.
+    Alternative5Prime,
+    /// Alternative 3' (acceptor) splice site.
+    Alternative3Prime,
+    MutuallyExclusiveExons,
+    AlternativeFirstExon,
+    AlternativeLastExon,
+}
+
+impl AsRef<str> for EventKind {
+    fn as_ref(&self) -> &str {
+        match self {
+            Self::SkippedExon => "SE",
+            Self::RetainedIntron => "RI",
+            Self::Alternative5Prime => "A5",
+            Self::Alternative3Prime => "A3",
+            Self::MutuallyExclusiveExons => "MX",
+            Self::AlternativeFirstExon => "AF",
+            Self::AlternativeLastExon => "AL",
+        }
+    }
+}
+
+/// An event between the transcripts of a gene. Coordinates are genomic exon boundaries in
+/// the order of the SUPPA event ID:
+///
+/// - SE: `e1-s2:e2-s3`, the skipped exon `s2-e2` between exons ending at `e1` and starting
+///   at `s3`.
+/// - MX: `e1-s2:e2-s4:e1-s3:e3-s4`, the exons `s2-e2` and `s3-e3`.
+/// - A5 and A3: `e1-s2:e1-s3` or `e1-s3:e2-s3`, the junction of the longer exon first.
+/// - RI: `s1:e1-s2:e2`, the intron `e1-s2` between exons `s1-e1` and `s2-e2`.
+/// - AF on the forward strand and AL on the reverse strand: `s1:e1-s3:s2:e2-s3`.
+/// - AL on the forward strand and AF on the reverse strand: `e1-s2:e2:e1-s3:e3`.
+///
+/// Inclusion transcripts have the form of the first exon or junction in the ID: the included
+/// exon for SE, the retained intron for RI and the longer exon for A5 and A3.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SplicingEvent {
+    pub gene_id: String,
+    pub kind: EventKind,
+    pub chromosome: Seqid,
+    pub strand: Strand,
+    pub coordinates: Vec<u64>,
+    pub inclusion: BTreeSet<String>,
+    pub exclusion: BTreeSet<String>,
+}
+
+impl SplicingEvent {
+    /// The SUPPA event ID, such as `gene1;SE:1:200-301:400-501:+`.
+    pub fn id(&self) -> String {
+        let c = &self.coordinates;
+        let coordinates = match (self.kind, self.low_end()) {
+            (EventKind::RetainedIntron, _) => format!("{}:{}-{}:{}", c[0], c[1], c[2], c[3]),
+            (EventKind::AlternativeFirstExon | EventKind::AlternativeLastExon, true) => {
+                format!("{}:{}-{}:{}:{}-{}", c[0], c[1], c[2], c[3], c[4], c[5])
+            }
+            (EventKind::AlternativeFirstExon | EventKind::AlternativeLastExon, false) => {
+                format!("{}-{}:{}:{}-{}:{}", c[0], c[1], c[2], c[3], c[4], c[5])
+            }
+            _ => c
+                .chunks(2)
+                .map(|pair| format!("{}-{}", pair[0], pair[1]))
+                .collect::<Vec<_>>()
+                .join(":"),
+        };
+        format!(
+            "{};{}:{}:{}:{}",
+            self.gene_id,
+            self.kind.as_ref(),
+            self.chromosome,
+            coordinates,
+            self.strand.as_ref()
+        )
+    }
+
+    /// Whether the alternative exons of an AF or AL event are at the low genomic end.
+    fn low_end(&self) -> bool {
+        matches!(
+            (self.kind, self.strand),
+            (EventKind::AlternativeFirstExon, Strand::Forward)
+                | (EventKind::AlternativeLastExon, Strand::Reverse)
+        )
+    }
+}
+
+impl fmt::Display for SplicingEvent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.id())
+    }
+}
+
+/// Exons and junctions shared by two isoforms or found in only one of them, as
+/// `(start, end)` exon coordinates and `(exon end, next exon start)` junctions.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct IsoformComparison {
+    pub shared_exons: Vec<(u64, u64)>,
+    pub first_only_exons: Vec<(u64, u64)>,
+    pub second_only_exons: Vec<(u64, u64)>,
+    pub shared_junctions: Vec<(u64, u64)>,
+    pub first_only_junctions: Vec<(u64, u64)>,
+    pub second_only_junctions: Vec<(u64, u64)>,
+}
+
+impl IsoformComparison {
+    pub fn new(first: &Transcript, second: &Transcript) -> Self {
+        let exons = |t: &Transcript| -> BTreeSet<(u64, u64)> {
+            t.exon_positions()
+                .iter()
+                .map(|e| (e.start, e.end))
+                .collect()
+        };
+        let junctions =
+            |t: &Transcript| -> BTreeSet<(u64, u64)> { t.splice_junctions().into_iter().collect() };
+        let (exons_a, exons_b) = (exons(first), exons(second));
+        let (junctions_a, junctions_b) = (junctions(first), junctions(second));
+        Self {
+            shared_exons: exons_a.intersection(&exons_b).copied().collect(),
+            first_only_exons: exons_a.difference(&exons_b).copied().collect(),
+            second_only_exons: exons_b.difference(&exons_a).copied().collect(),
+            shared_junctions: junctions_a.intersection(&junctions_b).copied().collect(),
+            first_only_junctions: junctions_a.difference(&junctions_b).copied().collect(),
+            second_only_junctions: junctions_b.difference(&junctions_a).copied().collect(),
+        }
+    }
+
+    /// Whether both isoforms are spliced at the same junctions, differing at most in their
+    /// transcription start and end.
+    pub fn same_intron_chain(&self) -> bool {
+        self.first_only_junctions.is_empty() && self.second_only_junctions.is_empty()
+    }
+}
+
+/// Events between each pair of transcripts with the same gene, chromosome and strand. An event
+/// found between several pairs is reported once, with all its inclusion and exclusion
+/// transcripts.
+pub fn splicing_events(transcripts: &[Transcript]) -> Vec<SplicingEvent> {
+    let mut events: BTreeMap<(String, EventKind, Vec<u64>), SplicingEvent> = BTreeMap::new();
+    for (i, a) in transcripts.iter().enumerate() {
+        for b in &transcripts[i + 1..] {
+            if a.gene_id != b.gene_id || a.chromosome() != b.chromosome() || a.strand != b.strand {
+                continue;
+            }
+            let pairs = pair_events(a, b).into_iter().chain(pair_events(b, a));
+            for (kind, coordinates, inclusion, exclusion) in pairs {
+                let event = events
+                    .entry((a.gene_id.clone(), kind, coordinates.clone()))
+                    .or_insert_with(|| SplicingEvent {
+                        gene_id: a.gene_id.clone(),
+                        kind,
+                        chromosome: a.chromosome().clone(),
+                        strand: a.strand,
+                        coordinates,
+                        inclusion: BTreeSet::new(),
+                        exclusion: BTreeSet::new(),
+                    });
+                event.inclusion.insert(inclusion.tx_id.clone());
+                event.exclusion.insert(exclusion.tx_id.clone());
+            }
+        }
+    }
+    events.into_values().collect()
+}
+
+/// Writes events in the SUPPA `.ioe` format, with the inclusion transcripts as the alternative
+/// transcripts.
+pub fn write_ioe<W: Write>(mut writer: W, events: &[SplicingEvent]) -> io::Result<()> {
+    writeln!(
+        writer,
+        "seqname\tgene_id\tevent_id\talternative_transcripts\ttotal_transcripts"
+    )?;
+    for event in events {
+        let join = |ids: &BTreeSet<String>| ids.iter().cloned().collect::<Vec<_>>().join(",");
+        let total: BTreeSet<String> = event.inclusion.union(&event.exclusion).cloned().collect();
+        writeln!(
+            writer,
+            "{}\t{}\t{}\t{}\t{}",
+            event.chromosome,
+            event.gene_id,
+            event.id(),
+            join(&event.inclusion),
+            join(&total)
+        )?;
+    }
+    Ok(())
+}
+
+type PairEvent<'a> = (EventKind, Vec<u64>, &'a Transcript, &'a Transcript);
+
+fn overlaps(a: &GenomePosition, b: &GenomePosition) -> bool {
+    a.start <= b.end && b.start <= a.end
+}
+
+/// Events as `(kind, coordinates, inclusion, exclusion)`, found from the structure of the first
+/// transcript. Calling it with both orders finds every event between the pair.
+fn pair_events<'a>(a: &'a Transcript, b: &'a Transcript) -> Vec<PairEvent<'a>> {
+    let exons_a = a.exon_positions();
+    let exons_b = b.exon_positions();
+    let junctions_a = a.splice_junctions();
+    let junctions_b = b.splice_junctions();
+    let forward = a.strand == Strand::Forward;
+    let mut events = Vec::new();
+
+    // Exon skipping: b joins the flanking exons of an internal exon of a.
+    for pair in junctions_a.windows(2) {
+        let ((e1, s2), (e2, s3)) = (pair[0], pair[1]);
+        if junctions_b.contains(&(e1, s3)) {
+            events.push((EventKind::SkippedExon, vec![e1, s2, e2, s3], a, b));
+        }
+    }
+
+    // Mutually exclusive exons: both join the same flanking exons through different internal
+    // exons, the exon of a upstream of the exon of b.
+    for pair_a in junctions_a.windows(2) {
+        for pair_b in junctions_b.windows(2) {
+            let ((e1, s2), (e2, s4)) = (pair_a[0], pair_a[1]);
+            let ((e1_b, s3), (e3, s4_b)) = (pair_b[0], pair_b[1]);
+            if e1 == e1_b && s4 == s4_b && e2 < s3 {
+                let coordinates = vec![e1, s2, e2, s4, e1, s3, e3, s4];
+                events.push((EventKind::MutuallyExclusiveExons, coordinates, a, b));
+            }
+        }
+    }
+
+    // Retained intron: an exon of b spans an intron of a, so b is the inclusion form.
+    for (i, &(e1, s2)) in junctions_a.iter().enumerate() {
+        if exons_b.iter().any(|e| e.start <= e1 && e.end >= s2) {
+            let coordinates = vec![exons_a[i].start, e1, s2, exons_a[i + 1].end];
+            events.push((EventKind::RetainedIntron, coordinates, b, a));
+        }
+    }
+
+    for (i, &(e1, s1)) in junctions_a.iter().enumerate() {
+        for (j, &(e2, s2)) in junctions_b.iter().enumerate() {
+            // Alternative splice sites: junctions sharing one end, from overlapping exons.
+            // The event is reported from the transcript with the longer exon.
+            if s1 == s2 && e1 > e2 && overlaps(&exons_a[i], &exons_b[j]) {
+                let kind = match forward {
+                    true => EventKind::Alternative5Prime,
+                    false => EventKind::Alternative3Prime,
+                };
+                events.push((kind, vec![e1, s1, e2, s1], a, b));
+            }
+            if e1 == e2 && s1 < s2 && overlaps(&exons_a[i + 1], &exons_b[j + 1]) {
+                let kind = match forward {
+                    true => EventKind::Alternative3Prime,
+                    false => EventKind::Alternative5Prime,
+                };
+                events.push((kind, vec![e1, s1, e1, s2], a, b));
+            }
+        }
+    }
+
+    // Alternative terminal exons: the lowest or highest exons differ without overlapping
+    // and join the same exon, the exon of a upstream of the exon of b.
+    if let (Some(&(e1, s1)), Some(&(e2, s2))) = (junctions_a.first(), junctions_b.first()) {
+        let (first_a, first_b) = (&exons_a[0], &exons_b[0]);
+        if s1 == s2 && first_a.end < first_b.start {
+            let kind = match forward {
+                true => EventKind::AlternativeFirstExon,
+                false => EventKind::AlternativeLastExon,
+            };
+            events.push((
+                kind,
+                vec![first_a.start, e1, s1, first_b.start, e2, s1],
+                a,
+                b,
+            ));
+        }
+    }
+    if let (Some(&(e1, s1)), Some(&(e2, s2))) = (junctions_a.last(), junctions_b.last()) {
+        let (last_a, last_b) = (&exons_a[exons_a.len() - 1], &exons_b[exons_b.len() - 1]);
+        if e1 == e2 && last_a.end < last_b.start {
+            let kind = match forward {
+                true => EventKind::AlternativeLastExon,
+                false => EventKind::AlternativeFirstExon,
+            };
+            events.push((kind, vec![e1, s1, last_a.end, e1, s2, last_b.end], a, b));
+        }
+    }
+
+    events
+}
+
+#[cfg(test)]
+mod test_splicing {
+    use super::*;
+    use crate::transcripts::TranscriptBuilder;
+
+    fn transcript(tx_id: &str, strand: Strand, exons: &[(u64, u64)]) -> Transcript {
+        exons
+            .iter()
+            .fold(
+                TranscriptBuilder::new(tx_id, "gene1", "1".parse().unwrap(), strand),
+                |builder, &(start, end)| builder.exon(start, end),
+            )
+            .build()
+            .unwrap()
+    }
+
+    fn ids(transcripts: &[Transcript]) -> Vec<String> {
+        splicing_events(transcripts)
+            .iter()
+            .map(|e| e.id())
+            .collect()
+    }
+
+    #[test]
+    fn test_skipped_exon_and_retained_intron() {
+        let transcripts = [
+            transcript(
+                "tx1",
+                Strand::Forward,
+                &[(101, 200), (301, 400), (501, 600)],
+            ),
+            transcript("tx2", Strand::Forward, &[(101, 200), (501, 600)]),
+            transcript("tx3", Strand::Forward, &[(101, 400), (501, 600)]),
+        ];
+        let events = splicing_events(&transcripts);
+        let skipped = events
+            .iter()
+            .find(|e| e.kind == EventKind::SkippedExon)
+            .unwrap();
+        assert_eq!(skipped.id(), "gene1;SE:1:200-301:400-501:+");
+        assert_eq!(skipped.inclusion, BTreeSet::from(["tx1".to_string()]));
+        assert_eq!(skipped.exclusion, BTreeSet::from(["tx2".to_string()]));
+
+        let retained = events
+            .iter()
+            .find(|e| e.kind == EventKind::RetainedIntron)
+            .unwrap();
+        assert_eq!(retained.id(), "gene1;RI:1:101:200-301:400:+");
+        assert_eq!(retained.inclusion, BTreeSet::from(["tx3".to_string()]));
+        assert_eq!(retained.exclusion, BTreeSet::from(["tx1".to_string()]));
+
+        let comparison = IsoformComparison::new(&transcripts[0], &transcripts[2]);
+        assert_eq!(comparison.shared_exons, vec![(501, 600)]);
+        assert_eq!(comparison.shared_junctions, vec![(400, 501)]);
+        assert!(!comparison.same_intron_chain());
+    }
+
+    #[test]
+    fn test_splice_sites_and_exclusive_exons() {
+        let forward = [
+            transcript("tx1", Strand::Forward, &[(101, 200), (301, 400)]),
+            transcript("tx2", Strand::Forward, &[(101, 180), (301, 400)]),
+            transcript("tx3", Strand::Forward, &[(101, 200), (321, 400)]),
+        ];
+        assert_eq!(
+            ids(&forward),
+            vec![
+                "gene1;A5:1:200-301:180-301:+",
+                "gene1;A3:1:200-301:200-321:+"
+            ]
+        );
+
+        let reverse = [
+            transcript("tx1", Strand::Reverse, &[(101, 200), (301, 400)]),
+            transcript("tx2", Strand::Reverse, &[(101, 180), (301, 400)]),
+        ];
+        assert_eq!(ids(&reverse), vec!["gene1;A3:1:200-301:180-301:-"]);
+
+        let exclusive = [
+            transcript(
+                "tx1",
+                Strand::Forward,
+                &[(101, 200), (301, 400), (701, 800)],
+            ),
+            transcript(
+                "tx2",
+                Strand::Forward,
+                &[(101, 200), (501, 600), (701, 800)],
+            ),
+        ];
+        assert_eq!(
+            ids(&exclusive),
+            vec!["gene1;MX:1:200-301:400-701:200-501:600-701:+"]
+        );
+    }
+
+    #[test]
+    fn test_terminal_exons() {
+        let transcripts = [
+            transcript(
+                "tx1",
+                Strand::Forward,
+                &[(101, 200), (501, 600), (701, 800)],
+            ),
+            transcript(
+                "tx2",
+                Strand::Forward,
+                &[(301, 400), (501, 600), (901, 1000)],
+            ),
+        ];
+        assert_eq!(
+            ids(&transcripts),
+            vec![
+                "gene1;AF:1:101:200-501:301:400-501:+",
+                "gene1;AL:1:600-701:800:600-901:1000:+"
+            ]
+        );
+
+        let mut buffer = Vec::new();
+        write_ioe(&mut buffer, &splicing_events(&transcripts)).unwrap();
+        let ioe = String::from_utf8(buffer).unwrap();
+        assert_eq!(
+            ioe.lines().nth(1).unwrap(),
+            "1\tgene1\tgene1;AF:1:101:200-501:301:400-501:+\ttx1\ttx1,tx2"
+        );
+    }
+}
@@ -1,15 +1,16 @@
+use std::error::Error;
+use std::fmt;
+
+use common::Seqid;
+use gff::chain::ChainMap;
+use gff::liftover::{lift_interval, LiftoverFailure, LiftoverOptions};
+use gff::Strand;
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
-#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
-pub enum Chromosome {
-    Char(char),
-    Number(u64),
-}
-
-#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Copy)]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 pub struct GenomePosition {
-    chromosome: Chromosome,
+    chromosome: Seqid,
     pub start: u64,
     pub end: u64,
 }
@@ -20,17 +21,21 @@ pub struct Transcript {
     pub tx_id: String,
     pub gene_id: String,
     pub position: GenomePosition,
+    pub strand: Strand,
     pub cds: Vec<GenomePosition>,
     pub exons: Vec<GenomePosition>,
 }
 
 impl Transcript {
+    /// Creates a transcript without checking its parts; see [`TranscriptBuilder`].
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         tx_id: &str,
         gene_id: &str,
-        chromosome: Chromosome,
+        chromosome: Seqid,
         start: u64,
         end: u64,
+        strand: Strand,
         cds: Vec<GenomePosition>,
         exons: Vec<GenomePosition>,
     ) -> Self {
@@ -43,8 +48,682 @@ impl Transcript {
                 start,
                 end,
             },
+            strand,
             cds,
             exons,
         }
     }
+
+    pub fn chromosome(&self) -> &Seqid {
+        &self.position.chromosome
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TranscriptError {
+    /// Neither exons nor CDS were given.
+    Empty,
+    InvalidInterval {
+        start: u64,
+        end: u64,
+    },
+    UnsortedExons {
+        start: u64,
+        previous_start: u64,
+    },
+    OverlappingExons {
+        start: u64,
+        previous_end: u64,
+    },
+    UnsortedCds {
+        start: u64,
+        previous_start: u64,
+    },
+    OverlappingCds {
+        start: u64,
+        previous_end: u64,
+    },
+    CdsOutsideExons {
+        start: u64,
+        end: u64,
+    },
+    OutsideSpan {
+        start: u64,
+        end: u64,
+    },
+}
+
+impl fmt::Display for TranscriptError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Empty => write!(f, "transcript has neither exons nor CDS"),
+            Self::InvalidInterval { start, end } => write!(f, "invalid interval {}-{}", start, end),
+            Self::UnsortedExons {
+                start,
+                previous_start,
+            } => write!(
+                f,
+                "exon starting at {} follows an exon starting at {}",
+                start, previous_start
+            ),
+            Self::OverlappingExons {
+                start,
+                previous_end,
+            } => write!(
+                f,
+                "exon starting at {} overlaps an exon ending at {}",
+                start, previous_end
+            ),
+            Self::UnsortedCds {
+                start,
+                previous_start,
+            } => write!(
+                f,
+                "CDS starting at {} follows a CDS starting at {}",
+                start, previous_start
+            ),
+            Self::OverlappingCds {
+                start,
+                previous_end,
+            } => write!(
+                f,
+                "CDS starting at {} overlaps a CDS ending at {}",
+                start, previous_end
+            ),
+            Self::CdsOutsideExons { start, end } => {
+                write!(f, "CDS {}-{} is not contained in an exon", start, end)
+            }
+            Self::OutsideSpan { start, end } => {
+                write!(f, "{}-{} extends beyond the transcript", start, end)
+            }
+        }
+    }
+}
+
+impl Error for TranscriptError {}
+
+/// Checks that intervals are sorted by start and disjoint.
+fn check_order(
+    intervals: &[(u64, u64)],
+    unsorted: fn(u64, u64) -> TranscriptError,
+    overlapping: fn(u64, u64) -> TranscriptError,
+) -> Result<(), TranscriptError> {
+    for pair in intervals.windows(2) {
+        let ((previous_start, previous_end), (start, _)) = (pair[0], pair[1]);
+        if start < previous_start {
+            return Err(unsorted(start, previous_start));
+        }
+        if start <= previous_end {
+            return Err(overlapping(start, previous_end));
+        }
+    }
+    Ok(())
+}
+
+/// Builds a [`Transcript`], checking that exons are sorted and disjoint, that the CDS lies
+/// within exons, or is itself sorted and disjoint when there are no exons, and that the span
+/// covers every part. The span defaults to the exons, or the
+/// CDS for transcripts annotated without exons.
+#[derive(Debug, Clone)]
+pub struct TranscriptBuilder {
+    tx_id: String,
+    gene_id: String,
+    chromosome: Seqid,
+    strand: Strand,
+    span: Option<(u64, u64)>,
+    exons: Vec<(u64, u64)>,
+    cds: Vec<(u64, u64)>,
+}
+
+impl TranscriptBuilder {
+    pub fn new(tx_id: &str, gene_id: &str, chromosome: Seqid, strand: Strand) -> Self {
+        Self {
+            tx_id: tx_id.to_string(),
+            gene_id: gene_id.to_string(),
+            chromosome,
+            strand,
+            span: None,
+            exons: Vec::new(),
+            cds: Vec::new(),
+        }
+    }
+
+    pub fn span(mut self, start: u64, end: u64) -> Self {
+        self.span = Some((start, end));
+        self
+    }
+
+    /// Adds an exon; exons must be added in genomic order.
+    pub fn exon(mut self, start: u64, end: u64) -> Self {
+        self.exons.push((start, end));
+        self
+    }
+
+    /// Adds a CDS segment; without exons, segments must be added in genomic order.
+    pub fn cds(mut self, start: u64, end: u64) -> Self {
+        self.cds.push((start, end));
+        self
+    }
+
+    pub fn build(self) -> Result<Transcript, TranscriptError> {
+        let intervals = self.exons.iter().chain(&self.cds).chain(&self.span);
+        if let Some(&(start, end)) = intervals
+            .clone()
+            .find(|(start, end)| *start == 0 || start > end)
+        {
+            return Err(TranscriptError::InvalidInterval { start, end });
+        }
+
+        check_order(
+            &self.exons,
+            |start, previous_start| TranscriptError::UnsortedExons {
+                start,
+                previous_start,
+            },
+            |start, previous_end| TranscriptError::OverlappingExons {
+                start,
+                previous_end,
+            },
+        )?;
+        // Without exons the CDS segments stand in for them.
+        if self.exons.is_empty() {
+            check_order(
+                &self.cds,
+                |start, previous_start| TranscriptError::UnsortedCds {
+                    start,
+                    previous_start,
+                },
+                |start, previous_end| TranscriptError::OverlappingCds {
+                    start,
+                    previous_end,
+                },
+            )?;
+        }
+        if !self.exons.is_empty() {
+            if let Some(&(start, end)) = self
+                .cds
+                .iter()
+                .find(|(start, end)| !self.exons.iter().any(|e| e.0 <= *start && *end <= e.1))
+            {
+                return Err(TranscriptError::CdsOutsideExons { start, end });
+            }
+        }
+
+        let parts = match self.exons.is_empty() {
+            true => &self.cds,
+            false => &self.exons,
+        };
+        let (start, end) = match self.span {
+            Some(span) => span,
+            None => (
+                parts
+                    .iter()
+                    .map(|p| p.0)
+                    .min()
+                    .ok_or(TranscriptError::Empty)?,
+                parts
+                    .iter()
+                    .map(|p| p.1)
+                    .max()
+                    .ok_or(TranscriptError::Empty)?,
+            ),
+        };
+        if parts.is_empty() {
+            return Err(TranscriptError::Empty);
+        }
+        if let Some(&(part_start, part_end)) = parts.iter().find(|p| p.0 < start || p.1 > end) {
+            return Err(TranscriptError::OutsideSpan {
+                start: part_start,
+                end: part_end,
+            });
+        }
+
+        let positions = |intervals: &[(u64, u64)]| {
+            intervals
+                .iter()
+                .map(|&(start, end)| GenomePosition::new(self.chromosome.clone(), start, end))
+                .collect()
+        };
+        Ok(Transcript::new(
+            &self.tx_id,
+            &self.gene_id,
+            self.chromosome.clone(),
+            start,
+            end,
+            self.strand,
+            positions(&self.cds),
+            positions(&self.exons),
+        ))
+    }
+}
+
+impl GenomePosition {
+    pub fn new(chromosome: Seqid, start: u64, end: u64) -> Self {
+        Self {
+            chromosome,
+            start,
+            end,
+        }
+    }
+
+    pub fn chromosome(&self) -> &Seqid {
+        &self.chromosome
+    }
+
+    /// Lifts the position to a new assembly; it must map to a single chain.
+    pub fn liftover(
+        &self,
+        chains: &ChainMap,
+        options: &LiftoverOptions,
+    ) -> Result<GenomePosition, LiftoverFailure> {
+        self.lift(chains, options).map(|(position, _)| position)
+    }
+
+    /// Lifted position, and whether the new assembly is reversed there.
+    fn lift(
+        &self,
+        chains: &ChainMap,
+        options: &LiftoverOptions,
+    ) -> Result<(GenomePosition, bool), LiftoverFailure> {
+        let options = LiftoverOptions {
+            allow_split: false,
+            ..*options
+        };
+        let lifted = lift_interval(chains, &self.chromosome, self.start, self.end, &options)?;
+        let lifted = &lifted[0];
+        let chromosome = lifted
+            .seqid
+            .parse::<Seqid>()
+            .map_err(|_| LiftoverFailure::UnsupportedSeqid(lifted.seqid.clone()))?;
+
+        let position = GenomePosition {
+            chromosome,
+            start: lifted.start,
+            end: lifted.end,
+        };
+        Ok((position, lifted.reversed))
+    }
+}
+
+/// Segments sorted by start.
+fn sorted(positions: &[GenomePosition]) -> Vec<GenomePosition> {
+    let mut positions = positions.to_vec();
+    positions.sort_by_key(|p| p.start);
+    positions
+}
+
+fn total_length(positions: &[GenomePosition]) -> u64 {
+    positions.iter().map(|p| p.end + 1 - p.start).sum()
+}
+
+/// 1-based offset of a genomic position in the spliced segments, counted on the strand.
+fn spliced_offset(segments: &[GenomePosition], strand: Strand, position: u64) -> Option<u64> {
+    let mut offset = 0;
+    for segment in sorted(segments) {
+        if (segment.start..=segment.end).contains(&position) {
+            let forward = offset + position - segment.start + 1;
+            return Some(match strand {
+                Strand::Forward => forward,
+                Strand::Reverse => total_length(segments) + 1 - forward,
+            });
+        }
+        offset += segment.end + 1 - segment.start;
+    }
+    None
+}
+
+/// Genomic position of a 1-based offset in the spliced segments, counted on the strand.
+fn genomic_position(segments: &[GenomePosition], strand: Strand, offset: u64) -> Option<u64> {
+    let length = total_length(segments);
+    if offset == 0 || offset > length {
+        return None;
+    }
+    let mut remaining = match strand {
+        Strand::Forward => offset,
+        Strand::Reverse => length + 1 - offset,
+    };
+    for segment in sorted(segments) {
+        let segment_length = segment.end + 1 - segment.start;
+        if remaining <= segment_length {
+            return Some(segment.start + remaining - 1);
+        }
+        remaining -= segment_length;
+    }
+    None
+}
+
+/// Parts of `segments` before `start` and after `end`.
+fn outside(
+    segments: &[GenomePosition],
+    start: u64,
+    end: u64,
+) -> (Vec<GenomePosition>, Vec<GenomePosition>) {
+    let (mut before, mut after) = (Vec::new(), Vec::new());
+    for segment in sorted(segments) {
+        if segment.start < start {
+            before.push(GenomePosition {
+                end: segment.end.min(start - 1),
+                ..segment.clone()
+            });
+        }
+        if segment.end > end {
+            after.push(GenomePosition {
+                start: segment.start.max(end + 1),
+                ..segment
+            });
+        }
+    }
+    (before, after)
+}
+
+/// Views derived from exons and CDS. Exons fall back to the CDS for transcripts annotated
+/// without exons, and segments are returned in genomic order.
+impl Transcript {
+    pub fn exon_positions(&self) -> Vec<GenomePosition> {
+        match self.exons.is_empty() {
+            true => sorted(&self.cds),
+            false => sorted(&self.exons),
+        }
+    }
+
+    pub fn introns(&self) -> Vec<GenomePosition> {
+        self.exon_positions()
+            .windows(2)
+            .filter(|pair| pair[1].start > pair[0].end + 1)
+            .map(|pair| GenomePosition {
+                start: pair[0].end + 1,
+                end: pair[1].start - 1,
+                ..pair[0].clone()
+            })
+            .collect()
+    }
+
+    /// Last base of the exon before each intron and first base of the exon after it.
+    pub fn splice_junctions(&self) -> Vec<(u64, u64)> {
+        self.introns()
+            .iter()
+            .map(|intron| (intron.start - 1, intron.end + 1))
+            .collect()
+    }
+
+    /// Lowest and highest CDS coordinates.
+    fn cds_span(&self) -> Option<(u64, u64)> {
+        let start = self.cds.iter().map(|c| c.start).min()?;
+        let end = self.cds.iter().map(|c| c.end).max()?;
+        Some((start, end))
+    }
+
+    pub fn five_prime_utr(&self) -> Vec<GenomePosition> {
+        let Some((start, end)) = self.cds_span() else {
+            return Vec::new();
+        };
+        let (before, after) = outside(&self.exon_positions(), start, end);
+        match self.strand {
+            Strand::Forward => before,
+            Strand::Reverse => after,
+        }
+    }
+
+    pub fn three_prime_utr(&self) -> Vec<GenomePosition> {
+        let Some((start, end)) = self.cds_span() else {
+            return Vec::new();
+        };
+        let (before, after) = outside(&self.exon_positions(), start, end);
+        match self.strand {
+            Strand::Forward => after,
+            Strand::Reverse => before,
+        }
+    }
+
+    /// Genomic position of the first CDS base on the strand, where translation starts.
+    pub fn cds_start(&self) -> Option<u64> {
+        let (start, end) = self.cds_span()?;
+        Some(match self.strand {
+            Strand::Forward => start,
+            Strand::Reverse => end,
+        })
+    }
+
+    /// Genomic position of the last CDS base on the strand, the end of the stop codon when
+    /// annotated.
+    pub fn cds_end(&self) -> Option<u64> {
+        let (start, end) = self.cds_span()?;
+        Some(match self.strand {
+            Strand::Forward => end,
+            Strand::Reverse => start,
+        })
+    }
+
+    pub fn spliced_length(&self) -> u64 {
+        total_length(&self.exon_positions())
+    }
+
+    pub fn cds_length(&self) -> u64 {
+        total_length(&self.cds)
+    }
+
+    /// 1-based position in the spliced transcript, or `None` outside exons.
+    pub fn genomic_to_transcript(&self, position: u64) -> Option<u64> {
+        spliced_offset(&self.exon_positions(), self.strand, position)
+    }
+
+    pub fn transcript_to_genomic(&self, position: u64) -> Option<u64> {
+        genomic_position(&self.exon_positions(), self.strand, position)
+    }
+
+    /// 1-based position in the spliced CDS, or `None` outside the CDS.
+    pub fn genomic_to_cds(&self, position: u64) -> Option<u64> {
+        spliced_offset(&self.cds, self.strand, position)
+    }
+
+    pub fn cds_to_genomic(&self, position: u64) -> Option<u64> {
+        genomic_position(&self.cds, self.strand, position)
+    }
+
+    /// Position in the spliced CDS of a position in the spliced transcript.
+    pub fn transcript_to_cds(&self, position: u64) -> Option<u64> {
+        self.genomic_to_cds(self.transcript_to_genomic(position)?)
+    }
+
+    pub fn cds_to_transcript(&self, position: u64) -> Option<u64> {
+        self.genomic_to_transcript(self.cds_to_genomic(position)?)
+    }
+}
+
+impl Transcript {
+    /// Lifts the transcript span, exons and CDS to a new assembly, keeping the same identity.
+    ///
+    /// Fails when any part fails to lift or when parts end up on different chromosomes. The
+    /// span may cross alignment gaps in introns, while exons and CDS segments follow the gap
+    /// options. The strand flips where the new assembly is reversed.
+    pub fn liftover(
+        &self,
+        chains: &ChainMap,
+        options: &LiftoverOptions,
+    ) -> Result<Transcript, LiftoverFailure> {
+        let span_options = LiftoverOptions {
+            allow_gaps: true,
+            ..*options
+        };
+        let (position, reversed) = self.position.lift(chains, &span_options)?;
+        let strand = match (reversed, self.strand) {
+            (false, strand) => strand,
+            (true, Strand::Forward) => Strand::Reverse,
+            (true, Strand::Reverse) => Strand::Forward,
+        };
+        let lift_all =
+            |positions: &[GenomePosition]| -> Result<Vec<GenomePosition>, LiftoverFailure> {
+                let mut lifted = positions
+                    .iter()
+                    .map(|p| p.liftover(chains, options))
+                    .collect::<Result<Vec<_>, _>>()?;
+                if lifted.iter().any(|p| p.chromosome != position.chromosome) {
+                    return Err(LiftoverFailure::DifferentChromosomes);
+                }
+                lifted.sort_by_key(|p| p.start);
+                Ok(lifted)
+            };
+
+        let cds = lift_all(&self.cds)?;
+        let exons = lift_all(&self.exons)?;
+        Ok(Transcript {
+            position,
+            strand,
+            cds,
+            exons,
+            ..self.clone()
+        })
+    }
+}
+
+#[cfg(test)]
+mod test_transcripts {
+    use super::*;
+
+    #[test]
+    fn test_transcript_liftover() {
+        let chains = ChainMap::from_reader(
+            "chain 1000 1 1000 + 0 1000 2 2000 + 1000 1990 1\n400\t20\t10\n580\n".as_bytes(),
+        )
+        .unwrap();
+        let exon = |start, end| GenomePosition {
+            chromosome: "1".parse().unwrap(),
+            start,
+            end,
+        };
+        let transcript = Transcript::new(
+            "tx1",
+            "gene1",
+            "1".parse().unwrap(),
+            101,
+            600,
+            Strand::Forward,
+            vec![exon(151, 350), exon(501, 550)],
+            vec![exon(101, 350), exon(501, 600)],
+        );
+
+        let lifted = transcript
+            .liftover(&chains, &LiftoverOptions::default())
+            .unwrap();
+        assert_eq!(lifted.id, transcript.id);
+        assert_eq!(lifted.position.chromosome, "2");
+        assert_eq!((lifted.position.start, lifted.position.end), (1101, 1590));
+        assert_eq!(
+            lifted.exons[1],
+            GenomePosition {
+                chromosome: "2".parse().unwrap(),
+                start: 1491,
+                end: 1590
+            }
+        );
+
+        let gapped = Transcript {
+            exons: vec![exon(101, 600)],
+            ..transcript.clone()
+        };
+        assert_eq!(
+            gapped
+                .liftover(&chains, &LiftoverOptions::default())
+                .unwrap_err(),
+            LiftoverFailure::Gapped { gaps: 1 }
+        );
+    }
+
+    fn coding_transcript(strand: Strand) -> Transcript {
+        TranscriptBuilder::new("tx1", "gene1", "1".parse().unwrap(), strand)
+            .exon(100, 200)
+            .exon(301, 400)
+            .cds(150, 200)
+            .cds(301, 350)
+            .build()
+            .unwrap()
+    }
+
+    #[test]
+    fn test_derived_segments() {
+        let tx = coding_transcript(Strand::Forward);
+        assert_eq!(
+            tx.introns(),
+            vec![GenomePosition::new("1".parse().unwrap(), 201, 300)]
+        );
+        assert_eq!(tx.splice_junctions(), vec![(200, 301)]);
+        assert_eq!(tx.spliced_length(), 201);
+        assert_eq!(tx.cds_length(), 101);
+
+        let utr = |start, end| vec![GenomePosition::new("1".parse().unwrap(), start, end)];
+        assert_eq!(tx.five_prime_utr(), utr(100, 149));
+        assert_eq!(tx.three_prime_utr(), utr(351, 400));
+        let tx = coding_transcript(Strand::Reverse);
+        assert_eq!(tx.five_prime_utr(), utr(351, 400));
+        assert_eq!(tx.cds_start(), Some(350));
+        assert_eq!(tx.cds_end(), Some(150));
+    }
+
+    #[test]
+    fn test_coordinate_conversions() {
+        let forward = coding_transcript(Strand::Forward);
+        let reverse = coding_transcript(Strand::Reverse);
+        assert_eq!(forward.genomic_to_transcript(301), Some(102));
+        assert_eq!(forward.genomic_to_transcript(250), None);
+        assert_eq!(reverse.genomic_to_transcript(400), Some(1));
+        assert_eq!(forward.transcript_to_genomic(102), Some(301));
+        assert_eq!(forward.transcript_to_genomic(202), None);
+
+        assert_eq!(forward.genomic_to_cds(150), Some(1));
+        assert_eq!(reverse.genomic_to_cds(301), Some(50));
+        assert_eq!(reverse.cds_to_genomic(51), Some(200));
+        assert_eq!(forward.transcript_to_cds(51), Some(1));
+        assert_eq!(reverse.cds_to_transcript(1), Some(51));
+    }
+
+    #[test]
+    fn test_builder_validation() {
+        let builder = TranscriptBuilder::new("tx1", "gene1", "1".parse().unwrap(), Strand::Forward);
+        let tx = builder.clone().cds(10, 30).build().unwrap();
+        assert_eq!((tx.position.start, tx.position.end), (10, 30));
+        assert_eq!(tx.chromosome(), "1");
+
+        let error = |builder: TranscriptBuilder| builder.build().unwrap_err();
+        assert_eq!(error(builder.clone()), TranscriptError::Empty);
+        assert_eq!(
+            error(builder.clone().exon(30, 10)),
+            TranscriptError::InvalidInterval { start: 30, end: 10 }
+        );
+        assert_eq!(
+            error(builder.clone().exon(50, 60).exon(10, 20)),
+            TranscriptError::UnsortedExons {
+                start: 10,
+                previous_start: 50
+            }
+        );
+        assert_eq!(
+            error(builder.clone().exon(10, 20).exon(20, 30)),
+            TranscriptError::OverlappingExons {
+                start: 20,
+                previous_end: 20
+            }
+        );
+        assert_eq!(
+            error(builder.clone().exon(10, 20).exon(30, 40).cds(15, 35)),
+            TranscriptError::CdsOutsideExons { start: 15, end: 35 }
+        );
+        assert_eq!(
+            error(builder.clone().cds(50, 60).cds(10, 20)),
+            TranscriptError::UnsortedCds {
+                start: 10,
+                previous_start: 50
+            }
+        );
+        assert_eq!(
+            error(builder.clone().cds(10, 20).cds(15, 30)),
+            TranscriptError::OverlappingCds {
+                start: 15,
+                previous_end: 20
+            }
+        );
+        assert_eq!(
+            error(builder.span(10, 35).exon(10, 20).exon(30, 40)),
+            TranscriptError::OutsideSpan { start: 30, end: 40 }
+        );
+    }
 }
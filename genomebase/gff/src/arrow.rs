@@ -0,0 +1,582 @@
+//! Conversion of GFF records to Apache Arrow record batches and Parquet files.
+//!
+//! Coordinates, score, strand and phase become typed columns. Attributes are stored either in
+//! a single `attributes` map column (tag to list of values) or flattened into one string
+//! column per tag, named `attributes.<tag>`, holding the percent-encoded GFF value. The
+//! `attribute_arrays` column lists the tags of each row whose values are arrays, so that
+//! one-element arrays are read back as arrays.
+
+use std::io::Write;
+use std::sync::Arc;
+
+use arrow::array::{
+    Array, ArrayRef, AsArray, Float64Builder, ListBuilder, MapBuilder, PrimitiveArray, RecordBatch,
+    StringBuilder, UInt32Builder, UInt8Builder,
+};
+use arrow::compute::{cast_with_options, CastOptions};
+use arrow::datatypes::{ArrowPrimitiveType, Field, Float64Type, Schema, UInt32Type, UInt8Type};
+use arrow::error::ArrowError;
+use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+use parquet::arrow::ArrowWriter;
+use parquet::errors::ParquetError;
+use parquet::file::reader::ChunkReader;
+
+use crate::attributes::{parse_value, Attributes, Tag, Value};
+use crate::{GffRecord, Phase, Strand};
+
+pub const ATTRIBUTES_COLUMN: &str = "attributes";
+pub const ARRAYS_COLUMN: &str = "attribute_arrays";
+const FLATTENED_PREFIX: &str = "attributes.";
+pub const DEFAULT_BATCH_SIZE: usize = 8192;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum AttributeLayout {
+    /// One `Map<Utf8, List<Utf8>>` column.
+    Map,
+    /// One nullable string column per listed tag. Records with other tags are rejected.
+    Flattened(Vec<Tag>),
+}
+
+impl AttributeLayout {
+    /// Flattened layout over every tag used by the records, in order of first appearance.
+    pub fn flattened_from(records: &[GffRecord]) -> Self {
+        let mut tags: Vec<Tag> = Vec::new();
+        for record in records {
+            for tag in record.attributes.keys() {
+                if !tags.contains(tag) {
+                    tags.push(tag.clone());
+                }
+            }
+        }
+        Self::Flattened(tags)
+    }
+}
+
+fn phase_number(phase: Phase) -> u8 {
+    match phase {
+        Phase::Zero => 0,
+        Phase::One => 1,
+        Phase::Two => 2,
+    }
+}
+
+fn attribute_columns(
+    records: &[GffRecord],
+    layout: &AttributeLayout,
+) -> Result<Vec<(String, ArrayRef)>, ArrowError> {
+    match layout {
+        AttributeLayout::Map => {
+            let mut builder = MapBuilder::new(
+                None,
+                StringBuilder::new(),
+                ListBuilder::new(StringBuilder::new()),
+            );
+            for record in records {
+                for (tag, value) in &record.attributes {
+                    builder.keys().append_value(tag.as_ref());
+                    let values = builder.values();
+                    for v in value.iter() {
+                        values.values().append_value(v);
+                    }
+                    values.append(true);
+                }
+                builder.append(true)?;
+            }
+            Ok(vec![(
+                ATTRIBUTES_COLUMN.to_string(),
+                Arc::new(builder.finish()),
+            )])
+        }
+        AttributeLayout::Flattened(tags) => {
+            if let Some(tag) = records
+                .iter()
+                .flat_map(|r| r.attributes.keys())
+                .find(|tag| !tags.contains(tag))
+            {
+                return Err(ArrowError::InvalidArgumentError(format!(
+                    "attribute {} is not part of the flattened layout",
+                    tag
+                )));
+            }
+            Ok(tags
+                .iter()
+                .map(|tag| {
+                    let mut builder = StringBuilder::new();
+                    for record in records {
+                        builder.append_option(record.attribute(tag).map(|v| v.to_string()));
+                    }
+                    let column: ArrayRef = Arc::new(builder.finish());
+                    (format!("{}{}", FLATTENED_PREFIX, tag), column)
+                })
+                .collect())
+        }
+    }
+}
+
+/// Converts records into one record batch.
+pub fn records_to_batch(
+    records: &[GffRecord],
+    layout: &AttributeLayout,
+) -> Result<RecordBatch, ArrowError> {
+    let mut seqid = StringBuilder::new();
+    let mut source = StringBuilder::new();
+    let mut r#type = StringBuilder::new();
+    let mut start = UInt32Builder::new();
+    let mut end = UInt32Builder::new();
+    let mut score = Float64Builder::new();
+    let mut strand = StringBuilder::new();
+    let mut phase = UInt8Builder::new();
+
+    for record in records {
+        seqid.append_value(&record.seqid);
+        source.append_value(&record.source);
+        r#type.append_value(&record.r#type);
+        start.append_value(record.start);
+        end.append_value(record.end);
+        score.append_option(record.score);
+        strand.append_option(record.strand.as_ref().map(|s| s.as_ref()));
+        phase.append_option(record.phase.map(phase_number));
+    }
+
+    let mut columns: Vec<(String, ArrayRef, bool)> = vec![
+        ("seqid".to_string(), Arc::new(seqid.finish()), false),
+        ("source".to_string(), Arc::new(source.finish()), false),
+        ("type".to_string(), Arc::new(r#type.finish()), false),
+        ("start".to_string(), Arc::new(start.finish()), false),
+        ("end".to_string(), Arc::new(end.finish()), false),
+        ("score".to_string(), Arc::new(score.finish()), true),
+        ("strand".to_string(), Arc::new(strand.finish()), true),
+        ("phase".to_string(), Arc::new(phase.finish()), true),
+    ];
+    let nullable_attributes = matches!(layout, AttributeLayout::Flattened(_));
+    for (name, column) in attribute_columns(records, layout)? {
+        columns.push((name, column, nullable_attributes));
+    }
+    columns.push((ARRAYS_COLUMN.to_string(), array_tags(records), true));
+
+    let fields: Vec<Field> = columns
+        .iter()
+        .map(|(name, column, nullable)| Field::new(name, column.data_type().clone(), *nullable))
+        .collect();
+    RecordBatch::try_new(
+        Arc::new(Schema::new(fields)),
+        columns.into_iter().map(|(_, column, _)| column).collect(),
+    )
+}
+
+fn column<'a>(batch: &'a RecordBatch, name: &str) -> Result<&'a ArrayRef, ArrowError> {
+    batch
+        .column_by_name(name)
+        .ok_or_else(|| ArrowError::SchemaError(format!("missing column: {}", name)))
+}
+
+/// Values of a string column of any string type.
+fn string_column(batch: &RecordBatch, name: &str) -> Result<Vec<Option<String>>, ArrowError> {
+    strings(column(batch, name)?.as_ref(), name)
+}
+
+/// A numeric column cast to `T`, failing on values out of the range of `T`.
+fn primitive_column<T: ArrowPrimitiveType>(
+    batch: &RecordBatch,
+    name: &str,
+) -> Result<PrimitiveArray<T>, ArrowError> {
+    let array = column(batch, name)?;
+    if !array.data_type().is_numeric() {
+        return Err(ArrowError::SchemaError(format!(
+            "column {} has an invalid type",
+            name
+        )));
+    }
+    let options = CastOptions {
+        safe: false,
+        ..CastOptions::default()
+    };
+    let array = cast_with_options(array, &T::DATA_TYPE, &options)
+        .map_err(|e| ArrowError::ParseError(format!("column {}: {}", name, e)))?;
+    Ok(array.as_primitive::<T>().clone())
+}
+
+fn required<T>(value: Option<T>, name: &str, row: usize) -> Result<T, ArrowError> {
+    value.ok_or_else(|| ArrowError::ParseError(format!("column {} is null in row {}", name, row)))
+}
+
+fn invalid<E: std::fmt::Display>(e: E) -> ArrowError {
+    ArrowError::ParseError(e.to_string())
+}
+
+/// The tags of each record whose values are arrays, or null when there are none.
+fn array_tags(records: &[GffRecord]) -> ArrayRef {
+    let mut builder = ListBuilder::new(StringBuilder::new());
+    for record in records {
+        let mut tags = record
+            .attributes
+            .iter()
+            .filter(|(_, value)| matches!(value, Value::Array(_)))
+            .map(|(tag, _)| tag.as_ref())
+            .peekable();
+        if tags.peek().is_none() {
+            builder.append_null();
+            continue;
+        }
+        for tag in tags {
+            builder.values().append_value(tag);
+        }
+        builder.append(true);
+    }
+    Arc::new(builder.finish())
+}
+
+/// Values of a `Utf8`, `LargeUtf8` or `Utf8View` array.
+fn strings(array: &dyn Array, name: &str) -> Result<Vec<Option<String>>, ArrowError> {
+    let owned = |v: Option<&str>| v.map(str::to_string);
+    if let Some(array) = array.as_string_opt::<i32>() {
+        Ok(array.iter().map(owned).collect())
+    } else if let Some(array) = array.as_string_opt::<i64>() {
+        Ok(array.iter().map(owned).collect())
+    } else if let Some(array) = array.as_string_view_opt() {
+        Ok(array.iter().map(owned).collect())
+    } else {
+        Err(ArrowError::SchemaError(format!(
+            "{} values are not strings",
+            name
+        )))
+    }
+}
+
+/// Element `i` of a `List` or `LargeList` array.
+fn list_value(array: &dyn Array, i: usize, name: &str) -> Result<ArrayRef, ArrowError> {
+    if let Some(list) = array.as_list_opt::<i32>() {
+        Ok(list.value(i))
+    } else if let Some(list) = array.as_list_opt::<i64>() {
+        Ok(list.value(i))
+    } else {
+        Err(ArrowError::SchemaError(format!(
+            "{} values are not lists",
+            name
+        )))
+    }
+}
+
+/// Array tags of each row, or `None` for files without the column, whose one-element lists
+/// are read as strings.
+fn read_array_tags(batch: &RecordBatch) -> Result<Vec<Option<Vec<String>>>, ArrowError> {
+    let Some(column) = batch.column_by_name(ARRAYS_COLUMN) else {
+        return Ok(vec![None; batch.num_rows()]);
+    };
+    (0..batch.num_rows())
+        .map(|row| {
+            if column.is_null(row) {
+                return Ok(Some(Vec::new()));
+            }
+            let tags = list_value(column.as_ref(), row, ARRAYS_COLUMN)?;
+            Ok(Some(
+                strings(tags.as_ref(), ARRAYS_COLUMN)?
+                    .into_iter()
+                    .flatten()
+                    .collect(),
+            ))
+        })
+        .collect()
+}
+
+fn shaped(value: Value, tag: &str, array_tags: &Option<Vec<String>>) -> Value {
+    let is_array = array_tags
+        .as_ref()
+        .map(|tags| tags.iter().any(|t| t == tag));
+    match (value, is_array) {
+        (Value::String(value), Some(true)) => Value::Array(vec![value]),
+        (Value::Array(mut values), Some(false) | None) if values.len() == 1 => {
+            Value::String(values.remove(0))
+        }
+        (value, _) => value,
+    }
+}
+
+fn read_attributes(batch: &RecordBatch) -> Result<Vec<Attributes>, ArrowError> {
+    let mut attributes = vec![Attributes::new(); batch.num_rows()];
+    let array_tags = read_array_tags(batch)?;
+
+    if let Some(map) = batch.column_by_name(ATTRIBUTES_COLUMN) {
+        let map = map.as_map_opt().ok_or_else(|| {
+            ArrowError::SchemaError(format!("column {} is not a map", ATTRIBUTES_COLUMN))
+        })?;
+        for (row, row_attributes) in attributes.iter_mut().enumerate() {
+            let entries = map.value(row);
+            let keys = strings(entries.column(0).as_ref(), ATTRIBUTES_COLUMN)?;
+            for (i, key) in keys.into_iter().enumerate() {
+                let key = key.unwrap_or_default();
+                let list = list_value(entries.column(1).as_ref(), i, ATTRIBUTES_COLUMN)?;
+                let items = strings(list.as_ref(), ATTRIBUTES_COLUMN)?;
+                let value = Value::Array(items.into_iter().flatten().collect());
+                let value = shaped(value, &key, &array_tags[row]);
+                row_attributes.insert(Tag::from(key.as_str()), value);
+            }
+        }
+        return Ok(attributes);
+    }
+
+    for field in batch.schema().fields() {
+        let Some(tag) = field.name().strip_prefix(FLATTENED_PREFIX) else {
+            continue;
+        };
+        let values = strings(column(batch, field.name())?.as_ref(), field.name())?;
+        for (row, value) in values.into_iter().enumerate() {
+            if let Some(value) = value {
+                let value = parse_value(&value).map_err(invalid)?;
+                attributes[row].insert(Tag::from(tag), shaped(value, tag, &array_tags[row]));
+            }
+        }
+    }
+
+    Ok(attributes)
+}
+
+/// Converts a record batch written by [`records_to_batch`] back into records. Columns written
+/// by other tools may use any string type and any numeric type in range.
+pub fn batch_to_records(batch: &RecordBatch) -> Result<Vec<GffRecord>, ArrowError> {
+    let seqid = string_column(batch, "seqid")?;
+    let source = string_column(batch, "source")?;
+    let r#type = string_column(batch, "type")?;
+    let start = primitive_column::<UInt32Type>(batch, "start")?;
+    let end = primitive_column::<UInt32Type>(batch, "end")?;
+    let score = primitive_column::<Float64Type>(batch, "score")?;
+    let strand = string_column(batch, "strand")?;
+    let phase = primitive_column::<UInt8Type>(batch, "phase")?;
+    let attributes = read_attributes(batch)?;
+
+    attributes
+        .into_iter()
+        .enumerate()
+        .map(|(i, attributes)| {
+            Ok(GffRecord {
+                seqid: required(seqid[i].as_deref(), "seqid", i)?
+                    .parse()
+                    .map_err(invalid)?,
+                source: required(source[i].clone(), "source", i)?,
+                r#type: required(r#type[i].clone(), "type", i)?,
+                start: required(start.is_valid(i).then(|| start.value(i)), "start", i)?,
+                end: required(end.is_valid(i).then(|| end.value(i)), "end", i)?,
+                score: score.is_valid(i).then(|| score.value(i)),
+                strand: strand[i]
+                    .as_deref()
+                    .map(str::parse::<Strand>)
+                    .transpose()
+                    .map_err(invalid)?,
+                phase: phase
+                    .is_valid(i)
+                    .then(|| phase.value(i).to_string().parse::<Phase>())
+                    .transpose()
+                    .map_err(invalid)?,
+                attributes,
+            })
+        })
+        .collect()
+}
+
+/// Writes records to Parquet, one row group per `batch_size` records at most.
+pub fn write_parquet<W, I>(
+    writer: W,
+    records: I,
+    layout: &AttributeLayout,
+    batch_size: usize,
+) -> Result<(), ParquetError>
+where
+    W: Write + Send,
+    I: IntoIterator<Item = GffRecord>,
+{
+    let mut records = records.into_iter().peekable();
+    let mut chunk: Vec<GffRecord> = Vec::with_capacity(batch_size);
+    // The schema is only known once the first batch is built.
+    let mut arrow_writer: Option<ArrowWriter<W>> = None;
+    let mut writer = Some(writer);
+
+    while records.peek().is_some() || arrow_writer.is_none() {
+        chunk.clear();
+        chunk.extend(records.by_ref().take(batch_size.max(1)));
+        let batch = records_to_batch(&chunk, layout)?;
+        if arrow_writer.is_none() {
+            let sink = writer.take().expect("writer is consumed once");
+            arrow_writer = Some(ArrowWriter::try_new(sink, batch.schema(), None)?);
+        }
+        if let Some(arrow_writer) = arrow_writer.as_mut() {
+            arrow_writer.write(&batch)?;
+        }
+    }
+
+    if let Some(arrow_writer) = arrow_writer {
+        arrow_writer.close()?;
+    }
+    Ok(())
+}
+
+/// Reads every record of a Parquet file written by [`write_parquet`].
+pub fn read_parquet<R: ChunkReader + 'static>(reader: R) -> Result<Vec<GffRecord>, ParquetError> {
+    let reader = ParquetRecordBatchReaderBuilder::try_new(reader)?.build()?;
+    let mut records = Vec::new();
+    for batch in reader {
+        records.extend(batch_to_records(&batch?)?);
+    }
+    Ok(records)
+}
+
+#[cfg(test)]
+mod test_arrow {
+    use std::fs::File;
+
+    use arrow::array::{LargeListBuilder, LargeStringBuilder};
+
+    use super::*;
+    use crate::parse_line;
+
+    fn records() -> Vec<GffRecord> {
+        "chr1\tpred\tgene\t1\t1000\t0.5\t+\t.\tID=gene1;Note=a%2Cb\n\
+         chr1\tpred\tmRNA\t1\t1000\t.\t+\t.\tID=mRNA1;Parent=gene1,gene2\n\
+         chr1\tpred\tCDS\t51\t100\t.\t-\t2\t."
+            .lines()
+            .map(|l| parse_line(l).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn test_batch_roundtrip() {
+        let records = records();
+        for layout in [
+            AttributeLayout::Map,
+            AttributeLayout::flattened_from(&records),
+        ] {
+            let batch = records_to_batch(&records, &layout).unwrap();
+            assert_eq!(batch_to_records(&batch).unwrap(), records);
+        }
+    }
+
+    #[test]
+    fn test_single_value_arrays() {
+        let mut records = records();
+        records[2]
+            .attributes
+            .insert(Tag::from("Dbxref"), Value::Array(vec!["GO:1".to_string()]));
+        for layout in [
+            AttributeLayout::Map,
+            AttributeLayout::flattened_from(&records),
+        ] {
+            let batch = records_to_batch(&records, &layout).unwrap();
+            assert_eq!(batch_to_records(&batch).unwrap(), records);
+        }
+    }
+
+    #[test]
+    fn test_large_types() {
+        let records = records();
+        let batch = records_to_batch(&records, &AttributeLayout::Map).unwrap();
+        let mut builder = MapBuilder::new(
+            None,
+            LargeStringBuilder::new(),
+            LargeListBuilder::new(LargeStringBuilder::new()),
+        );
+        for record in &records {
+            for (tag, value) in &record.attributes {
+                builder.keys().append_value(tag.as_ref());
+                for v in value.iter() {
+                    builder.values().values().append_value(v);
+                }
+                builder.values().append(true);
+            }
+            builder.append(true).unwrap();
+        }
+        let index = batch.schema().index_of(ATTRIBUTES_COLUMN).unwrap();
+        let mut fields: Vec<Field> = batch
+            .schema()
+            .fields()
+            .iter()
+            .map(|f| f.as_ref().clone())
+            .collect();
+        let mut columns = batch.columns().to_vec();
+        columns[index] = Arc::new(builder.finish());
+        fields[index] = Field::new(ATTRIBUTES_COLUMN, columns[index].data_type().clone(), false);
+        // Files from other writers have no array tags column.
+        fields.pop();
+        columns.pop();
+        let large = RecordBatch::try_new(Arc::new(Schema::new(fields)), columns).unwrap();
+        assert_eq!(batch_to_records(&large).unwrap(), records);
+
+        let mut fields: Vec<Field> = batch
+            .schema()
+            .fields()
+            .iter()
+            .map(|f| f.as_ref().clone())
+            .collect();
+        let mut columns = batch.columns().to_vec();
+        columns[index] = Arc::new(arrow::array::Int32Array::from(vec![0, 1, 2]));
+        fields[index] = Field::new(ATTRIBUTES_COLUMN, arrow::datatypes::DataType::Int32, false);
+        let invalid = RecordBatch::try_new(Arc::new(Schema::new(fields)), columns).unwrap();
+        assert!(matches!(
+            batch_to_records(&invalid),
+            Err(ArrowError::SchemaError(_))
+        ));
+    }
+
+    #[test]
+    fn test_core_column_types() {
+        use arrow::datatypes::DataType;
+
+        let records = records();
+        let batch = records_to_batch(&records, &AttributeLayout::Map).unwrap();
+        let recast = |batch: &RecordBatch, types: &[(&str, DataType)]| {
+            let mut fields = Vec::new();
+            let mut columns = Vec::new();
+            for (field, column) in batch.schema().fields().iter().zip(batch.columns()) {
+                let column = match types.iter().find(|(name, _)| name == field.name()) {
+                    Some((_, data_type)) => arrow::compute::cast(column, data_type).unwrap(),
+                    None => column.clone(),
+                };
+                fields.push(Field::new(
+                    field.name(),
+                    column.data_type().clone(),
+                    field.is_nullable(),
+                ));
+                columns.push(column);
+            }
+            RecordBatch::try_new(Arc::new(Schema::new(fields)), columns).unwrap()
+        };
+
+        let large = recast(
+            &batch,
+            &[
+                ("seqid", DataType::LargeUtf8),
+                ("source", DataType::Utf8View),
+                ("type", DataType::LargeUtf8),
+                ("strand", DataType::LargeUtf8),
+                ("start", DataType::Int64),
+                ("end", DataType::Int64),
+                ("phase", DataType::Int64),
+            ],
+        );
+        assert_eq!(batch_to_records(&large).unwrap(), records);
+
+        let start = arrow::array::Int64Array::from(vec![1, -5, 51]);
+        let mut columns = large.columns().to_vec();
+        columns[large.schema().index_of("start").unwrap()] = Arc::new(start);
+        let out_of_range = RecordBatch::try_new(large.schema(), columns).unwrap();
+        assert!(matches!(
+            batch_to_records(&out_of_range),
+            Err(ArrowError::ParseError(_))
+        ));
+    }
+
+    #[test]
+    fn test_parquet_roundtrip() {
+        let path = std::env::temp_dir().join(format!("gff-arrow-{}.parquet", std::process::id()));
+        let records = records();
+        write_parquet(
+            File::create(&path).unwrap(),
+            records.clone(),
+            &AttributeLayout::Map,
+            2,
+        )
+        .unwrap();
+
+        let read = read_parquet(File::open(&path).unwrap()).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(read, records);
+    }
+}
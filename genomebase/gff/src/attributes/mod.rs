@@ -6,8 +6,12 @@ use indexmap::IndexMap;
 use serde::{Deserialize, Serialize};
 
 const DELIMITER: char = ',';
+const ATTRIBUTE_DELIMITER: char = ';';
+const TAG_VALUE_DELIMITER: char = '=';
 
+/// Serialized as a plain string or an array of strings.
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(untagged)]
 pub enum Value {
     String(String),
     Array(Vec<String>),
@@ -26,7 +30,33 @@ impl FromStr for Value {
     }
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Hash)]
+impl Value {
+    /// Returns the first value, which is the value itself for `Value::String`.
+    pub fn first(&self) -> Option<&str> {
+        match self {
+            Self::String(value) => Some(value),
+            Self::Array(values) => values.first().map(|v| v.as_str()),
+        }
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &str> {
+        let values: &[String] = match self {
+            Self::String(value) => std::slice::from_ref(value),
+            Self::Array(values) => values,
+        };
+        values.iter().map(|v| v.as_str())
+    }
+}
+
+impl Display for Value {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let values: Vec<String> = self.iter().map(escape).collect();
+        write!(f, "{}", values.join(&DELIMITER.to_string()))
+    }
+}
+
+/// Serialized as the tag name used in GFF3 files, e.g. `ID` or `Derives_from`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub enum Tag {
     Id,
     Name,
@@ -42,6 +72,63 @@ pub enum Tag {
     Other(String),
 }
 
+impl From<&str> for Tag {
+    fn from(tag: &str) -> Self {
+        match tag {
+            "ID" => Self::Id,
+            "Name" => Self::Name,
+            "Alias" => Self::Alias,
+            "Parent" => Self::Parent,
+            "Target" => Self::Target,
+            "Gap" => Self::Gap,
+            "Derives_from" => Self::DerivesFrom,
+            "Note" => Self::Note,
+            "Dbxref" => Self::Dbxref,
+            "Ontology_term" => Self::OntologyTerm,
+            "Is_circular" => Self::IsCircular,
+            _ => Self::Other(tag.to_string()),
+        }
+    }
+}
+
+impl AsRef<str> for Tag {
+    fn as_ref(&self) -> &str {
+        match self {
+            Self::Id => "ID",
+            Self::Name => "Name",
+            Self::Alias => "Alias",
+            Self::Parent => "Parent",
+            Self::Target => "Target",
+            Self::Gap => "Gap",
+            Self::DerivesFrom => "Derives_from",
+            Self::Note => "Note",
+            Self::Dbxref => "Dbxref",
+            Self::OntologyTerm => "Ontology_term",
+            Self::IsCircular => "Is_circular",
+            Self::Other(tag) => tag,
+        }
+    }
+}
+
+impl Display for Tag {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.as_ref())
+    }
+}
+
+impl Serialize for Tag {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.as_ref())
+    }
+}
+
+impl<'de> Deserialize<'de> for Tag {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        Ok(Self::from(s.as_str()))
+    }
+}
+
 pub type Attributes = IndexMap<Tag, Value>;
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
@@ -66,33 +153,137 @@ impl Error for ParseAttibuteError {}
 pub fn parse_attributes(attributes: &str) -> Result<Attributes, ParseAttibuteError> {
     let mut map = IndexMap::new();
 
-    for attribute in attributes.split(';') {
-        let mut parts = attribute.split('=');
+    if attributes == crate::MISSING_FIELD {
+        return Ok(map);
+    }
+
+    for attribute in attributes.split(ATTRIBUTE_DELIMITER) {
+        // Trailing or doubled delimiters are common in the wild, so empty attributes are skipped.
+        if attribute.trim().is_empty() {
+            continue;
+        }
+        let mut parts = attribute.splitn(2, TAG_VALUE_DELIMITER);
         let tag = parts
             .next()
             .ok_or_else(|| ParseAttibuteError::MissingTag(attribute.to_string()))?;
         let value = parts
             .next()
             .ok_or_else(|| ParseAttibuteError::MissingValue(attribute.to_string()))?;
-        let tag = match tag {
-            "ID" => Tag::Id,
-            "Name" => Tag::Name,
-            "Alias" => Tag::Alias,
-            "Parent" => Tag::Parent,
-            "Target" => Tag::Target,
-            "Gap" => Tag::Gap,
-            "Derives_from" => Tag::DerivesFrom,
-            "Note" => Tag::Note,
-            "Dbxref" => Tag::Dbxref,
-            "Ontology_term" => Tag::OntologyTerm,
-            "Is_circular" => Tag::IsCircular,
-            _ => Tag::Other(tag.to_string()),
-        };
-        let value = value
-            .parse::<Value>()
-            .map_err(|_| ParseAttibuteError::InvalidValue(value.to_string()))?;
-        map.insert(tag, value);
+        let tag = Tag::from(tag.trim());
+        map.insert(tag, parse_value(value)?);
     }
 
     Ok(map)
 }
+
+/// Parses a single percent-encoded attribute value, as written by `Value`'s `Display`.
+pub fn parse_value(value: &str) -> Result<Value, ParseAttibuteError> {
+    let value = match value
+        .parse::<Value>()
+        .map_err(|_| ParseAttibuteError::InvalidValue(value.to_string()))?
+    {
+        Value::String(value) => Value::String(unescape(&value)?),
+        Value::Array(values) => Value::Array(
+            values
+                .iter()
+                .map(|v| unescape(v))
+                .collect::<Result<_, _>>()?,
+        ),
+    };
+    Ok(value)
+}
+
+/// Formats attributes as the ninth column of a GFF3 line, escaping reserved characters.
+pub fn format_attributes(attributes: &Attributes) -> String {
+    if attributes.is_empty() {
+        return crate::MISSING_FIELD.to_string();
+    }
+
+    attributes
+        .iter()
+        .map(|(tag, value)| format!("{}{}{}", escape(tag.as_ref()), TAG_VALUE_DELIMITER, value))
+        .collect::<Vec<_>>()
+        .join(&ATTRIBUTE_DELIMITER.to_string())
+}
+
+/// Characters with reserved meaning in column 9, which must be percent-encoded.
+const RESERVED_CHARACTERS: [char; 7] = [';', '=', '&', ',', '%', '\t', '\n'];
+
+fn escape(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+    for c in s.chars() {
+        if RESERVED_CHARACTERS.contains(&c) || c.is_control() {
+            for byte in c.encode_utf8(&mut [0; 4]).bytes() {
+                escaped.push_str(&format!("%{:02X}", byte));
+            }
+        } else {
+            escaped.push(c);
+        }
+    }
+    escaped
+}
+
+fn unescape(s: &str) -> Result<String, ParseAttibuteError> {
+    if !s.contains('%') {
+        return Ok(s.to_string());
+    }
+
+    let bytes = s.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' {
+            let byte = s
+                .get(i + 1..i + 3)
+                .and_then(|hex| u8::from_str_radix(hex, 16).ok())
+                .ok_or_else(|| ParseAttibuteError::InvalidValue(s.to_string()))?;
+            decoded.push(byte);
+            i += 3;
+        } else {
+            decoded.push(bytes[i]);
+            i += 1;
+        }
+    }
+
+    String::from_utf8(decoded).map_err(|_| ParseAttibuteError::InvalidValue(s.to_string()))
+}
+
+#[cfg(test)]
+mod test_attributes {
+    use super::*;
+
+    #[test]
+    fn test_escape_roundtrip() {
+        let value = "a;b\u{85}c";
+        assert_eq!(escape(value), "a%3Bb%C2%85c");
+        assert_eq!(unescape(&escape(value)).unwrap(), value);
+    }
+
+    #[test]
+    fn test_parse_attributes() {
+        let attributes = parse_attributes("ID=mRNA1;Parent=gene1,gene2;Note=a%3Bb;").unwrap();
+        assert_eq!(
+            attributes.get(&Tag::Id),
+            Some(&Value::String("mRNA1".to_string()))
+        );
+        assert_eq!(
+            attributes.get(&Tag::Parent),
+            Some(&Value::Array(vec![
+                "gene1".to_string(),
+                "gene2".to_string()
+            ]))
+        );
+        assert_eq!(
+            attributes.get(&Tag::Note),
+            Some(&Value::String("a;b".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_format_attributes_roundtrip() {
+        let line = "ID=mRNA1;Parent=gene1,gene2;Note=a%3Bb;product=x%3Dy";
+        let attributes = parse_attributes(line).unwrap();
+        assert_eq!(format_attributes(&attributes), line);
+        assert_eq!(format_attributes(&parse_attributes(".").unwrap()), ".");
+    }
+}
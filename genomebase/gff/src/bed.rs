@@ -0,0 +1,145 @@
+//! Export of transcripts and features as BED12.
+
+use std::fmt;
+use std::io::{self, Write};
+
+use crate::gene_model::{GeneModel, TranscriptModel};
+use crate::{GffRecord, Strand, MISSING_FIELD};
+
+/// A BED12 line. Coordinates are 0-based and half-open.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BedRecord {
+    pub chrom: String,
+    pub start: u32,
+    pub end: u32,
+    pub name: String,
+    pub score: u32,
+    pub strand: Option<Strand>,
+    pub thick_start: u32,
+    pub thick_end: u32,
+    /// Block starts relative to `start`, and block sizes.
+    pub blocks: Vec<(u32, u32)>,
+}
+
+impl BedRecord {
+    /// A single-block record covering a feature, named by its ID.
+    pub fn from_record(record: &GffRecord) -> Self {
+        let start = record.start.saturating_sub(1);
+        Self {
+            chrom: record.seqid.to_string(),
+            start,
+            end: record.end,
+            name: record.id().unwrap_or(MISSING_FIELD).to_string(),
+            score: 0,
+            strand: record.strand,
+            thick_start: start,
+            thick_end: record.end,
+            blocks: vec![(0, record.len())],
+        }
+    }
+
+    /// A record with one block per exon, thick over the CDS span. Non-coding transcripts have
+    /// an empty thick region at their start, as UCSC tools expect. The record spans the
+    /// exons and CDS even where they extend past the transcript feature. An exon whose start
+    /// is after its end, which `lint` reports, gives an empty block.
+    pub fn from_transcript(transcript: &TranscriptModel) -> Self {
+        let mut bed = Self::from_record(&transcript.transcript);
+        let exons = transcript.exon_intervals();
+        let cds = transcript.cds_span();
+        for (s, e) in exons.iter().chain(cds.iter()) {
+            bed.start = bed.start.min(s.saturating_sub(1));
+            bed.end = bed.end.max(*e);
+        }
+        bed.blocks = exons
+            .iter()
+            .map(|(s, e)| (s.saturating_sub(1) - bed.start, (e + 1).saturating_sub(*s)))
+            .collect();
+        (bed.thick_start, bed.thick_end) = match transcript.cds_span() {
+            Some((start, end)) => (start - 1, end),
+            None => (bed.start, bed.start),
+        };
+        bed
+    }
+}
+
+impl fmt::Display for BedRecord {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let join = |values: Vec<u32>| values.iter().map(|v| format!("{},", v)).collect::<String>();
+        write!(
+            f,
+            "{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t0\t{}\t{}\t{}",
+            self.chrom,
+            self.start,
+            self.end,
+            self.name,
+            self.score,
+            self.strand.as_ref().map_or(MISSING_FIELD, |s| s.as_ref()),
+            self.thick_start,
+            self.thick_end,
+            self.blocks.len(),
+            join(self.blocks.iter().map(|(_, size)| *size).collect()),
+            join(self.blocks.iter().map(|(start, _)| *start).collect()),
+        )
+    }
+}
+
+/// Writes one line per transcript.
+pub fn write_bed<W: Write>(mut writer: W, genes: &[GeneModel]) -> io::Result<()> {
+    for transcript in genes.iter().flat_map(|g| g.transcripts.iter()) {
+        writeln!(writer, "{}", BedRecord::from_transcript(transcript))?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test_bed {
+    use super::*;
+    use crate::gene_model::assemble_gene_models;
+    use crate::parse_line;
+
+    #[test]
+    fn test_transcript_bed() {
+        let records = "chr1\tgb\tgene\t101\t1000\t.\t+\t.\tID=gene1\n\
+             chr1\tgb\tmRNA\t101\t1000\t.\t+\t.\tID=mRNA1;Parent=gene1\n\
+             chr1\tgb\texon\t101\t200\t.\t+\t.\tParent=mRNA1\n\
+             chr1\tgb\texon\t501\t1000\t.\t+\t.\tParent=mRNA1\n\
+             chr1\tgb\tCDS\t151\t200\t.\t+\t0\tParent=mRNA1\n\
+             chr1\tgb\tCDS\t501\t600\t.\t+\t1\tParent=mRNA1"
+            .lines()
+            .map(|l| parse_line(l).unwrap());
+        let genes = assemble_gene_models(records).unwrap();
+        let bed = BedRecord::from_transcript(&genes[0].transcripts[0]);
+
+        assert_eq!(
+            bed.to_string(),
+            "chr1\t100\t1000\tmRNA1\t0\t+\t150\t600\t0\t2\t100,500,\t0,400,"
+        );
+    }
+
+    #[test]
+    fn test_exon_outside_transcript() {
+        let records = "chr1\tgb\tgene\t101\t1000\t.\t+\t.\tID=gene1\n\
+             chr1\tgb\tmRNA\t101\t1000\t.\t+\t.\tID=mRNA1;Parent=gene1\n\
+             chr1\tgb\texon\t51\t200\t.\t+\t.\tParent=mRNA1\n\
+             chr1\tgb\texon\t501\t1000\t.\t+\t.\tParent=mRNA1"
+            .lines()
+            .map(|l| parse_line(l).unwrap());
+        let genes = assemble_gene_models(records).unwrap();
+        let bed = BedRecord::from_transcript(&genes[0].transcripts[0]);
+        assert_eq!((bed.start, bed.end), (50, 1000));
+        assert_eq!(bed.blocks, vec![(0, 150), (450, 500)]);
+    }
+
+    #[test]
+    fn test_reversed_exon() {
+        let records = "chr1\tgb\tgene\t101\t1000\t.\t+\t.\tID=gene1\n\
+             chr1\tgb\tmRNA\t101\t1000\t.\t+\t.\tID=mRNA1;Parent=gene1\n\
+             chr1\tgb\texon\t101\t200\t.\t+\t.\tParent=mRNA1\n\
+             chr1\tgb\texon\t900\t501\t.\t+\t.\tParent=mRNA1"
+            .lines()
+            .map(|l| parse_line(l).unwrap());
+        let genes = assemble_gene_models(records).unwrap();
+        let bed = BedRecord::from_transcript(&genes[0].transcripts[0]);
+        assert_eq!(bed.blocks, vec![(0, 100), (799, 0)]);
+    }
+}
@@ -0,0 +1,330 @@
+//! UCSC chain files describing pairwise alignments between two assemblies.
+//!
+//! See <https://genome.ucsc.edu/goldenPath/help/chain.html> for the format. The reference
+//! (`t`) side is the assembly annotations are lifted from, the query (`q`) side the assembly
+//! they are lifted to. Coordinates are 0-based and half-open, as in the file.
+
+use std::collections::HashMap;
+use std::io::BufRead;
+
+use anyhow::{anyhow, bail, Context, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::Strand;
+
+const CHAIN_PREFIX: &str = "chain";
+const HEADER_FIELDS: usize = 13;
+
+/// An ungapped aligned block. `q_start` is on the chain's query strand.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ChainBlock {
+    pub t_start: u64,
+    pub q_start: u64,
+    pub size: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Chain {
+    pub id: u64,
+    pub score: f64,
+    pub t_name: String,
+    pub t_size: u64,
+    pub t_start: u64,
+    pub t_end: u64,
+    pub q_name: String,
+    pub q_size: u64,
+    pub q_strand: Strand,
+    pub q_start: u64,
+    pub q_end: u64,
+    pub blocks: Vec<ChainBlock>,
+}
+
+impl Chain {
+    fn parse_header(line: &str) -> Result<Self> {
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        if fields.len() < HEADER_FIELDS - 1 || fields[0] != CHAIN_PREFIX {
+            bail!("invalid chain header: {}", line);
+        }
+        let number = |i: usize| -> Result<u64> {
+            fields[i]
+                .parse::<u64>()
+                .with_context(|| format!("invalid number in chain header: {}", fields[i]))
+        };
+        if fields[4] != Strand::Forward.as_ref() {
+            bail!("reference strand must be +: {}", line);
+        }
+
+        let chain = Self {
+            score: fields[1]
+                .parse::<f64>()
+                .with_context(|| format!("invalid score: {}", fields[1]))?,
+            t_name: fields[2].to_string(),
+            t_size: number(3)?,
+            t_start: number(5)?,
+            t_end: number(6)?,
+            q_name: fields[7].to_string(),
+            q_size: number(8)?,
+            q_strand: fields[9].parse::<Strand>().map_err(|e| anyhow!(e))?,
+            q_start: number(10)?,
+            q_end: number(11)?,
+            // The id column is optional in older files.
+            id: fields.get(12).map(|_| number(12)).transpose()?.unwrap_or(0),
+            blocks: Vec::new(),
+        };
+        if chain.t_start > chain.t_end || chain.t_end > chain.t_size {
+            bail!("reference interval outside of its sequence: {}", line);
+        }
+        if chain.q_start > chain.q_end || chain.q_end > chain.q_size {
+            bail!("query interval outside of its sequence: {}", line);
+        }
+        Ok(chain)
+    }
+
+    /// Converts a query interval on the chain's query strand to forward strand coordinates.
+    fn forward_query(&self, start: u64, end: u64) -> (u64, u64) {
+        match self.q_strand {
+            Strand::Reverse => (self.q_size - end, self.q_size - start),
+            Strand::Forward => (start, end),
+        }
+    }
+}
+
+/// Parses every chain of a chain file. Chains whose intervals lie outside their sequences, or
+/// whose blocks do not end at the end of the chain, are rejected.
+pub fn parse_chains<R: BufRead>(reader: R) -> Result<Vec<Chain>> {
+    let mut chains: Vec<Chain> = Vec::new();
+    // Position of the next block on both sides while a chain is being read.
+    let mut cursor: Option<(u64, u64)> = None;
+
+    for (i, line) in reader.lines().enumerate() {
+        let line = line?;
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        if line.starts_with(CHAIN_PREFIX) {
+            if cursor.is_some() {
+                bail!(
+                    "line {}: chain started before the previous one ended",
+                    i + 1
+                );
+            }
+            let chain = Chain::parse_header(line).with_context(|| format!("line {}", i + 1))?;
+            cursor = Some((chain.t_start, chain.q_start));
+            chains.push(chain);
+            continue;
+        }
+
+        let (Some((t, q)), Some(chain)) = (cursor, chains.last_mut()) else {
+            bail!("line {}: alignment data outside of a chain", i + 1);
+        };
+        let numbers = line
+            .split_whitespace()
+            .map(|n| n.parse::<u64>())
+            .collect::<Result<Vec<_>, _>>()
+            .with_context(|| format!("line {}: invalid alignment data", i + 1))?;
+        let size = *numbers
+            .first()
+            .ok_or_else(|| anyhow!("line {}: missing block size", i + 1))?;
+
+        chain.blocks.push(ChainBlock {
+            t_start: t,
+            q_start: q,
+            size,
+        });
+        cursor = match numbers.as_slice() {
+            [_] => None,
+            [_, dt, dq] => Some((t + size + dt, q + size + dq)),
+            _ => bail!("line {}: expected 1 or 3 numbers", i + 1),
+        };
+        let (t_end, q_end) = cursor.unwrap_or((t + size, q + size));
+        if cursor.is_none() && (t_end, q_end) != (chain.t_end, chain.q_end) {
+            bail!(
+                "line {}: blocks end at {} and {}, not at the chain end {} and {}",
+                i + 1,
+                t_end,
+                q_end,
+                chain.t_end,
+                chain.q_end
+            );
+        }
+        if t_end > chain.t_end || q_end > chain.q_end {
+            bail!("line {}: block extends beyond the chain end", i + 1);
+        }
+    }
+
+    if cursor.is_some() {
+        bail!("chain file ended inside a chain");
+    }
+
+    Ok(chains)
+}
+
+/// A piece of a reference interval mapped through one aligned block.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MappedPiece {
+    /// Index of the chain in [`ChainMap::chains`].
+    pub chain: usize,
+    pub t_start: u64,
+    pub t_end: u64,
+    pub q_name: String,
+    pub q_strand: Strand,
+    /// Query coordinates on the forward strand.
+    pub q_start: u64,
+    pub q_end: u64,
+}
+
+/// Chains indexed by reference sequence for interval lookup.
+#[derive(Debug, Clone, Default)]
+pub struct ChainMap {
+    chains: Vec<Chain>,
+    /// Per reference seqid: `(chain, block)` sorted by block start, and the largest block size.
+    index: HashMap<String, (Vec<(usize, usize)>, u64)>,
+}
+
+impl ChainMap {
+    pub fn new(chains: Vec<Chain>) -> Self {
+        let mut index: HashMap<String, (Vec<(usize, usize)>, u64)> = HashMap::new();
+        for (c, chain) in chains.iter().enumerate() {
+            let (blocks, max_size) = index.entry(chain.t_name.clone()).or_default();
+            for (b, block) in chain.blocks.iter().enumerate() {
+                blocks.push((c, b));
+                *max_size = (*max_size).max(block.size);
+            }
+        }
+        for (blocks, _) in index.values_mut() {
+            blocks.sort_by_key(|&(c, b)| chains[c].blocks[b].t_start);
+        }
+
+        Self { chains, index }
+    }
+
+    pub fn from_reader<R: BufRead>(reader: R) -> Result<Self> {
+        Ok(Self::new(parse_chains(reader)?))
+    }
+
+    pub fn chains(&self) -> &[Chain] {
+        &self.chains
+    }
+
+    /// Query sequences with their lengths, in order of first appearance.
+    pub fn query_sequences(&self) -> Vec<(&str, u64)> {
+        let mut sequences: Vec<(&str, u64)> = Vec::new();
+        for chain in &self.chains {
+            if !sequences.iter().any(|(name, _)| *name == chain.q_name) {
+                sequences.push((&chain.q_name, chain.q_size));
+            }
+        }
+        sequences
+    }
+
+    /// Maps the 0-based half-open reference interval `[start, end)` through every overlapping
+    /// aligned block. Bases falling into alignment gaps are not represented in the result.
+    pub fn map_interval(&self, seqid: &str, start: u64, end: u64) -> Vec<MappedPiece> {
+        let Some((blocks, max_size)) = self.index.get(seqid) else {
+            return Vec::new();
+        };
+
+        let first =
+            blocks.partition_point(|&(c, b)| self.chains[c].blocks[b].t_start + max_size <= start);
+        let mut pieces = Vec::new();
+        for &(c, b) in &blocks[first..] {
+            let chain = &self.chains[c];
+            let block = &chain.blocks[b];
+            if block.t_start >= end {
+                break;
+            }
+            let t_start = start.max(block.t_start);
+            let t_end = end.min(block.t_start + block.size);
+            if t_start >= t_end {
+                continue;
+            }
+
+            let offset = t_start - block.t_start;
+            let q_start = block.q_start + offset;
+            let (q_start, q_end) = chain.forward_query(q_start, q_start + (t_end - t_start));
+            pieces.push(MappedPiece {
+                chain: c,
+                t_start,
+                t_end,
+                q_name: chain.q_name.clone(),
+                q_strand: chain.q_strand,
+                q_start,
+                q_end,
+            });
+        }
+
+        pieces
+    }
+}
+
+#[cfg(test)]
+mod test_chain {
+    use super::*;
+
+    const CHAINS: &str = "chain 1000 chr1 1000 + 100 400 chr1 2000 + 1100 1410 1\n\
+        100\t10\t20\n\
+        190\n\
+        \n\
+        chain 500 chr2 500 + 0 100 chrB 300 - 50 150 2\n\
+        100\n";
+
+    #[test]
+    fn test_parse_chains() {
+        let chains = parse_chains(CHAINS.as_bytes()).unwrap();
+        assert_eq!(chains.len(), 2);
+        assert_eq!(
+            chains[0].blocks,
+            vec![
+                ChainBlock {
+                    t_start: 100,
+                    q_start: 1100,
+                    size: 100
+                },
+                ChainBlock {
+                    t_start: 210,
+                    q_start: 1220,
+                    size: 190
+                },
+            ]
+        );
+        assert_eq!(chains[1].q_strand, Strand::Reverse);
+    }
+
+    #[test]
+    fn test_malformed_chains() {
+        let error = |chains: &str| parse_chains(chains.as_bytes()).unwrap_err();
+        // The query end lies beyond the query sequence.
+        let chains = "chain 500 chr2 500 + 0 100 chrB 120 - 50 150 2\n100\n";
+        assert!(format!("{:#}", error(chains)).contains("query interval outside"));
+        let chains = "chain 500 chr2 500 + 0 100 chrB 300 - 150 50 2\n100\n";
+        assert!(format!("{:#}", error(chains)).contains("query interval outside"));
+        let chains = "chain 500 chr2 50 + 0 100 chrB 300 - 50 150 2\n100\n";
+        assert!(format!("{:#}", error(chains)).contains("reference interval outside"));
+        // Blocks running past or stopping short of the chain end.
+        let chains = "chain 500 chr2 500 + 0 100 chrB 300 - 50 150 2\n80\t30\t10\n20\n";
+        assert_eq!(
+            error(chains).to_string(),
+            "line 2: block extends beyond the chain end"
+        );
+        let chains = "chain 500 chr2 500 + 0 100 chrB 300 - 50 150 2\n90\n";
+        assert_eq!(
+            error(chains).to_string(),
+            "line 2: blocks end at 90 and 140, not at the chain end 100 and 150"
+        );
+    }
+
+    #[test]
+    fn test_map_interval() {
+        let map = ChainMap::from_reader(CHAINS.as_bytes()).unwrap();
+
+        let pieces = map.map_interval("chr1", 150, 250);
+        let q: Vec<(u64, u64)> = pieces.iter().map(|p| (p.q_start, p.q_end)).collect();
+        assert_eq!(q, vec![(1150, 1200), (1220, 1260)]);
+
+        let pieces = map.map_interval("chr2", 10, 20);
+        assert_eq!((pieces[0].q_start, pieces[0].q_end), (230, 240));
+        assert!(map.map_interval("chr3", 0, 10).is_empty());
+    }
+}
@@ -0,0 +1,477 @@
+//! Comparison of two annotation releases at the gene and transcript level.
+//!
+//! Genes are matched by `ID` and by coordinate overlap on the same seqid and strand. Each
+//! connected group of matched genes becomes one [`GeneChange`].
+
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+use strum::{Display, EnumString};
+
+use crate::attributes::{Tag, Value};
+use crate::gene_model::{GeneModel, TranscriptModel};
+use crate::GffRecord;
+
+/// Attribute carrying the change type in the GFF rendering of a diff.
+pub const CHANGE_TYPE_ATTRIBUTE: &str = "change_type";
+/// Attribute listing the IDs of the matched features in the other release.
+pub const RELATED_IDS_ATTRIBUTE: &str = "related_ids";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, Display, EnumString)]
+#[strum(serialize_all = "snake_case")]
+#[serde(rename_all = "snake_case")]
+pub enum ChangeType {
+    Unchanged,
+    Added,
+    Removed,
+    /// Same ID, but no longer overlapping its previous location.
+    Moved,
+    /// One old gene overlaps several new genes.
+    Split,
+    /// Several old genes overlap one new gene.
+    Merged,
+    /// Several old genes overlap several new genes.
+    Complex,
+    /// One-to-one match whose span or transcripts changed.
+    StructureChanged,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, Display, EnumString)]
+#[strum(serialize_all = "snake_case")]
+#[serde(rename_all = "snake_case")]
+pub enum TranscriptChangeType {
+    Unchanged,
+    Added,
+    Removed,
+    BoundariesChanged,
+    ExonsChanged,
+    CdsChanged,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TranscriptChange {
+    pub change: TranscriptChangeType,
+    pub old_id: Option<String>,
+    pub new_id: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GeneChange {
+    pub change: ChangeType,
+    pub old_ids: Vec<String>,
+    pub new_ids: Vec<String>,
+    /// Transcript level changes, only filled for one-to-one gene matches.
+    pub transcripts: Vec<TranscriptChange>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AnnotationDiff {
+    pub changes: Vec<GeneChange>,
+}
+
+impl AnnotationDiff {
+    /// Number of gene changes per change type.
+    pub fn summary(&self) -> HashMap<ChangeType, usize> {
+        let mut summary = HashMap::new();
+        for change in &self.changes {
+            *summary.entry(change.change).or_insert(0) += 1;
+        }
+        summary
+    }
+
+    /// Changes other than [`ChangeType::Unchanged`].
+    pub fn changed(&self) -> impl Iterator<Item = &GeneChange> {
+        self.changes
+            .iter()
+            .filter(|c| c.change != ChangeType::Unchanged)
+    }
+}
+
+fn overlaps(a: &GffRecord, b: &GffRecord) -> bool {
+    a.seqid == b.seqid && a.strand == b.strand && a.start <= b.end && b.start <= a.end
+}
+
+fn intervals(records: &[GffRecord]) -> Vec<(u32, u32)> {
+    records.iter().map(|r| (r.start, r.end)).collect()
+}
+
+fn compare_transcripts(old: &TranscriptModel, new: &TranscriptModel) -> TranscriptChangeType {
+    if intervals(&old.cds) != intervals(&new.cds) {
+        TranscriptChangeType::CdsChanged
+    } else if old.exon_intervals() != new.exon_intervals() {
+        TranscriptChangeType::ExonsChanged
+    } else if (old.transcript.start, old.transcript.end)
+        != (new.transcript.start, new.transcript.end)
+    {
+        TranscriptChangeType::BoundariesChanged
+    } else {
+        TranscriptChangeType::Unchanged
+    }
+}
+
+/// Matches transcripts of two versions of a gene by ID, then by overlap, and compares them.
+pub fn diff_transcripts(old: &GeneModel, new: &GeneModel) -> Vec<TranscriptChange> {
+    let mut pairs: Vec<(Option<usize>, Option<usize>)> = Vec::new();
+    let mut new_matched = vec![false; new.transcripts.len()];
+
+    let mut old_unmatched = Vec::new();
+    for (i, old_tx) in old.transcripts.iter().enumerate() {
+        match new
+            .transcripts
+            .iter()
+            .position(|t| !t.id().is_empty() && t.id() == old_tx.id())
+        {
+            Some(j) if !new_matched[j] => {
+                new_matched[j] = true;
+                pairs.push((Some(i), Some(j)));
+            }
+            _ => old_unmatched.push(i),
+        }
+    }
+
+    for i in old_unmatched {
+        let old_tx = &old.transcripts[i].transcript;
+        let candidate = new
+            .transcripts
+            .iter()
+            .enumerate()
+            .position(|(j, t)| !new_matched[j] && overlaps(old_tx, &t.transcript));
+        match candidate {
+            Some(j) => {
+                new_matched[j] = true;
+                pairs.push((Some(i), Some(j)));
+            }
+            None => pairs.push((Some(i), None)),
+        }
+    }
+    for (j, matched) in new_matched.iter().enumerate() {
+        if !matched {
+            pairs.push((None, Some(j)));
+        }
+    }
+
+    pairs
+        .into_iter()
+        .map(|(i, j)| {
+            let old_tx = i.map(|i| &old.transcripts[i]);
+            let new_tx = j.map(|j| &new.transcripts[j]);
+            let change = match (old_tx, new_tx) {
+                (Some(o), Some(n)) => compare_transcripts(o, n),
+                (Some(_), None) => TranscriptChangeType::Removed,
+                _ => TranscriptChangeType::Added,
+            };
+            TranscriptChange {
+                change,
+                old_id: old_tx.map(|t| t.id().to_string()),
+                new_id: new_tx.map(|t| t.id().to_string()),
+            }
+        })
+        .collect()
+}
+
+/// Minimal union-find over the concatenated old and new gene indices.
+struct DisjointSet {
+    parent: Vec<usize>,
+}
+
+impl DisjointSet {
+    fn new(size: usize) -> Self {
+        Self {
+            parent: (0..size).collect(),
+        }
+    }
+
+    fn find(&mut self, x: usize) -> usize {
+        let mut root = x;
+        while self.parent[root] != root {
+            root = self.parent[root];
+        }
+        let mut x = x;
+        while self.parent[x] != root {
+            let next = self.parent[x];
+            self.parent[x] = root;
+            x = next;
+        }
+        root
+    }
+
+    fn union(&mut self, a: usize, b: usize) {
+        let (a, b) = (self.find(a), self.find(b));
+        if a != b {
+            self.parent[b.max(a)] = a.min(b);
+        }
+    }
+}
+
+fn diff_one_to_one(old: &GeneModel, new: &GeneModel) -> GeneChange {
+    let transcripts = diff_transcripts(old, new);
+    let change = if !overlaps(&old.gene, &new.gene) {
+        ChangeType::Moved
+    } else if (old.gene.start, old.gene.end) != (new.gene.start, new.gene.end)
+        || transcripts
+            .iter()
+            .any(|t| t.change != TranscriptChangeType::Unchanged)
+    {
+        ChangeType::StructureChanged
+    } else {
+        ChangeType::Unchanged
+    };
+
+    GeneChange {
+        change,
+        old_ids: vec![old.id().to_string()],
+        new_ids: vec![new.id().to_string()],
+        transcripts,
+    }
+}
+
+/// Compares two annotation releases. Changes are ordered by first appearance in `new`, with
+/// removed genes following in `old` order.
+pub fn diff_annotations(old: &[GeneModel], new: &[GeneModel]) -> AnnotationDiff {
+    let offset = old.len();
+    let mut sets = DisjointSet::new(old.len() + new.len());
+
+    let new_by_id: HashMap<&str, usize> =
+        new.iter().enumerate().map(|(j, g)| (g.id(), j)).collect();
+    for (i, old_gene) in old.iter().enumerate() {
+        if let Some(&j) = new_by_id.get(old_gene.id()) {
+            sets.union(i, offset + j);
+        }
+    }
+
+    // Sweep the genes of both releases per seqid in start order to link overlapping pairs.
+    let mut by_seqid: HashMap<&str, Vec<(usize, &GffRecord)>> = HashMap::new();
+    for (i, gene) in old.iter().enumerate() {
+        by_seqid
+            .entry(gene.seqid())
+            .or_default()
+            .push((i, &gene.gene));
+    }
+    for (j, gene) in new.iter().enumerate() {
+        by_seqid
+            .entry(gene.seqid())
+            .or_default()
+            .push((offset + j, &gene.gene));
+    }
+    for genes in by_seqid.values_mut() {
+        genes.sort_by_key(|(_, g)| g.start);
+        let mut active: Vec<(usize, &GffRecord)> = Vec::new();
+        for &(idx, gene) in genes.iter() {
+            active.retain(|(_, g)| g.end >= gene.start);
+            for &(other, other_gene) in &active {
+                if (idx < offset) != (other < offset) && overlaps(gene, other_gene) {
+                    sets.union(idx, other);
+                }
+            }
+            active.push((idx, gene));
+        }
+    }
+
+    let mut groups: Vec<(Vec<usize>, Vec<usize>)> = Vec::new();
+    let mut group_index: HashMap<usize, usize> = HashMap::new();
+    let order = (offset..offset + new.len()).chain(0..offset);
+    for idx in order {
+        let root = sets.find(idx);
+        let group = *group_index.entry(root).or_insert_with(|| {
+            groups.push((Vec::new(), Vec::new()));
+            groups.len() - 1
+        });
+        if idx < offset {
+            groups[group].0.push(idx);
+        } else {
+            groups[group].1.push(idx - offset);
+        }
+    }
+
+    let changes = groups
+        .into_iter()
+        .map(|(old_idx, new_idx)| {
+            if let ([i], [j]) = (old_idx.as_slice(), new_idx.as_slice()) {
+                return diff_one_to_one(&old[*i], &new[*j]);
+            }
+            let change = match (old_idx.len(), new_idx.len()) {
+                (0, _) => ChangeType::Added,
+                (_, 0) => ChangeType::Removed,
+                (1, _) => ChangeType::Split,
+                (_, 1) => ChangeType::Merged,
+                _ => ChangeType::Complex,
+            };
+            GeneChange {
+                change,
+                old_ids: old_idx.iter().map(|&i| old[i].id().to_string()).collect(),
+                new_ids: new_idx.iter().map(|&j| new[j].id().to_string()).collect(),
+                transcripts: Vec::new(),
+            }
+        })
+        .collect();
+
+    AnnotationDiff { changes }
+}
+
+fn annotate(record: &mut GffRecord, change: &str, related_ids: &[String]) {
+    record.attributes.insert(
+        Tag::Other(CHANGE_TYPE_ATTRIBUTE.to_string()),
+        Value::String(change.to_string()),
+    );
+    let related = match related_ids {
+        [] => return,
+        [id] => Value::String(id.clone()),
+        ids => Value::Array(ids.to_vec()),
+    };
+    record
+        .attributes
+        .insert(Tag::Other(RELATED_IDS_ATTRIBUTE.to_string()), related);
+}
+
+/// Renders a diff as GFF records: every gene of `new` plus the removed genes of `old`, with
+/// `change_type` and `related_ids` attributes on genes and transcripts.
+pub fn diff_to_records(
+    diff: &AnnotationDiff,
+    old: &[GeneModel],
+    new: &[GeneModel],
+) -> Vec<GffRecord> {
+    let old_by_id: HashMap<&str, &GeneModel> = old.iter().map(|g| (g.id(), g)).collect();
+    let new_by_id: HashMap<&str, &GeneModel> = new.iter().map(|g| (g.id(), g)).collect();
+
+    let mut records = Vec::new();
+    for change in &diff.changes {
+        let (genes, related) = if change.change == ChangeType::Removed {
+            (&change.old_ids, &change.new_ids)
+        } else {
+            (&change.new_ids, &change.old_ids)
+        };
+        let lookup = if change.change == ChangeType::Removed {
+            &old_by_id
+        } else {
+            &new_by_id
+        };
+
+        for id in genes {
+            let Some(gene) = lookup.get(id.as_str()) else {
+                continue;
+            };
+            let mut gene = (*gene).clone();
+            annotate(&mut gene.gene, &change.change.to_string(), related);
+            for transcript in gene.transcripts.iter_mut() {
+                let tx_change = change
+                    .transcripts
+                    .iter()
+                    .find(|t| t.new_id.as_deref() == Some(transcript.id()));
+                if let Some(tx_change) = tx_change {
+                    let related: Vec<String> = tx_change.old_id.iter().cloned().collect();
+                    annotate(
+                        &mut transcript.transcript,
+                        &tx_change.change.to_string(),
+                        &related,
+                    );
+                }
+            }
+            records.extend(gene.into_records());
+        }
+
+        // Transcripts dropped from a gene that still exists are kept so the output is complete.
+        if let ([old_id], [_]) = (change.old_ids.as_slice(), change.new_ids.as_slice()) {
+            let Some(old_gene) = old_by_id.get(old_id.as_str()) else {
+                continue;
+            };
+            for tx_change in &change.transcripts {
+                if tx_change.change != TranscriptChangeType::Removed {
+                    continue;
+                }
+                let removed = old_gene
+                    .transcripts
+                    .iter()
+                    .find(|t| tx_change.old_id.as_deref() == Some(t.id()));
+                if let Some(removed) = removed {
+                    let mut removed = removed.clone();
+                    annotate(
+                        &mut removed.transcript,
+                        &TranscriptChangeType::Removed.to_string(),
+                        &[],
+                    );
+                    records.extend(removed.records().cloned());
+                }
+            }
+        }
+    }
+
+    records
+}
+
+#[cfg(test)]
+mod test_diff {
+    use super::*;
+    use crate::gene_model::assemble_gene_models;
+    use crate::parse_line;
+
+    fn genes(lines: &str) -> Vec<GeneModel> {
+        assemble_gene_models(lines.lines().map(|line| parse_line(line).unwrap())).unwrap()
+    }
+
+    fn change_types(diff: &AnnotationDiff) -> Vec<ChangeType> {
+        diff.changes.iter().map(|c| c.change).collect()
+    }
+
+    const OLD: &str = "chr1\t.\tgene\t100\t500\t.\t+\t.\tID=g1\n\
+        chr1\t.\tmRNA\t100\t500\t.\t+\t.\tID=t1;Parent=g1\n\
+        chr1\t.\texon\t100\t500\t.\t+\t.\tParent=t1\n\
+        chr1\t.\tgene\t1000\t3000\t.\t+\t.\tID=g2\n\
+        chr1\t.\tgene\t5000\t5500\t.\t+\t.\tID=g3\n\
+        chr1\t.\tgene\t6000\t6500\t.\t+\t.\tID=g4\n\
+        chr1\t.\tgene\t8000\t8500\t.\t+\t.\tID=g5\n\
+        chr1\t.\tgene\t9000\t9500\t.\t-\t.\tID=g6";
+
+    const NEW: &str = "chr1\t.\tgene\t100\t500\t.\t+\t.\tID=g1\n\
+        chr1\t.\tmRNA\t100\t500\t.\t+\t.\tID=t1;Parent=g1\n\
+        chr1\t.\texon\t100\t200\t.\t+\t.\tParent=t1\n\
+        chr1\t.\texon\t300\t500\t.\t+\t.\tParent=t1\n\
+        chr1\t.\tgene\t1000\t1800\t.\t+\t.\tID=g2a\n\
+        chr1\t.\tgene\t2000\t3000\t.\t+\t.\tID=g2b\n\
+        chr1\t.\tgene\t5000\t6500\t.\t+\t.\tID=g34\n\
+        chr2\t.\tgene\t100\t600\t.\t+\t.\tID=g5\n\
+        chr1\t.\tgene\t9000\t9500\t.\t-\t.\tID=g6\n\
+        chr3\t.\tgene\t1\t100\t.\t+\t.\tID=g7";
+
+    #[test]
+    fn test_diff_annotations() {
+        let diff = diff_annotations(&genes(OLD), &genes(NEW));
+
+        assert_eq!(
+            change_types(&diff),
+            vec![
+                ChangeType::StructureChanged,
+                ChangeType::Split,
+                ChangeType::Merged,
+                ChangeType::Moved,
+                ChangeType::Unchanged,
+                ChangeType::Added,
+            ]
+        );
+        assert_eq!(diff.changes[1].new_ids, vec!["g2a", "g2b"]);
+        assert_eq!(diff.changes[2].old_ids, vec!["g3", "g4"]);
+        assert_eq!(
+            diff.changes[0].transcripts[0].change,
+            TranscriptChangeType::ExonsChanged
+        );
+    }
+
+    #[test]
+    fn test_diff_removed_and_records() {
+        let old = genes(OLD);
+        let new = genes("chr1\t.\tgene\t100\t500\t.\t+\t.\tID=g1\n");
+        let diff = diff_annotations(&old, &new);
+        assert_eq!(diff.summary().get(&ChangeType::Removed), Some(&5));
+
+        let records = diff_to_records(&diff, &old, &new);
+        let g1 = records.iter().find(|r| r.id() == Some("g1")).unwrap();
+        assert_eq!(
+            g1.attribute(&Tag::Other(CHANGE_TYPE_ATTRIBUTE.to_string())),
+            Some(&Value::String("structure_changed".to_string()))
+        );
+        let t1 = records.iter().find(|r| r.id() == Some("t1")).unwrap();
+        assert_eq!(
+            t1.attribute(&Tag::Other(CHANGE_TYPE_ATTRIBUTE.to_string())),
+            Some(&Value::String("removed".to_string()))
+        );
+    }
+}
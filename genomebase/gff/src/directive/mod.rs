@@ -49,6 +49,22 @@ pub struct DirectiveHeader {
 }
 
 impl DirectiveHeader {
+    pub fn genome_build(&self) -> &GenomeBuild {
+        &self.genome_build
+    }
+
+    pub fn set_genome_build(&mut self, genome_build: GenomeBuild) {
+        self.genome_build = genome_build;
+    }
+
+    pub fn sequence_regions(&self) -> &[SequenceRegion] {
+        &self.sequence_region
+    }
+
+    pub fn set_sequence_regions(&mut self, sequence_regions: Vec<SequenceRegion>) {
+        self.sequence_region = sequence_regions;
+    }
+
     pub fn format_as_header(&self) -> String {
         let mut header: Vec<String> = Vec::new();
 
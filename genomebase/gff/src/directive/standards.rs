@@ -1,3 +1,4 @@
+use common::Seqid;
 use derive_new::new;
 use serde::{Deserialize, Serialize};
 use std::error::Error;
@@ -27,6 +28,7 @@ impl fmt::Display for GenomeBuildParseError {
 
 impl Error for GenomeBuildParseError {}
 
+/// `##genome-build source buildName`, e.g. `##genome-build NCBI GRCh38.p13`.
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq, new)]
 pub struct GenomeBuild {
     pub name: String,
@@ -38,7 +40,7 @@ impl fmt::Display for GenomeBuild {
         write!(
             f,
             "{}genome-build {} {}",
-            DIRECTIVE_PREFIX, self.name, self.source
+            DIRECTIVE_PREFIX, self.source, self.name
         )
     }
 }
@@ -48,18 +50,18 @@ impl FromStr for GenomeBuild {
 
     fn from_str(s: &str) -> Result<Self, GenomeBuildParseError> {
         let mut parts = s.split_whitespace();
-        let prefix = parts.next().ok_or_else(|| GenomeBuildParseError::Empty)?;
+        let prefix = parts.next().ok_or(GenomeBuildParseError::Empty)?;
 
         if prefix != format!("{}genome-build", DIRECTIVE_PREFIX) {
             return Err(GenomeBuildParseError::InvalidPrefix(prefix.to_string()));
         }
 
-        let name = parts
-            .next()
-            .ok_or_else(|| GenomeBuildParseError::MissingName("".to_string()))?;
         let source = parts
             .next()
             .ok_or_else(|| GenomeBuildParseError::MissingSource("".to_string()))?;
+        let name = parts
+            .next()
+            .ok_or_else(|| GenomeBuildParseError::MissingName("".to_string()))?;
 
         Ok(Self::new(name.to_string(), source.to_string()))
     }
@@ -67,7 +69,7 @@ impl FromStr for GenomeBuild {
 
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq, new)]
 pub struct SequenceRegion {
-    pub seqid: String,
+    pub seqid: Seqid,
     pub start: u64,
     pub end: u64,
 }
@@ -76,7 +78,7 @@ impl fmt::Display for SequenceRegion {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(
             f,
-            "{}sequence-region{} {} {}",
+            "{}sequence-region {} {} {}",
             DIRECTIVE_PREFIX, self.seqid, self.start, self.end
         )
     }
@@ -96,7 +98,7 @@ impl FromStr for SequenceRegion {
         let seqid = parts
             .next()
             .ok_or_else(|| "missing seqid".to_string())?
-            .to_string();
+            .parse::<Seqid>()?;
 
         let start = parts
             .next()
@@ -109,6 +111,12 @@ impl FromStr for SequenceRegion {
             .ok_or_else(|| "missing end position".to_string())?
             .parse::<u64>()
             .map_err(|e| format!("invalid end position: {}", e))?;
+        if start > end {
+            return Err(format!(
+                "start position {} is after end position {}",
+                start, end
+            ));
+        }
 
         Ok(Self::new(seqid, start, end))
     }
@@ -315,12 +323,13 @@ mod test_standards {
 
     #[test]
     fn test_genomebuild_fromstr() {
-        let genomebuild = "##genome-build GRCh38.p13 NCBI";
+        let genomebuild = "##genome-build NCBI GRCh38.p13";
         let genomebuild = GenomeBuild::from_str(genomebuild).unwrap();
         assert_eq!(
             genomebuild,
             GenomeBuild::new("GRCh38.p13".to_string(), "NCBI".to_string())
         );
+        assert_eq!(genomebuild.to_string(), "##genome-build NCBI GRCh38.p13");
     }
 
     #[test]
@@ -329,7 +338,11 @@ mod test_standards {
         let sequence_region = SequenceRegion::from_str(sequence_region).unwrap();
         assert_eq!(
             sequence_region,
-            SequenceRegion::new("NC_000001.11".to_string(), 1, 248956422)
+            SequenceRegion::new("NC_000001.11".parse().unwrap(), 1, 248956422)
+        );
+        assert_eq!(
+            sequence_region.to_string(),
+            "##sequence-region NC_000001.11 1 248956422"
         );
     }
 
@@ -0,0 +1,735 @@
+//! Filter expressions over GFF records.
+//!
+//! A filter combines conditions with `and`, `or`, `not` and parentheses:
+//!
+//! ```text
+//! type == CDS and seqid == chr3 and Note ~ "kinase"
+//! type in (mRNA, transcript) and length >= 300 and not exists pseudo
+//! overlaps chr1:10000-20000 and strand == - and score > 0.5
+//! ```
+//!
+//! The columns are `seqid`, `source`, `type`, `start`, `end`, `length`, `score`, `strand` and
+//! `phase`. Any other name refers to an attribute tag, and `attr.<tag>` refers to an attribute
+//! whose tag collides with a column name. Conditions are `==`, `!=`, `<`, `<=`, `>`, `>=`,
+//! regular expression matches `~` and `!~`, `in (...)`, `exists <tag>` and the region tests
+//! `overlaps` and `within`. Values containing spaces or operators are written in double quotes.
+//!
+//! An attribute with several values satisfies a condition when any of its values does, and
+//! `!=` is the negation of `==`, so it also holds for records without the attribute.
+
+use std::borrow::Cow;
+use std::error::Error;
+use std::fmt;
+use std::str::FromStr;
+
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+use crate::attributes::Tag;
+use crate::{GffRecord, MISSING_FIELD};
+
+const ATTRIBUTE_PREFIX: &str = "attr.";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FilterError {
+    UnexpectedEnd,
+    UnexpectedToken {
+        position: usize,
+        token: String,
+    },
+    UnterminatedString(usize),
+    InvalidNumber {
+        field: String,
+        value: String,
+    },
+    /// Ordering comparison on a text column such as `type`.
+    UnorderedField(String),
+    InvalidRegex(String),
+    InvalidRegion(String),
+    UnspecifiedOp,
+}
+
+impl fmt::Display for FilterError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UnexpectedEnd => write!(f, "unexpected end of filter"),
+            Self::UnexpectedToken { position, token } => {
+                write!(f, "unexpected {} at position {}", token, position)
+            }
+            Self::UnterminatedString(position) => {
+                write!(f, "unterminated string at position {}", position)
+            }
+            Self::InvalidNumber { field, value } => {
+                write!(f, "{} is compared with non-numeric value {}", field, value)
+            }
+            Self::UnorderedField(field) => write!(f, "{} cannot be compared by order", field),
+            Self::InvalidRegex(e) => write!(f, "invalid regular expression: {}", e),
+            Self::InvalidRegion(region) => write!(f, "invalid region: {}", region),
+            Self::UnspecifiedOp => write!(f, "filter operator is unspecified"),
+        }
+    }
+}
+
+impl Error for FilterError {}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Field {
+    Seqid,
+    Source,
+    Type,
+    Start,
+    End,
+    Length,
+    Score,
+    Strand,
+    Phase,
+    Attribute(Tag),
+}
+
+impl Field {
+    fn is_numeric(&self) -> bool {
+        matches!(self, Self::Start | Self::End | Self::Length | Self::Score)
+    }
+
+    fn texts<'a>(&self, record: &'a GffRecord) -> Vec<Cow<'a, str>> {
+        match self {
+            Self::Seqid => vec![Cow::Borrowed(record.seqid.as_str())],
+            Self::Source => vec![Cow::Borrowed(record.source.as_str())],
+            Self::Type => vec![Cow::Borrowed(record.r#type.as_str())],
+            Self::Strand => vec![Cow::Borrowed(
+                record.strand.as_ref().map_or(MISSING_FIELD, |s| s.as_ref()),
+            )],
+            Self::Phase => vec![Cow::Borrowed(
+                record.phase.as_ref().map_or(MISSING_FIELD, |p| p.as_ref()),
+            )],
+            Self::Attribute(tag) => record
+                .attribute(tag)
+                .map(|v| v.iter().map(Cow::Borrowed).collect())
+                .unwrap_or_default(),
+            _ => self
+                .numbers(record)
+                .into_iter()
+                .map(|n| Cow::Owned(n.to_string()))
+                .collect(),
+        }
+    }
+
+    fn numbers(&self, record: &GffRecord) -> Vec<f64> {
+        match self {
+            Self::Start => vec![record.start as f64],
+            Self::End => vec![record.end as f64],
+            Self::Length => vec![record.len() as f64],
+            Self::Score => record.score.into_iter().collect(),
+            Self::Attribute(tag) => record
+                .attribute(tag)
+                .map(|v| v.iter().filter_map(|s| s.parse().ok()).collect())
+                .unwrap_or_default(),
+            _ => Vec::new(),
+        }
+    }
+}
+
+impl FromStr for Field {
+    type Err = FilterError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(match s {
+            "seqid" => Self::Seqid,
+            "source" => Self::Source,
+            "type" => Self::Type,
+            "start" => Self::Start,
+            "end" => Self::End,
+            "length" => Self::Length,
+            "score" => Self::Score,
+            "strand" => Self::Strand,
+            "phase" => Self::Phase,
+            _ => Self::Attribute(Tag::from(s.strip_prefix(ATTRIBUTE_PREFIX).unwrap_or(s))),
+        })
+    }
+}
+
+impl fmt::Display for Field {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Self::Seqid => "seqid",
+            Self::Source => "source",
+            Self::Type => "type",
+            Self::Start => "start",
+            Self::End => "end",
+            Self::Length => "length",
+            Self::Score => "score",
+            Self::Strand => "strand",
+            Self::Phase => "phase",
+            Self::Attribute(tag) => tag.as_ref(),
+        };
+        write!(f, "{}", name)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CompareOp {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+impl CompareOp {
+    fn is_ordering(&self) -> bool {
+        !matches!(self, Self::Eq | Self::Ne)
+    }
+
+    fn holds<T: PartialOrd>(&self, left: T, right: T) -> bool {
+        match self {
+            Self::Eq => left == right,
+            Self::Ne => left != right,
+            Self::Lt => left < right,
+            Self::Le => left <= right,
+            Self::Gt => left > right,
+            Self::Ge => left >= right,
+        }
+    }
+}
+
+/// A seqid-qualified or bare 1-based inclusive interval, written `chr1:100-200` or `100-200`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Region {
+    pub seqid: Option<String>,
+    pub start: u32,
+    pub end: u32,
+}
+
+impl FromStr for Region {
+    type Err = FilterError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || FilterError::InvalidRegion(s.to_string());
+        let (seqid, range) = match s.rsplit_once(':') {
+            Some((seqid, range)) => (Some(seqid.to_string()), range),
+            None => (None, s),
+        };
+        let (start, end) = range.split_once('-').ok_or_else(invalid)?;
+        let start: u32 = start.parse().map_err(|_| invalid())?;
+        let end: u32 = end.parse().map_err(|_| invalid())?;
+        if start > end {
+            return Err(invalid());
+        }
+        Ok(Self { seqid, start, end })
+    }
+}
+
+impl Region {
+    fn same_seqid(&self, record: &GffRecord) -> bool {
+        self.seqid
+            .as_ref()
+            .is_none_or(|seqid| *seqid == record.seqid)
+    }
+
+    fn overlaps(&self, record: &GffRecord) -> bool {
+        self.same_seqid(record) && record.start <= self.end && record.end >= self.start
+    }
+
+    fn contains(&self, record: &GffRecord) -> bool {
+        self.same_seqid(record) && record.start >= self.start && record.end <= self.end
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum Condition {
+    Text {
+        field: Field,
+        op: CompareOp,
+        value: String,
+    },
+    Number {
+        field: Field,
+        op: CompareOp,
+        value: f64,
+    },
+    Matches {
+        field: Field,
+        regex: Regex,
+    },
+    Exists(Tag),
+    Overlaps(Region),
+    Within(Region),
+}
+
+impl Condition {
+    /// Builds a comparison, reading the value as a number for numeric columns and ordering.
+    pub fn compare(field: Field, op: CompareOp, value: &str) -> Result<Self, FilterError> {
+        if op.is_ordering() && !field.is_numeric() && !matches!(field, Field::Attribute(_)) {
+            return Err(FilterError::UnorderedField(field.to_string()));
+        }
+        if field.is_numeric() || op.is_ordering() {
+            let number = value.parse().map_err(|_| FilterError::InvalidNumber {
+                field: field.to_string(),
+                value: value.to_string(),
+            })?;
+            return Ok(Self::Number {
+                field,
+                op,
+                value: number,
+            });
+        }
+        Ok(Self::Text {
+            field,
+            op,
+            value: value.to_string(),
+        })
+    }
+
+    pub fn matches(field: Field, pattern: &str) -> Result<Self, FilterError> {
+        let regex = Regex::new(pattern).map_err(|e| FilterError::InvalidRegex(e.to_string()))?;
+        Ok(Self::Matches { field, regex })
+    }
+
+    pub fn test(&self, record: &GffRecord) -> bool {
+        match self {
+            // `!=` negates `==` rather than asking whether any value differs.
+            Self::Text {
+                field,
+                op: CompareOp::Ne,
+                value,
+            } => !field.texts(record).iter().any(|t| t == value),
+            Self::Text { field, op, value } => field
+                .texts(record)
+                .iter()
+                .any(|t| op.holds(t.as_ref(), value.as_str())),
+            Self::Number {
+                field,
+                op: CompareOp::Ne,
+                value,
+            } => !field.numbers(record).contains(value),
+            Self::Number { field, op, value } => {
+                field.numbers(record).iter().any(|n| op.holds(n, value))
+            }
+            Self::Matches { field, regex } => field.texts(record).iter().any(|t| regex.is_match(t)),
+            Self::Exists(tag) => record.attribute(tag).is_some(),
+            Self::Overlaps(region) => region.overlaps(record),
+            Self::Within(region) => region.contains(record),
+        }
+    }
+}
+
+/// A compiled filter expression.
+#[derive(Debug, Clone)]
+pub enum Filter {
+    Condition(Condition),
+    Not(Box<Filter>),
+    /// Holds when every filter holds, including for an empty list.
+    And(Vec<Filter>),
+    Or(Vec<Filter>),
+}
+
+impl Filter {
+    pub fn parse(s: &str) -> Result<Self, FilterError> {
+        let tokens = tokenize(s)?;
+        let mut parser = Parser { tokens, next: 0 };
+        let filter = parser.or()?;
+        match parser.tokens.get(parser.next) {
+            Some(token) => Err(token.unexpected()),
+            None => Ok(filter),
+        }
+    }
+
+    /// Combines key filters as sent in `Filter` messages of the gRPC services, all of which
+    /// must hold.
+    pub fn from_key_filters(filters: &[KeyFilter]) -> Result<Self, FilterError> {
+        filters
+            .iter()
+            .map(|f| f.to_condition().map(Self::Condition))
+            .collect::<Result<_, _>>()
+            .map(Self::And)
+    }
+
+    pub fn matches(&self, record: &GffRecord) -> bool {
+        match self {
+            Self::Condition(condition) => condition.test(record),
+            Self::Not(filter) => !filter.matches(record),
+            Self::And(filters) => filters.iter().all(|f| f.matches(record)),
+            Self::Or(filters) => filters.iter().any(|f| f.matches(record)),
+        }
+    }
+}
+
+impl FromStr for Filter {
+    type Err = FilterError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::parse(s)
+    }
+}
+
+/// Comparison operator of the proto `FilterOp` enum, with the same numbering.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum FilterOp {
+    Unspecified = 0,
+    Gt = 1,
+    Gte = 2,
+    Lt = 3,
+    Lte = 4,
+    Eq = 5,
+}
+
+impl TryFrom<i32> for FilterOp {
+    type Error = FilterError;
+
+    fn try_from(value: i32) -> Result<Self, Self::Error> {
+        match value {
+            0 => Ok(Self::Unspecified),
+            1 => Ok(Self::Gt),
+            2 => Ok(Self::Gte),
+            3 => Ok(Self::Lt),
+            4 => Ok(Self::Lte),
+            5 => Ok(Self::Eq),
+            _ => Err(FilterError::UnspecifiedOp),
+        }
+    }
+}
+
+/// A single `key op value` condition, mirroring the proto `Filter` message.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct KeyFilter {
+    pub op: FilterOp,
+    pub key: String,
+    pub value: String,
+}
+
+impl KeyFilter {
+    pub fn to_condition(&self) -> Result<Condition, FilterError> {
+        let op = match self.op {
+            FilterOp::Unspecified => return Err(FilterError::UnspecifiedOp),
+            FilterOp::Gt => CompareOp::Gt,
+            FilterOp::Gte => CompareOp::Ge,
+            FilterOp::Lt => CompareOp::Lt,
+            FilterOp::Lte => CompareOp::Le,
+            FilterOp::Eq => CompareOp::Eq,
+        };
+        Condition::compare(self.key.parse()?, op, &self.value)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum TokenKind {
+    LParen,
+    RParen,
+    Comma,
+    Op(CompareOp),
+    Match,
+    NotMatch,
+    Word(String),
+    Quoted(String),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Token {
+    kind: TokenKind,
+    position: usize,
+}
+
+impl Token {
+    fn unexpected(&self) -> FilterError {
+        let token = match &self.kind {
+            TokenKind::LParen => "(".to_string(),
+            TokenKind::RParen => ")".to_string(),
+            TokenKind::Comma => ",".to_string(),
+            TokenKind::Op(_) | TokenKind::Match | TokenKind::NotMatch => "operator".to_string(),
+            TokenKind::Word(word) => word.clone(),
+            TokenKind::Quoted(text) => format!("\"{}\"", text),
+        };
+        FilterError::UnexpectedToken {
+            position: self.position,
+            token,
+        }
+    }
+
+    fn is_keyword(&self, keyword: &str) -> bool {
+        matches!(&self.kind, TokenKind::Word(word) if word.eq_ignore_ascii_case(keyword))
+    }
+}
+
+fn is_word_char(c: char) -> bool {
+    !c.is_whitespace() && !matches!(c, '(' | ')' | ',' | '"' | '=' | '!' | '<' | '>' | '~')
+}
+
+fn tokenize(s: &str) -> Result<Vec<Token>, FilterError> {
+    let mut tokens = Vec::new();
+    let mut chars = s.char_indices().peekable();
+
+    while let Some((position, c)) = chars.next() {
+        let mut followed_by = |next: char| chars.next_if(|&(_, c)| c == next).is_some();
+        let kind = match c {
+            _ if c.is_whitespace() => continue,
+            '(' => TokenKind::LParen,
+            ')' => TokenKind::RParen,
+            ',' => TokenKind::Comma,
+            '~' => TokenKind::Match,
+            '=' if followed_by('=') => TokenKind::Op(CompareOp::Eq),
+            '=' => TokenKind::Op(CompareOp::Eq),
+            '!' if followed_by('=') => TokenKind::Op(CompareOp::Ne),
+            '!' if followed_by('~') => TokenKind::NotMatch,
+            '<' if followed_by('=') => TokenKind::Op(CompareOp::Le),
+            '<' => TokenKind::Op(CompareOp::Lt),
+            '>' if followed_by('=') => TokenKind::Op(CompareOp::Ge),
+            '>' => TokenKind::Op(CompareOp::Gt),
+            '"' => {
+                let mut text = String::new();
+                loop {
+                    match chars.next() {
+                        Some((_, '"')) => break,
+                        Some((_, '\\')) => match chars.next() {
+                            Some((_, escaped)) => text.push(escaped),
+                            None => return Err(FilterError::UnterminatedString(position)),
+                        },
+                        Some((_, c)) => text.push(c),
+                        None => return Err(FilterError::UnterminatedString(position)),
+                    }
+                }
+                TokenKind::Quoted(text)
+            }
+            _ if is_word_char(c) => {
+                let mut word = c.to_string();
+                while let Some((_, c)) = chars.next_if(|&(_, c)| is_word_char(c)) {
+                    word.push(c);
+                }
+                TokenKind::Word(word)
+            }
+            _ => {
+                return Err(FilterError::UnexpectedToken {
+                    position,
+                    token: c.to_string(),
+                })
+            }
+        };
+        tokens.push(Token { kind, position });
+    }
+
+    Ok(tokens)
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    next: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.next)
+    }
+
+    fn advance(&mut self) -> Result<Token, FilterError> {
+        let token = self.peek().cloned().ok_or(FilterError::UnexpectedEnd)?;
+        self.next += 1;
+        Ok(token)
+    }
+
+    fn eat_keyword(&mut self, keyword: &str) -> bool {
+        let found = self.peek().is_some_and(|t| t.is_keyword(keyword));
+        if found {
+            self.next += 1;
+        }
+        found
+    }
+
+    fn expect(&mut self, kind: TokenKind) -> Result<(), FilterError> {
+        let token = self.advance()?;
+        match token.kind == kind {
+            true => Ok(()),
+            false => Err(token.unexpected()),
+        }
+    }
+
+    /// A bare word or quoted string.
+    fn value(&mut self) -> Result<String, FilterError> {
+        let token = self.advance()?;
+        match token.kind {
+            TokenKind::Word(word) => Ok(word),
+            TokenKind::Quoted(text) => Ok(text),
+            _ => Err(token.unexpected()),
+        }
+    }
+
+    fn or(&mut self) -> Result<Filter, FilterError> {
+        let mut filters = vec![self.and()?];
+        while self.eat_keyword("or") {
+            filters.push(self.and()?);
+        }
+        Ok(match filters.len() {
+            1 => filters.remove(0),
+            _ => Filter::Or(filters),
+        })
+    }
+
+    fn and(&mut self) -> Result<Filter, FilterError> {
+        let mut filters = vec![self.unary()?];
+        while self.eat_keyword("and") {
+            filters.push(self.unary()?);
+        }
+        Ok(match filters.len() {
+            1 => filters.remove(0),
+            _ => Filter::And(filters),
+        })
+    }
+
+    fn unary(&mut self) -> Result<Filter, FilterError> {
+        if self.eat_keyword("not") {
+            return Ok(Filter::Not(Box::new(self.unary()?)));
+        }
+        if self.peek().is_some_and(|t| t.kind == TokenKind::LParen) {
+            self.next += 1;
+            let filter = self.or()?;
+            self.expect(TokenKind::RParen)?;
+            return Ok(filter);
+        }
+        if self.eat_keyword("exists") {
+            let tag = self.value()?;
+            let tag = tag.strip_prefix(ATTRIBUTE_PREFIX).unwrap_or(&tag);
+            return Ok(Filter::Condition(Condition::Exists(Tag::from(tag))));
+        }
+        if self.eat_keyword("overlaps") {
+            return Ok(Filter::Condition(Condition::Overlaps(
+                self.value()?.parse()?,
+            )));
+        }
+        if self.eat_keyword("within") {
+            return Ok(Filter::Condition(Condition::Within(self.value()?.parse()?)));
+        }
+        self.predicate()
+    }
+
+    fn predicate(&mut self) -> Result<Filter, FilterError> {
+        let token = self.advance()?;
+        let field: Field = match token.kind {
+            TokenKind::Word(word) => word.parse()?,
+            _ => return Err(token.unexpected()),
+        };
+
+        let operator = self.advance()?;
+        let condition = match operator.kind {
+            TokenKind::Op(op) => Condition::compare(field, op, &self.value()?)?,
+            TokenKind::Match => Condition::matches(field, &self.value()?)?,
+            TokenKind::NotMatch => {
+                let condition = Condition::matches(field, &self.value()?)?;
+                return Ok(Filter::Not(Box::new(Filter::Condition(condition))));
+            }
+            _ if operator.is_keyword("in") => {
+                self.expect(TokenKind::LParen)?;
+                let mut filters = Vec::new();
+                loop {
+                    let condition =
+                        Condition::compare(field.clone(), CompareOp::Eq, &self.value()?)?;
+                    filters.push(Filter::Condition(condition));
+                    let token = self.advance()?;
+                    match token.kind {
+                        TokenKind::Comma => continue,
+                        TokenKind::RParen => break,
+                        _ => return Err(token.unexpected()),
+                    }
+                }
+                return Ok(Filter::Or(filters));
+            }
+            _ => return Err(operator.unexpected()),
+        };
+        Ok(Filter::Condition(condition))
+    }
+}
+
+#[cfg(test)]
+mod test_filter {
+    use super::*;
+    use crate::parse_line;
+
+    fn records() -> Vec<GffRecord> {
+        "chr3\tgb\tCDS\t100\t400\t0.9\t+\t0\tID=cds1;Note=protein kinase domain\n\
+         chr3\tgb\tCDS\t900\t1000\t.\t-\t0\tID=cds2;Note=unknown;Dbxref=Pfam:PF00069,GO:0004672\n\
+         chr1\tgb\tmRNA\t1\t1000\t0.2\t+\t.\tID=mRNA1;pseudo=true\n\
+         chr1\tgb\texon\t200\t300\t.\t.\t.\tParent=mRNA1"
+            .lines()
+            .map(|l| parse_line(l).unwrap())
+            .collect()
+    }
+
+    fn matching(filter: &str) -> Vec<usize> {
+        let filter = Filter::parse(filter).unwrap();
+        records()
+            .iter()
+            .enumerate()
+            .filter(|(_, r)| filter.matches(r))
+            .map(|(i, _)| i)
+            .collect()
+    }
+
+    #[test]
+    fn test_filter_expressions() {
+        assert_eq!(
+            matching(r#"type == CDS and seqid == chr3 and Note ~ "kinase""#),
+            vec![0]
+        );
+        assert_eq!(
+            matching("type in (mRNA, exon) and not exists pseudo"),
+            vec![3]
+        );
+        assert_eq!(
+            matching("overlaps chr3:350-950 or within 150-350"),
+            vec![0, 1, 3]
+        );
+        assert_eq!(matching("score > 0.5 or strand == ."), vec![0, 3]);
+        assert_eq!(matching("Dbxref ~ ^GO: and length <= 101"), vec![1]);
+        assert_eq!(
+            matching("Note != unknown and (start >= 100 or phase = 0)"),
+            vec![0, 3]
+        );
+        assert_eq!(matching(r#"Note !~ "kin""#), vec![1, 2, 3]);
+    }
+
+    #[test]
+    fn test_filter_errors() {
+        assert_eq!(
+            Filter::parse("type < CDS").unwrap_err(),
+            FilterError::UnorderedField("type".to_string())
+        );
+        assert_eq!(
+            Filter::parse("start > abc").unwrap_err(),
+            FilterError::InvalidNumber {
+                field: "start".to_string(),
+                value: "abc".to_string()
+            }
+        );
+        assert_eq!(
+            Filter::parse("type == CDS and").unwrap_err(),
+            FilterError::UnexpectedEnd
+        );
+        assert!(matches!(
+            Filter::parse("type == CDS )"),
+            Err(FilterError::UnexpectedToken { position: 12, .. })
+        ));
+        assert!(Filter::parse(r#"Note == "open"#).is_err());
+    }
+
+    #[test]
+    fn test_key_filters() {
+        let filters = vec![
+            KeyFilter {
+                op: FilterOp::try_from(5).unwrap(),
+                key: "type".to_string(),
+                value: "CDS".to_string(),
+            },
+            KeyFilter {
+                op: FilterOp::Gte,
+                key: "end".to_string(),
+                value: "1000".to_string(),
+            },
+        ];
+        let filter = Filter::from_key_filters(&filters).unwrap();
+        let ids: Vec<_> = records()
+            .iter()
+            .filter(|r| filter.matches(r))
+            .filter_map(|r| r.id().map(|id| id.to_string()))
+            .collect();
+        assert_eq!(ids, vec!["cds2"]);
+    }
+}
@@ -0,0 +1,308 @@
+use std::collections::{HashMap, HashSet};
+use std::error::Error;
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{GffRecord, Strand};
+
+/// Feature types treated as the root of a gene model.
+pub const GENE_TYPES: [&str; 4] = [
+    "gene",
+    "pseudogene",
+    "ncRNA_gene",
+    "transposable_element_gene",
+];
+pub const EXON_TYPE: &str = "exon";
+pub const CDS_TYPE: &str = "CDS";
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum GeneModelError {
+    MissingId(String),
+    DuplicateId(String),
+    UnknownParent { id: String, parent: String },
+}
+
+impl fmt::Display for GeneModelError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::MissingId(r#type) => write!(f, "{} feature without ID", r#type),
+            Self::DuplicateId(id) => write!(f, "duplicate ID: {}", id),
+            Self::UnknownParent { id, parent } => {
+                write!(f, "unknown parent {} referenced by {}", parent, id)
+            }
+        }
+    }
+}
+
+impl Error for GeneModelError {}
+
+/// A transcript together with its exon, CDS and other child features, sorted by start.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TranscriptModel {
+    pub transcript: GffRecord,
+    pub exons: Vec<GffRecord>,
+    pub cds: Vec<GffRecord>,
+    pub others: Vec<GffRecord>,
+}
+
+impl TranscriptModel {
+    pub fn new(transcript: GffRecord) -> Self {
+        Self {
+            transcript,
+            exons: Vec::new(),
+            cds: Vec::new(),
+            others: Vec::new(),
+        }
+    }
+
+    pub fn id(&self) -> &str {
+        self.transcript.id().unwrap_or_default()
+    }
+
+    pub fn strand(&self) -> Option<Strand> {
+        self.transcript.strand
+    }
+
+    pub fn is_coding(&self) -> bool {
+        !self.cds.is_empty()
+    }
+
+    /// Lowest and highest coordinate covered by CDS segments.
+    pub fn cds_span(&self) -> Option<(u32, u32)> {
+        let start = self.cds.iter().map(|c| c.start).min()?;
+        let end = self.cds.iter().map(|c| c.end).max()?;
+        Some((start, end))
+    }
+
+    /// Exon intervals, falling back to CDS segments for models annotated without exons.
+    pub fn exon_intervals(&self) -> Vec<(u32, u32)> {
+        let exons = if self.exons.is_empty() {
+            &self.cds
+        } else {
+            &self.exons
+        };
+        exons.iter().map(|e| (e.start, e.end)).collect()
+    }
+
+    /// Every record of the model, transcript first, in the order they are stored.
+    pub fn records(&self) -> impl Iterator<Item = &GffRecord> {
+        std::iter::once(&self.transcript)
+            .chain(self.exons.iter())
+            .chain(self.cds.iter())
+            .chain(self.others.iter())
+    }
+
+    fn push_child(&mut self, record: GffRecord) {
+        match record.r#type.as_str() {
+            EXON_TYPE => self.exons.push(record),
+            CDS_TYPE => self.cds.push(record),
+            _ => self.others.push(record),
+        }
+    }
+
+    fn sort_children(&mut self) {
+        self.exons.sort_by_key(|r| (r.start, r.end));
+        self.cds.sort_by_key(|r| (r.start, r.end));
+        self.others.sort_by_key(|r| (r.start, r.end));
+    }
+}
+
+/// A gene feature and the transcripts that name it as `Parent`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GeneModel {
+    pub gene: GffRecord,
+    pub transcripts: Vec<TranscriptModel>,
+}
+
+impl GeneModel {
+    pub fn new(gene: GffRecord) -> Self {
+        Self {
+            gene,
+            transcripts: Vec::new(),
+        }
+    }
+
+    pub fn id(&self) -> &str {
+        self.gene.id().unwrap_or_default()
+    }
+
+    pub fn seqid(&self) -> &str {
+        &self.gene.seqid
+    }
+
+    pub fn strand(&self) -> Option<Strand> {
+        self.gene.strand
+    }
+
+    /// Every record of the model, gene first, transcripts in order.
+    pub fn records(&self) -> impl Iterator<Item = &GffRecord> {
+        std::iter::once(&self.gene).chain(self.transcripts.iter().flat_map(|t| t.records()))
+    }
+
+    pub fn into_records(self) -> Vec<GffRecord> {
+        let mut records = vec![self.gene];
+        for transcript in self.transcripts {
+            records.push(transcript.transcript);
+            records.extend(transcript.exons);
+            records.extend(transcript.cds);
+            records.extend(transcript.others);
+        }
+        records
+    }
+}
+
+/// Assembles gene -> transcript -> exon/CDS hierarchies from flat GFF records via `ID`/`Parent`.
+///
+/// Genes are top-level features whose type is one of [`GENE_TYPES`], transcripts are the
+/// direct children of genes and every child of a transcript is attached to it. Features with
+/// several parents (e.g. shared exons, or read-through transcripts of two genes) are copied
+/// into each parent. Top-level features
+/// that are not genes, and features nested below other kinds of parents, are ignored.
+/// Genes are returned in input order.
+pub fn assemble_gene_models<I>(records: I) -> Result<Vec<GeneModel>, GeneModelError>
+where
+    I: IntoIterator<Item = GffRecord>,
+{
+    let records: Vec<GffRecord> = records.into_iter().collect();
+    let known_ids: HashSet<&str> = records.iter().filter_map(|r| r.id()).collect();
+
+    let mut genes: Vec<GeneModel> = Vec::new();
+    let mut gene_index: HashMap<String, usize> = HashMap::new();
+    for record in records.iter() {
+        if record.parents().is_empty() && GENE_TYPES.contains(&record.r#type.as_str()) {
+            let id = record
+                .id()
+                .ok_or_else(|| GeneModelError::MissingId(record.r#type.clone()))?
+                .to_string();
+            if gene_index.insert(id.clone(), genes.len()).is_some() {
+                return Err(GeneModelError::DuplicateId(id));
+            }
+            genes.push(GeneModel::new(record.clone()));
+        }
+    }
+
+    // Transcripts of several genes, such as read-through transcripts, are copied into each
+    // gene; the index keeps the record they come from and every copy.
+    let mut transcript_index: HashMap<String, (usize, Vec<(usize, usize)>)> = HashMap::new();
+    for (record_idx, record) in records.iter().enumerate() {
+        for parent in record.parents() {
+            if let Some(&gene_idx) = gene_index.get(parent) {
+                let id = record
+                    .id()
+                    .ok_or_else(|| GeneModelError::MissingId(record.r#type.clone()))?
+                    .to_string();
+                let (source, copies) = transcript_index
+                    .entry(id.clone())
+                    .or_insert((record_idx, Vec::new()));
+                if *source != record_idx {
+                    return Err(GeneModelError::DuplicateId(id));
+                }
+                if copies.iter().any(|&(g, _)| g == gene_idx) {
+                    continue;
+                }
+                let transcripts = &mut genes[gene_idx].transcripts;
+                copies.push((gene_idx, transcripts.len()));
+                transcripts.push(TranscriptModel::new(record.clone()));
+            }
+        }
+    }
+
+    for record in records.iter() {
+        for parent in record.parents() {
+            if let Some((_, copies)) = transcript_index.get(parent) {
+                for &(gene_idx, tx_idx) in copies {
+                    genes[gene_idx].transcripts[tx_idx].push_child(record.clone());
+                }
+            } else if !known_ids.contains(parent) {
+                return Err(GeneModelError::UnknownParent {
+                    id: record.id().unwrap_or(&record.r#type).to_string(),
+                    parent: parent.to_string(),
+                });
+            }
+        }
+    }
+
+    for gene in genes.iter_mut() {
+        for transcript in gene.transcripts.iter_mut() {
+            transcript.sort_children();
+        }
+    }
+
+    Ok(genes)
+}
+
+#[cfg(test)]
+mod test_gene_model {
+    use super::*;
+    use crate::parse_line;
+
+    fn records(lines: &str) -> Vec<GffRecord> {
+        lines
+            .lines()
+            .map(|line| parse_line(line).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn test_assemble_gene_models() {
+        let records = records(
+            "chr1\t.\tgene\t1\t1000\t.\t+\t.\tID=gene1\n\
+             chr1\t.\tmRNA\t1\t1000\t.\t+\t.\tID=mRNA1;Parent=gene1\n\
+             chr1\t.\tmRNA\t1\t800\t.\t+\t.\tID=mRNA2;Parent=gene1\n\
+             chr1\t.\texon\t500\t800\t.\t+\t.\tParent=mRNA1,mRNA2\n\
+             chr1\t.\texon\t1\t100\t.\t+\t.\tParent=mRNA1,mRNA2\n\
+             chr1\t.\tCDS\t50\t100\t.\t+\t0\tID=cds1;Parent=mRNA1\n\
+             chr1\t.\tregion\t1\t5000\t.\t+\t.\tID=region1",
+        );
+        let genes = assemble_gene_models(records).unwrap();
+
+        assert_eq!(genes.len(), 1);
+        assert_eq!(genes[0].transcripts.len(), 2);
+        let mrna1 = &genes[0].transcripts[0];
+        assert_eq!(mrna1.id(), "mRNA1");
+        assert_eq!(mrna1.exon_intervals(), vec![(1, 100), (500, 800)]);
+        assert_eq!(mrna1.cds_span(), Some((50, 100)));
+        assert!(!genes[0].transcripts[1].is_coding());
+    }
+
+    #[test]
+    fn test_read_through_transcript() {
+        let lines = "chr1\t.\tgene\t1\t500\t.\t+\t.\tID=gene1\n\
+             chr1\t.\tgene\t601\t1000\t.\t+\t.\tID=gene2\n\
+             chr1\t.\tmRNA\t1\t1000\t.\t+\t.\tID=mRNA1;Parent=gene1,gene2\n\
+             chr1\t.\texon\t1\t100\t.\t+\t.\tParent=mRNA1\n\
+             chr1\t.\texon\t901\t1000\t.\t+\t.\tParent=mRNA1";
+        let genes = assemble_gene_models(records(lines)).unwrap();
+        assert_eq!(genes.len(), 2);
+        for gene in &genes {
+            assert_eq!(gene.transcripts.len(), 1);
+            assert_eq!(gene.transcripts[0].id(), "mRNA1");
+            assert_eq!(
+                gene.transcripts[0].exon_intervals(),
+                vec![(1, 100), (901, 1000)]
+            );
+        }
+
+        let duplicate = format!(
+            "{}\nchr1\t.\tmRNA\t1\t500\t.\t+\t.\tID=mRNA1;Parent=gene1",
+            lines
+        );
+        assert_eq!(
+            assemble_gene_models(records(&duplicate)),
+            Err(GeneModelError::DuplicateId("mRNA1".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_assemble_gene_models_unknown_parent() {
+        let records = records("chr1\t.\texon\t1\t100\t.\t+\t.\tID=exon1;Parent=mRNA1");
+        assert_eq!(
+            assemble_gene_models(records),
+            Err(GeneModelError::UnknownParent {
+                id: "exon1".to_string(),
+                parent: "mRNA1".to_string()
+            })
+        );
+    }
+}
@@ -0,0 +1,187 @@
+//! Export of gene models as GTF, following the GENCODE layout of gene, transcript, exon and
+//! CDS lines keyed by `gene_id` and `transcript_id`.
+
+use std::io::{self, Write};
+
+use crate::gene_model::{GeneModel, TranscriptModel};
+use crate::{GffRecord, Strand, MISSING_FIELD};
+
+pub const GTF_GENE_TYPE: &str = "gene";
+pub const GTF_TRANSCRIPT_TYPE: &str = "transcript";
+
+fn gtf_line(record: &GffRecord, r#type: &str, attributes: &[(&str, String)]) -> String {
+    let score = record
+        .score
+        .map(|s| s.to_string())
+        .unwrap_or_else(|| MISSING_FIELD.to_string());
+    let strand = record.strand.as_ref().map_or(MISSING_FIELD, |s| s.as_ref());
+    let phase = record.phase.as_ref().map_or(MISSING_FIELD, |p| p.as_ref());
+    // Values are quoted, with backslashes and quotes escaped as in C strings.
+    let attributes: Vec<String> = attributes
+        .iter()
+        .map(|(key, value)| {
+            let value = value.replace('\\', "\\\\").replace('"', "\\\"");
+            format!("{} \"{}\";", key, value)
+        })
+        .collect();
+
+    format!(
+        "{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}",
+        record.seqid,
+        record.source,
+        r#type,
+        record.start,
+        record.end,
+        score,
+        strand,
+        phase,
+        attributes.join(" ")
+    )
+}
+
+fn transcript_lines(gene_id: &str, transcript: &TranscriptModel) -> Vec<String> {
+    let ids = || {
+        vec![
+            ("gene_id", gene_id.to_string()),
+            ("transcript_id", transcript.id().to_string()),
+        ]
+    };
+
+    let mut attributes = ids();
+    attributes.push(("transcript_biotype", transcript.transcript.r#type.clone()));
+    if let Some(name) = transcript.transcript.name() {
+        attributes.push(("transcript_name", name.to_string()));
+    }
+    let mut lines = vec![gtf_line(
+        &transcript.transcript,
+        GTF_TRANSCRIPT_TYPE,
+        &attributes,
+    )];
+
+    // Exons are numbered in transcription order.
+    let mut exons: Vec<&GffRecord> = transcript.exons.iter().collect();
+    if transcript.strand() == Some(Strand::Reverse) {
+        exons.reverse();
+    }
+    let mut exon_lines: Vec<(u32, String)> = exons
+        .iter()
+        .enumerate()
+        .map(|(i, exon)| {
+            let mut attributes = ids();
+            attributes.push(("exon_number", (i + 1).to_string()));
+            (exon.start, gtf_line(exon, &exon.r#type, &attributes))
+        })
+        .collect();
+    exon_lines.sort_by_key(|(start, _)| *start);
+    lines.extend(exon_lines.into_iter().map(|(_, line)| line));
+
+    for cds in &transcript.cds {
+        lines.push(gtf_line(cds, &cds.r#type, &ids()));
+    }
+    lines
+}
+
+/// Lines of one gene model. Features other than exons and CDS are not part of GTF.
+pub fn gtf_lines(gene: &GeneModel) -> Vec<String> {
+    let mut attributes = vec![("gene_id", gene.id().to_string())];
+    if let Some(name) = gene.gene.name() {
+        attributes.push(("gene_name", name.to_string()));
+    }
+    attributes.push(("gene_biotype", gene.gene.r#type.clone()));
+
+    let mut lines = vec![gtf_line(&gene.gene, GTF_GENE_TYPE, &attributes)];
+    for transcript in &gene.transcripts {
+        lines.extend(transcript_lines(gene.id(), transcript));
+    }
+    lines
+}
+
+pub fn write_gtf<W: Write>(mut writer: W, genes: &[GeneModel]) -> io::Result<()> {
+    for gene in genes {
+        for line in gtf_lines(gene) {
+            writeln!(writer, "{}", line)?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test_gtf {
+    use super::*;
+    use crate::gene_model::assemble_gene_models;
+    use crate::parse_line;
+
+    #[test]
+    fn test_gtf_lines() {
+        let records = "chr1\tgb\tgene\t1\t1000\t.\t-\t.\tID=gene1;Name=ABC1\n\
+             chr1\tgb\tmRNA\t1\t1000\t.\t-\t.\tID=mRNA1;Parent=gene1\n\
+             chr1\tgb\texon\t1\t100\t.\t-\t.\tParent=mRNA1\n\
+             chr1\tgb\texon\t500\t1000\t.\t-\t.\tParent=mRNA1\n\
+             chr1\tgb\tCDS\t50\t100\t.\t-\t2\tParent=mRNA1"
+            .lines()
+            .map(|l| parse_line(l).unwrap());
+        let genes = assemble_gene_models(records).unwrap();
+        let lines = gtf_lines(&genes[0]);
+
+        assert_eq!(lines.len(), 5);
+        assert_eq!(
+            lines[0],
+            "chr1\tgb\tgene\t1\t1000\t.\t-\t.\tgene_id \"gene1\"; gene_name \"ABC1\"; gene_biotype \"gene\";"
+        );
+        assert!(lines[1].starts_with("chr1\tgb\ttranscript\t1\t1000"));
+        assert!(lines[2].ends_with("transcript_id \"mRNA1\"; exon_number \"2\";"));
+        assert!(lines[4].starts_with("chr1\tgb\tCDS\t50\t100\t.\t-\t2\t"));
+    }
+
+    #[test]
+    fn test_attribute_quoting() {
+        let records = "chr1\tgb\tncRNA_gene\t1\t100\t.\t+\t.\tID=gene1;Name=say \"hi\"\n\
+             chr1\tgb\tlnc_RNA\t1\t100\t.\t+\t.\tID=tx1;Parent=gene1;Name=a\\b\n\
+             chr1\tgb\texon\t1\t100\t.\t+\t.\tParent=tx1"
+            .lines()
+            .map(|l| parse_line(l).unwrap());
+        let genes = assemble_gene_models(records).unwrap();
+        let lines = gtf_lines(&genes[0]);
+
+        assert_eq!(
+            lines[0],
+            "chr1\tgb\tgene\t1\t100\t.\t+\t.\tgene_id \"gene1\"; gene_name \"say \\\"hi\\\"\"; gene_biotype \"ncRNA_gene\";"
+        );
+        assert!(lines[1].ends_with(
+            "transcript_id \"tx1\"; transcript_biotype \"lnc_RNA\"; transcript_name \"a\\\\b\";"
+        ));
+        assert!(lines[2].ends_with("transcript_id \"tx1\"; exon_number \"1\";"));
+    }
+
+    #[test]
+    fn test_exon_numbering() {
+        let records = "chr1\tgb\tgene\t1\t1000\t.\t+\t.\tID=gene1\n\
+             chr1\tgb\tmRNA\t1\t1000\t.\t+\t.\tID=mRNA1;Parent=gene1\n\
+             chr1\tgb\texon\t500\t1000\t.\t+\t.\tParent=mRNA1\n\
+             chr1\tgb\texon\t1\t100\t.\t+\t.\tParent=mRNA1\n\
+             chr1\tgb\tmRNA\t1\t1000\t.\t-\t.\tID=mRNA2;Parent=gene1\n\
+             chr1\tgb\texon\t1\t100\t.\t-\t.\tParent=mRNA2\n\
+             chr1\tgb\texon\t500\t1000\t.\t-\t.\tParent=mRNA2"
+            .lines()
+            .map(|l| parse_line(l).unwrap());
+        let genes = assemble_gene_models(records).unwrap();
+        let exons: Vec<(String, String)> = gtf_lines(&genes[0])
+            .iter()
+            .filter(|l| l.split('\t').nth(2) == Some("exon"))
+            .map(|l| {
+                let fields: Vec<&str> = l.split('\t').collect();
+                let number = l.rsplit("exon_number ").next().unwrap();
+                (fields[3].to_string(), number.to_string())
+            })
+            .collect();
+        assert_eq!(
+            exons,
+            vec![
+                ("1".to_string(), "\"1\";".to_string()),
+                ("500".to_string(), "\"2\";".to_string()),
+                ("1".to_string(), "\"2\";".to_string()),
+                ("500".to_string(), "\"1\";".to_string()),
+            ]
+        );
+    }
+}
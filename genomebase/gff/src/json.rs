@@ -0,0 +1,249 @@
+//! JSON representations of annotations.
+//!
+//! A [`GffRecord`] serializes to a flat object whose attributes are keyed by the tag names of
+//! the GFF3 specification, with single values as strings and multiple values as arrays:
+//!
+//! ```json
+//! {"seqid":"chr1","source":"genomebase","type":"mRNA","start":1,"end":1000,"score":null,
+//!  "strand":"+","phase":null,"attributes":{"ID":"mRNA1","Parent":["gene1","gene2"]}}
+//! ```
+//!
+//! The nested form, [`FeatureNode`], adds a `children` array holding the features that name
+//! the node as `Parent`. JSON Lines files hold one record, or one root feature, per line.
+
+use std::collections::{HashMap, HashSet};
+use std::io::{BufRead, Write};
+
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::GffRecord;
+
+/// A feature with its child features, nested through `Parent` references.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FeatureNode {
+    #[serde(flatten)]
+    pub record: GffRecord,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub children: Vec<FeatureNode>,
+}
+
+impl FeatureNode {
+    pub fn new(record: GffRecord) -> Self {
+        Self {
+            record,
+            children: Vec::new(),
+        }
+    }
+
+    /// Flattens the tree in depth-first order.
+    pub fn records(&self) -> Vec<&GffRecord> {
+        let mut records = vec![&self.record];
+        for child in &self.children {
+            records.extend(child.records());
+        }
+        records
+    }
+}
+
+fn attach(
+    index: usize,
+    records: &[GffRecord],
+    children: &HashMap<usize, Vec<usize>>,
+    path: &mut Vec<usize>,
+) -> FeatureNode {
+    let mut node = FeatureNode::new(records[index].clone());
+    path.push(index);
+    for &child in children.get(&index).into_iter().flatten() {
+        // Cyclic Parent references are cut rather than followed forever.
+        if !path.contains(&child) {
+            node.children.push(attach(child, records, children, path));
+        }
+    }
+    path.pop();
+    node
+}
+
+/// Nests records by `Parent`. Records without a known parent become roots, and records with
+/// several parents appear under each of them. Roots keep their input order.
+pub fn build_feature_tree(records: &[GffRecord]) -> Vec<FeatureNode> {
+    // Discontinuous features share an ID across lines; children attach to the first one.
+    let mut by_id: HashMap<&str, usize> = HashMap::new();
+    for (i, record) in records.iter().enumerate() {
+        if let Some(id) = record.id() {
+            by_id.entry(id).or_insert(i);
+        }
+    }
+
+    let mut children: HashMap<usize, Vec<usize>> = HashMap::new();
+    let mut roots = Vec::new();
+    for (i, record) in records.iter().enumerate() {
+        let parents: Vec<usize> = record
+            .parents()
+            .into_iter()
+            .filter_map(|p| by_id.get(p).copied())
+            .collect();
+        if parents.is_empty() {
+            roots.push(i);
+        }
+        for parent in parents {
+            children.entry(parent).or_default().push(i);
+        }
+    }
+
+    roots
+        .into_iter()
+        .map(|root| attach(root, records, &children, &mut Vec::new()))
+        .collect()
+}
+
+/// Flattens trees back into records. A feature with several parents is emitted once, from
+/// the copy under its first parent present in the trees; identical records elsewhere, such as
+/// duplicated lines, are all kept.
+pub fn flatten_feature_tree(nodes: &[FeatureNode]) -> Vec<GffRecord> {
+    let ids: HashSet<&str> = nodes
+        .iter()
+        .flat_map(|node| node.records())
+        .filter_map(GffRecord::id)
+        .collect();
+    let mut records = Vec::new();
+    for node in nodes {
+        flatten(node, &ids, &mut records);
+    }
+    records
+}
+
+fn flatten(node: &FeatureNode, ids: &HashSet<&str>, records: &mut Vec<GffRecord>) {
+    records.push(node.record.clone());
+    let parent = node.record.id();
+    for child in &node.children {
+        let first_parent = child.record.parents().into_iter().find(|p| ids.contains(p));
+        if first_parent.is_none() || first_parent == parent {
+            flatten(child, ids, records);
+        }
+    }
+}
+
+/// Writes one JSON value per line.
+pub fn write_json_lines<W, T, I>(mut writer: W, values: I) -> Result<()>
+where
+    W: Write,
+    T: Serialize,
+    I: IntoIterator<Item = T>,
+{
+    for value in values {
+        serde_json::to_writer(&mut writer, &value)?;
+        writeln!(writer)?;
+    }
+    Ok(())
+}
+
+/// Reads JSON Lines, one value per non-empty line, such as [`GffRecord`] or [`FeatureNode`].
+pub struct JsonLinesReader<R, T> {
+    lines: std::io::Lines<R>,
+    line_number: usize,
+    _marker: std::marker::PhantomData<T>,
+}
+
+impl<R: BufRead, T> JsonLinesReader<R, T> {
+    pub fn new(reader: R) -> Self {
+        Self {
+            lines: reader.lines(),
+            line_number: 0,
+            _marker: std::marker::PhantomData,
+        }
+    }
+}
+
+impl<R, T> Iterator for JsonLinesReader<R, T>
+where
+    R: BufRead,
+    T: for<'de> Deserialize<'de>,
+{
+    type Item = Result<T>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let line = match self.lines.next()? {
+                Ok(line) => line,
+                Err(e) => return Some(Err(e.into())),
+            };
+            self.line_number += 1;
+            if line.trim().is_empty() {
+                continue;
+            }
+            return Some(
+                serde_json::from_str(&line)
+                    .map_err(|e| anyhow!("line {}: {}", self.line_number, e)),
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod test_json {
+    use super::*;
+    use crate::parse_line;
+
+    fn records() -> Vec<GffRecord> {
+        "chr1\tgb\tgene\t1\t1000\t.\t+\t.\tID=gene1;Name=abc1;locus_tag=ABC_1\n\
+         chr1\tgb\tmRNA\t1\t1000\t.\t+\t.\tID=mRNA1;Parent=gene1\n\
+         chr1\tgb\tmRNA\t1\t800\t.\t+\t.\tID=mRNA2;Parent=gene1\n\
+         chr1\tgb\texon\t1\t100\t.\t+\t.\tParent=mRNA1,mRNA2"
+            .lines()
+            .map(|l| parse_line(l).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn test_record_json() {
+        let json = serde_json::to_value(&records()[3]).unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!({
+                "seqid": "chr1",
+                "source": "gb",
+                "type": "exon",
+                "start": 1,
+                "end": 100,
+                "score": null,
+                "strand": "+",
+                "phase": null,
+                "attributes": {"Parent": ["mRNA1", "mRNA2"]},
+            })
+        );
+        let json = serde_json::to_value(&records()[0]).unwrap();
+        assert_eq!(
+            json["attributes"],
+            serde_json::json!({"ID": "gene1", "Name": "abc1", "locus_tag": "ABC_1"})
+        );
+    }
+
+    #[test]
+    fn test_feature_tree_json_lines_roundtrip() {
+        let records = records();
+        let tree = build_feature_tree(&records);
+        assert_eq!(tree.len(), 1);
+        assert_eq!(tree[0].children.len(), 2);
+        assert_eq!(tree[0].children[1].children.len(), 1);
+
+        let mut lines = Vec::new();
+        write_json_lines(&mut lines, &tree).unwrap();
+        let read: Vec<FeatureNode> = JsonLinesReader::new(lines.as_slice())
+            .collect::<Result<_>>()
+            .unwrap();
+        assert_eq!(read, tree);
+        let flattened = flatten_feature_tree(&read);
+        let expected = [0, 1, 3, 2].map(|i| records[i].clone());
+        assert_eq!(flattened, expected);
+    }
+
+    #[test]
+    fn test_flatten_duplicates() {
+        let mut records = records();
+        records.push(records[3].clone());
+        let flattened = flatten_feature_tree(&build_feature_tree(&records));
+        let expected = [0, 1, 3, 3, 2].map(|i| records[i].clone());
+        assert_eq!(flattened, expected);
+    }
+}
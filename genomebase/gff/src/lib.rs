@@ -1,16 +1,35 @@
+#[cfg(feature = "arrow")]
+pub mod arrow;
 pub mod attributes;
+pub mod bed;
+pub mod chain;
+pub mod diff;
 pub mod directive;
+pub mod filter;
+pub mod gene_model;
+pub mod gtf;
+pub mod json;
+pub mod liftover;
+pub mod lint;
+pub mod merge;
+pub mod reader;
+pub mod stats;
+pub mod tbl;
 
+use std::cmp::Reverse;
+use std::collections::BTreeMap;
+use std::fmt;
 use std::str::FromStr;
 
-use attributes::{parse_attributes, Attributes};
+use attributes::{format_attributes, parse_attributes, Attributes, Tag, Value};
+use common::{Seqid, Topology};
 use serde::{Deserialize, Serialize};
 
 pub(crate) const MISSING_FIELD: &str = ".";
 const FIELD_DELIMITER: char = '\t';
 const MAX_FIELDS: usize = 9;
 
-#[derive(Debug, Clone, Copy, PartialEq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum Strand {
     Forward,
     Reverse,
@@ -54,7 +73,7 @@ impl FromStr for Strand {
     }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum Phase {
     Zero,
     One,
@@ -104,7 +123,7 @@ impl FromStr for Phase {
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct GffRecord {
-    pub seqid: String,
+    pub seqid: Seqid,
     pub source: String,
     pub r#type: String,
     pub start: u32,
@@ -115,6 +134,68 @@ pub struct GffRecord {
     pub attributes: Attributes,
 }
 
+impl GffRecord {
+    pub fn attribute(&self, tag: &Tag) -> Option<&Value> {
+        self.attributes.get(tag)
+    }
+
+    pub fn id(&self) -> Option<&str> {
+        self.attribute(&Tag::Id).and_then(|v| v.first())
+    }
+
+    pub fn name(&self) -> Option<&str> {
+        self.attribute(&Tag::Name).and_then(|v| v.first())
+    }
+
+    pub fn parents(&self) -> Vec<&str> {
+        self.attribute(&Tag::Parent)
+            .map(|v| v.iter().collect())
+            .unwrap_or_default()
+    }
+
+    /// Length of the feature in bases. Coordinates are 1-based and inclusive.
+    pub fn len(&self) -> u32 {
+        (self.end + 1).saturating_sub(self.start)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Topology declared by the `Is_circular` attribute, usually on `region` features.
+    pub fn topology(&self) -> Option<Topology> {
+        match self.attribute(&Tag::IsCircular)?.first()? {
+            "true" => Some(Topology::Circular),
+            _ => Some(Topology::Linear),
+        }
+    }
+}
+
+impl fmt::Display for GffRecord {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let score = self
+            .score
+            .map(|s| s.to_string())
+            .unwrap_or_else(|| MISSING_FIELD.to_string());
+        let strand = self.strand.as_ref().map_or(MISSING_FIELD, |s| s.as_ref());
+        let phase = self.phase.as_ref().map_or(MISSING_FIELD, |p| p.as_ref());
+
+        let fields = [
+            self.seqid.as_str(),
+            self.source.as_str(),
+            self.r#type.as_str(),
+            &self.start.to_string(),
+            &self.end.to_string(),
+            &score,
+            strand,
+            phase,
+            &format_attributes(&self.attributes),
+        ];
+
+        write!(f, "{}", fields.join(&FIELD_DELIMITER.to_string()))
+    }
+}
+
 pub fn parse_line(line: &str) -> Result<GffRecord, String> {
     let fields: Vec<&str> = line.split(FIELD_DELIMITER).collect();
     if fields.len() != MAX_FIELDS {
@@ -125,7 +206,7 @@ pub fn parse_line(line: &str) -> Result<GffRecord, String> {
         ));
     }
 
-    let seqid = fields[0].to_string();
+    let seqid = fields[0].parse::<Seqid>()?;
     let source = fields[1].to_string();
     let r#type = fields[2].to_string();
     let start = fields[3].parse::<u32>().map_err(|e| e.to_string())?;
@@ -156,3 +237,57 @@ pub fn parse_line(line: &str) -> Result<GffRecord, String> {
         attributes,
     })
 }
+
+/// Sorts records by seqid in natural order and start, longer features first so that parents precede their
+/// children. The sort is stable, so features with identical spans keep their input order.
+pub fn sort_records(records: &mut [GffRecord]) {
+    records.sort_by(|a, b| {
+        (&a.seqid, a.start, Reverse(a.end)).cmp(&(&b.seqid, b.start, Reverse(b.end)))
+    });
+}
+
+/// Topology of each seqid with a feature declaring it; a seqid is circular when any does.
+pub fn seqid_topologies<'a, I>(records: I) -> BTreeMap<Seqid, Topology>
+where
+    I: IntoIterator<Item = &'a GffRecord>,
+{
+    let mut topologies = BTreeMap::new();
+    for record in records {
+        if let Some(topology) = record.topology() {
+            let entry = topologies.entry(record.seqid.clone()).or_insert(topology);
+            if topology == Topology::Circular {
+                *entry = topology;
+            }
+        }
+    }
+    topologies
+}
+
+#[cfg(test)]
+mod test_records {
+    use super::*;
+
+    #[test]
+    fn test_natural_sort_and_topology() {
+        let mut records: Vec<GffRecord> = [
+            "chr10\t.\tgene\t1\t100\t.\t+\t.\tID=gene3",
+            "chrM\t.\tregion\t1\t16569\t.\t+\t.\tID=chrM;Is_circular=true",
+            "chr2\t.\tgene\t50\t100\t.\t+\t.\tID=gene2",
+            "chr2\t.\tregion\t1\t1000\t.\t+\t.\tID=chr2;Is_circular=false",
+        ]
+        .iter()
+        .map(|line| parse_line(line).unwrap())
+        .collect();
+        sort_records(&mut records);
+        let ids: Vec<&str> = records.iter().map(|r| r.id().unwrap()).collect();
+        assert_eq!(ids, vec!["chr2", "gene2", "gene3", "chrM"]);
+
+        let topologies = seqid_topologies(&records);
+        assert_eq!(topologies.len(), 2);
+        assert_eq!(
+            topologies.values().collect::<Vec<_>>(),
+            vec![&Topology::Linear, &Topology::Circular]
+        );
+        assert!(parse_line("\t.\tgene\t1\t100\t.\t+\t.\tID=gene1").is_err());
+    }
+}
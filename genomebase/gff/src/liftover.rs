@@ -0,0 +1,531 @@
+//! Liftover of GFF records between assemblies through a [`ChainMap`].
+
+use std::collections::{BTreeMap, HashSet};
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+use crate::chain::{ChainMap, MappedPiece};
+use crate::directive::{DirectiveHeader, GenomeBuild, SequenceRegion};
+use crate::{GffRecord, Strand};
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct LiftoverOptions {
+    /// Minimum fraction of the bases of a feature that must map for it to be lifted.
+    pub min_match: f64,
+    /// Emit one record per chain, or per gapless run within a chain, when a feature maps to
+    /// several pieces instead of failing.
+    pub allow_split: bool,
+    /// Lift a feature spanning alignment gaps within a chain as one interval from its first
+    /// to its last mapped base, as for genes whose introns hold indels.
+    pub allow_gaps: bool,
+}
+
+impl Default for LiftoverOptions {
+    fn default() -> Self {
+        Self {
+            min_match: 0.95,
+            allow_split: false,
+            allow_gaps: false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum LiftoverFailure {
+    /// No base of the feature maps to the new assembly.
+    Deleted,
+    /// Fewer bases than [`LiftoverOptions::min_match`] map.
+    PartiallyDeleted { mapped_fraction: f64 },
+    /// The feature maps to several chains and splitting is disabled.
+    Split { chains: usize },
+    /// The feature spans alignment gaps within a chain and neither gaps nor splitting are
+    /// allowed.
+    Gapped { gaps: usize },
+    /// Parts of the feature map to different sequences.
+    DifferentChromosomes,
+    /// A parent of the feature failed to lift.
+    ParentFailed(String),
+    /// The new seqid cannot be represented by the target model.
+    UnsupportedSeqid(String),
+    /// The lifted coordinate does not fit the target coordinate type.
+    CoordinateOutOfRange(u64),
+}
+
+impl fmt::Display for LiftoverFailure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Deleted => write!(f, "deleted in new assembly"),
+            Self::PartiallyDeleted { mapped_fraction } => {
+                write!(f, "partially deleted ({:.3} mapped)", mapped_fraction)
+            }
+            Self::Split { chains } => write!(f, "split across {} chains", chains),
+            Self::Gapped { gaps } => write!(f, "spans {} alignment gaps", gaps),
+            Self::DifferentChromosomes => write!(f, "parts map to different chromosomes"),
+            Self::ParentFailed(parent) => write!(f, "parent {} failed to lift", parent),
+            Self::UnsupportedSeqid(seqid) => write!(f, "unsupported seqid: {}", seqid),
+            Self::CoordinateOutOfRange(pos) => write!(f, "coordinate out of range: {}", pos),
+        }
+    }
+}
+
+impl std::error::Error for LiftoverFailure {}
+
+/// A lifted interval in 1-based inclusive coordinates.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LiftedInterval {
+    pub seqid: String,
+    pub start: u64,
+    pub end: u64,
+    /// Whether the new assembly is reverse complemented relative to the old one here.
+    pub reversed: bool,
+}
+
+impl LiftedInterval {
+    pub fn len(&self) -> u64 {
+        self.end + 1 - self.start
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.end < self.start
+    }
+}
+
+/// Pieces of one chain split where an alignment gap lies between consecutive pieces.
+fn gapless_runs<'a>(pieces: &[&'a MappedPiece]) -> Vec<Vec<&'a MappedPiece>> {
+    let mut runs: Vec<Vec<&MappedPiece>> = Vec::new();
+    for &piece in pieces {
+        let adjacent = runs.last().and_then(|run| run.last()).is_some_and(|last| {
+            let query_adjacent = match piece.q_strand {
+                Strand::Reverse => piece.q_end == last.q_start,
+                Strand::Forward => piece.q_start == last.q_end,
+            };
+            piece.t_start == last.t_end && query_adjacent
+        });
+        match runs.last_mut() {
+            Some(run) if adjacent => run.push(piece),
+            _ => runs.push(vec![piece]),
+        }
+    }
+    runs
+}
+
+/// Lifts a 1-based inclusive interval, returning one interval per piece it maps to.
+///
+/// A feature spanning alignment gaps within a chain is lifted from its first to its last
+/// mapped base with [`LiftoverOptions::allow_gaps`], split at the gaps with
+/// [`LiftoverOptions::allow_split`], and fails otherwise.
+pub fn lift_interval(
+    chains: &ChainMap,
+    seqid: &str,
+    start: u64,
+    end: u64,
+    options: &LiftoverOptions,
+) -> Result<Vec<LiftedInterval>, LiftoverFailure> {
+    let pieces = chains.map_interval(seqid, start.saturating_sub(1), end);
+    if pieces.is_empty() {
+        return Err(LiftoverFailure::Deleted);
+    }
+
+    let length = end + 1 - start;
+    let mapped: u64 = pieces.iter().map(|p| p.t_end - p.t_start).sum();
+    let mapped_fraction = mapped as f64 / length as f64;
+    if mapped_fraction < options.min_match {
+        return Err(LiftoverFailure::PartiallyDeleted { mapped_fraction });
+    }
+
+    let mut by_chain: BTreeMap<usize, Vec<&MappedPiece>> = BTreeMap::new();
+    for piece in &pieces {
+        by_chain.entry(piece.chain).or_default().push(piece);
+    }
+    if by_chain.len() > 1 && !options.allow_split {
+        return Err(LiftoverFailure::Split {
+            chains: by_chain.len(),
+        });
+    }
+    let runs: Vec<Vec<&MappedPiece>> = if options.allow_gaps {
+        by_chain.into_values().collect()
+    } else {
+        by_chain
+            .values()
+            .flat_map(|pieces| gapless_runs(pieces))
+            .collect()
+    };
+    if runs.len() > 1 && !options.allow_split {
+        return Err(LiftoverFailure::Gapped {
+            gaps: runs.len() - 1,
+        });
+    }
+
+    Ok(runs
+        .iter()
+        .map(|pieces| LiftedInterval {
+            seqid: pieces[0].q_name.clone(),
+            start: pieces.iter().map(|p| p.q_start).min().unwrap_or_default() + 1,
+            end: pieces.iter().map(|p| p.q_end).max().unwrap_or_default(),
+            reversed: pieces[0].q_strand == Strand::Reverse,
+        })
+        .collect())
+}
+
+fn flip(strand: Option<Strand>) -> Option<Strand> {
+    match strand {
+        Some(Strand::Forward) => Some(Strand::Reverse),
+        Some(Strand::Reverse) => Some(Strand::Forward),
+        None => None,
+    }
+}
+
+/// Lifts a single record. Split features yield one record per chain, sharing the same `ID`.
+pub fn lift_record(
+    chains: &ChainMap,
+    record: &GffRecord,
+    options: &LiftoverOptions,
+) -> Result<Vec<GffRecord>, LiftoverFailure> {
+    let intervals = lift_interval(
+        chains,
+        &record.seqid,
+        record.start as u64,
+        record.end as u64,
+        options,
+    )?;
+
+    intervals
+        .into_iter()
+        .map(|interval| {
+            let start = u32::try_from(interval.start)
+                .map_err(|_| LiftoverFailure::CoordinateOutOfRange(interval.start))?;
+            let end = u32::try_from(interval.end)
+                .map_err(|_| LiftoverFailure::CoordinateOutOfRange(interval.end))?;
+            let strand = if interval.reversed {
+                flip(record.strand)
+            } else {
+                record.strand
+            };
+            let seqid = interval
+                .seqid
+                .parse()
+                .map_err(|_| LiftoverFailure::UnsupportedSeqid(interval.seqid.clone()))?;
+            Ok(GffRecord {
+                seqid,
+                start,
+                end,
+                strand,
+                ..record.clone()
+            })
+        })
+        .collect()
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum LiftoverStatus {
+    Failed(LiftoverFailure),
+    LengthChanged { old: u32, new: u32 },
+    Split { pieces: usize },
+}
+
+/// Outcome for a record that was not lifted unchanged.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LiftoverEvent {
+    /// Position of the record in the input.
+    pub index: usize,
+    pub id: Option<String>,
+    pub r#type: String,
+    pub status: LiftoverStatus,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct LiftoverReport {
+    pub lifted: usize,
+    pub events: Vec<LiftoverEvent>,
+}
+
+impl LiftoverReport {
+    pub fn failures(&self) -> impl Iterator<Item = &LiftoverEvent> {
+        self.events
+            .iter()
+            .filter(|e| matches!(e.status, LiftoverStatus::Failed(_)))
+    }
+}
+
+/// Lifts records, dropping those that fail along with all their descendants. Features with
+/// children, such as genes and transcripts, may span alignment gaps; their children carry
+/// the gaps.
+pub fn liftover_records<I>(
+    chains: &ChainMap,
+    records: I,
+    options: &LiftoverOptions,
+) -> (Vec<GffRecord>, LiftoverReport)
+where
+    I: IntoIterator<Item = GffRecord>,
+{
+    let records: Vec<GffRecord> = records.into_iter().collect();
+    let mut report = LiftoverReport::default();
+
+    let mut lifted: Vec<Option<Vec<GffRecord>>> = Vec::with_capacity(records.len());
+    let mut failed_ids: HashSet<String> = HashSet::new();
+    let parent_ids: HashSet<&str> = records.iter().flat_map(|r| r.parents()).collect();
+    let parent_options = LiftoverOptions {
+        allow_gaps: true,
+        ..*options
+    };
+    for (index, record) in records.iter().enumerate() {
+        let options = match record.id() {
+            Some(id) if parent_ids.contains(id) => &parent_options,
+            _ => options,
+        };
+        let event = |status| LiftoverEvent {
+            index,
+            id: record.id().map(|id| id.to_string()),
+            r#type: record.r#type.clone(),
+            status,
+        };
+        match lift_record(chains, record, options) {
+            Ok(new) => {
+                if new.len() > 1 {
+                    report
+                        .events
+                        .push(event(LiftoverStatus::Split { pieces: new.len() }));
+                } else if new[0].len() != record.len() {
+                    report.events.push(event(LiftoverStatus::LengthChanged {
+                        old: record.len(),
+                        new: new[0].len(),
+                    }));
+                }
+                lifted.push(Some(new));
+            }
+            Err(failure) => {
+                if let Some(id) = record.id() {
+                    failed_ids.insert(id.to_string());
+                }
+                report.events.push(event(LiftoverStatus::Failed(failure)));
+                lifted.push(None);
+            }
+        }
+    }
+
+    // Propagate failures to descendants until no new feature is dropped.
+    loop {
+        let mut changed = false;
+        for (index, record) in records.iter().enumerate() {
+            if lifted[index].is_none() {
+                continue;
+            }
+            let Some(parent) = record
+                .parents()
+                .into_iter()
+                .find(|p| failed_ids.contains(*p))
+            else {
+                continue;
+            };
+            report.events.push(LiftoverEvent {
+                index,
+                id: record.id().map(|id| id.to_string()),
+                r#type: record.r#type.clone(),
+                status: LiftoverStatus::Failed(LiftoverFailure::ParentFailed(parent.to_string())),
+            });
+            if let Some(id) = record.id() {
+                failed_ids.insert(id.to_string());
+            }
+            lifted[index] = None;
+            changed = true;
+        }
+        if !changed {
+            break;
+        }
+    }
+
+    report.events.sort_by_key(|e| e.index);
+    let records: Vec<GffRecord> = lifted.into_iter().flatten().flatten().collect();
+    report.lifted = records.len();
+    (records, report)
+}
+
+/// `##sequence-region` directives for every query sequence of the chains.
+pub fn lifted_sequence_regions(chains: &ChainMap) -> Vec<SequenceRegion> {
+    chains
+        .query_sequences()
+        .into_iter()
+        .filter_map(|(seqid, size)| Some(SequenceRegion::new(seqid.parse().ok()?, 1, size)))
+        .collect()
+}
+
+/// Header for the lifted file: sequence regions of the new assembly and its genome build.
+///
+/// Only sequences that appear in the original header's regions after liftover are kept, or
+/// every query sequence when the original header lists none.
+pub fn lift_header(
+    header: &DirectiveHeader,
+    chains: &ChainMap,
+    genome_build: GenomeBuild,
+) -> DirectiveHeader {
+    let regions = lifted_sequence_regions(chains);
+    let original = header.sequence_regions();
+    let regions = if original.is_empty() {
+        regions
+    } else {
+        let targets: HashSet<String> = original
+            .iter()
+            .flat_map(|region| {
+                chains
+                    .map_interval(&region.seqid, region.start.saturating_sub(1), region.end)
+                    .into_iter()
+                    .map(|piece| piece.q_name)
+            })
+            .collect();
+        regions
+            .into_iter()
+            .filter(|region| targets.contains(region.seqid.as_str()))
+            .collect()
+    };
+
+    let mut header = header.clone();
+    header.set_sequence_regions(regions);
+    header.set_genome_build(genome_build);
+    header
+}
+
+#[cfg(test)]
+mod test_liftover {
+    use super::*;
+    use crate::parse_line;
+
+    const CHAINS: &str = "chain 1000 chr1 1000 + 0 1000 chr1 2000 + 1000 1990 1\n\
+        400\t20\t10\n\
+        580\n\
+        chain 500 chr2 500 + 0 100 chrB 300 - 50 150 2\n\
+        100\n";
+
+    fn records(lines: &str) -> Vec<GffRecord> {
+        lines.lines().map(|l| parse_line(l).unwrap()).collect()
+    }
+
+    #[test]
+    fn test_liftover_records() {
+        let chains = ChainMap::from_reader(CHAINS.as_bytes()).unwrap();
+        let records = records(
+            "chr1\t.\tgene\t1\t100\t.\t+\t.\tID=g1\n\
+             chr1\t.\tgene\t301\t700\t.\t+\t.\tID=g2\n\
+             chr1\t.\texon\t301\t700\t.\t+\t.\tID=e2;Parent=g2\n\
+             chr1\t.\tgene\t395\t425\t.\t+\t.\tID=g3\n\
+             chr1\t.\tmRNA\t396\t399\t.\t+\t.\tID=t3;Parent=g3\n\
+             chr2\t.\tgene\t11\t20\t.\t+\t.\tID=g4",
+        );
+        let (lifted, report) =
+            liftover_records(&chains, records.clone(), &LiftoverOptions::default());
+
+        assert_eq!(lifted.len(), 3);
+        assert_eq!((lifted[0].start, lifted[0].end), (1001, 1100));
+        assert_eq!((lifted[1].start, lifted[1].end), (1301, 1690));
+        assert_eq!(lifted[2].seqid, "chrB");
+        assert_eq!((lifted[2].start, lifted[2].end), (231, 240));
+        assert_eq!(lifted[2].strand, Some(Strand::Reverse));
+
+        let statuses: Vec<&LiftoverStatus> = report.events.iter().map(|e| &e.status).collect();
+        assert_eq!(
+            statuses,
+            vec![
+                &LiftoverStatus::LengthChanged { old: 400, new: 390 },
+                &LiftoverStatus::Failed(LiftoverFailure::Gapped { gaps: 1 }),
+                &LiftoverStatus::Failed(LiftoverFailure::PartiallyDeleted {
+                    mapped_fraction: 11.0 / 31.0
+                }),
+                &LiftoverStatus::Failed(LiftoverFailure::ParentFailed("g3".to_string())),
+            ]
+        );
+
+        let split = LiftoverOptions {
+            allow_split: true,
+            ..LiftoverOptions::default()
+        };
+        let exon = lift_record(&chains, &records[2], &split).unwrap();
+        let spans: Vec<(u32, u32)> = exon.iter().map(|r| (r.start, r.end)).collect();
+        assert_eq!(spans, vec![(1301, 1400), (1411, 1690)]);
+    }
+
+    /// chr3 maps to the reverse strand of chrC with a 10 base insertion in chrC, then to the
+    /// forward strand of chrD.
+    const MINUS_CHAINS: &str = "chain 500 chr3 1000 + 100 290 chrC 1000 - 200 400 3\n\
+        100\t0\t10\n\
+        90\n\
+        chain 400 chr3 1000 + 290 400 chrD 500 + 0 110 4\n\
+        110\n";
+
+    #[test]
+    fn test_minus_strand() {
+        let chains = ChainMap::from_reader(MINUS_CHAINS.as_bytes()).unwrap();
+        let records = records(
+            "chr3\t.\texon\t111\t150\t.\t+\t.\tID=e1\n\
+             chr3\t.\texon\t151\t250\t.\t-\t.\tID=e2",
+        );
+        let options = LiftoverOptions::default();
+        let exon = lift_record(&chains, &records[0], &options).unwrap();
+        assert_eq!(exon[0].seqid, "chrC");
+        assert_eq!((exon[0].start, exon[0].end), (751, 790));
+        assert_eq!(exon[0].strand, Some(Strand::Reverse));
+
+        // The insertion in chrC lies between the two blocks the exon maps through.
+        assert_eq!(
+            lift_record(&chains, &records[1], &options),
+            Err(LiftoverFailure::Gapped { gaps: 1 })
+        );
+        let split = LiftoverOptions {
+            allow_split: true,
+            ..options
+        };
+        let exon = lift_record(&chains, &records[1], &split).unwrap();
+        let spans: Vec<(u32, u32)> = exon.iter().map(|r| (r.start, r.end)).collect();
+        assert_eq!(spans, vec![(701, 750), (641, 690)]);
+        assert!(exon.iter().all(|r| r.strand == Some(Strand::Forward)));
+        let gaps = LiftoverOptions {
+            allow_gaps: true,
+            ..options
+        };
+        let exon = lift_record(&chains, &records[1], &gaps).unwrap();
+        assert_eq!((exon[0].start, exon[0].end), (641, 750));
+    }
+
+    #[test]
+    fn test_partial_and_split_features() {
+        let chains = ChainMap::from_reader(CHAINS.as_bytes()).unwrap();
+        // Half of the feature lies in the 20 base deletion of the reference.
+        let record = &records("chr1\t.\texon\t391\t430\t.\t+\t.\tID=e1")[0];
+        assert_eq!(
+            lift_record(&chains, record, &LiftoverOptions::default()),
+            Err(LiftoverFailure::PartiallyDeleted {
+                mapped_fraction: 0.5
+            })
+        );
+        let partial = LiftoverOptions {
+            min_match: 0.5,
+            allow_split: true,
+            ..LiftoverOptions::default()
+        };
+        let exon = lift_record(&chains, record, &partial).unwrap();
+        let spans: Vec<(u32, u32)> = exon.iter().map(|r| (r.start, r.end)).collect();
+        assert_eq!(spans, vec![(1391, 1400), (1411, 1420)]);
+
+        let chains = ChainMap::from_reader(MINUS_CHAINS.as_bytes()).unwrap();
+        let record = &records("chr3\t.\texon\t281\t300\t.\t+\t.\tID=e1")[0];
+        assert_eq!(
+            lift_record(&chains, record, &LiftoverOptions::default()),
+            Err(LiftoverFailure::Split { chains: 2 })
+        );
+        let split = LiftoverOptions {
+            allow_split: true,
+            ..LiftoverOptions::default()
+        };
+        let exon = lift_record(&chains, record, &split).unwrap();
+        let pieces: Vec<(&str, u32, u32, Option<Strand>)> = exon
+            .iter()
+            .map(|r| (r.seqid.as_str(), r.start, r.end, r.strand))
+            .collect();
+        assert_eq!(
+            pieces,
+            vec![
+                ("chrC", 601, 610, Some(Strand::Reverse)),
+                ("chrD", 1, 10, Some(Strand::Forward)),
+            ]
+        );
+    }
+}
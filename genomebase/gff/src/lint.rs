@@ -0,0 +1,241 @@
+//! Structural checks of GFF3 records against the specification.
+
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+use crate::directive::SequenceRegion;
+use crate::gene_model::CDS_TYPE;
+use crate::tbl::Severity;
+use crate::GffRecord;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum LintIssueKind {
+    StartAfterEnd,
+    /// The same ID on features of different type or seqid. Lines of one discontinuous
+    /// feature share their ID and are not reported.
+    DuplicateId(String),
+    UnknownParent(String),
+    MissingCdsPhase,
+    OutsideSequenceRegion {
+        start: u64,
+        end: u64,
+    },
+    UndeclaredSeqid,
+    OutsideParent(String),
+}
+
+impl fmt::Display for LintIssueKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::StartAfterEnd => write!(f, "start is after end"),
+            Self::DuplicateId(id) => write!(f, "ID {} is used by unrelated features", id),
+            Self::UnknownParent(parent) => write!(f, "unknown parent {}", parent),
+            Self::MissingCdsPhase => write!(f, "CDS without phase"),
+            Self::OutsideSequenceRegion { start, end } => {
+                write!(f, "outside the sequence region {}-{}", start, end)
+            }
+            Self::UndeclaredSeqid => write!(f, "seqid has no ##sequence-region"),
+            Self::OutsideParent(parent) => write!(f, "extends beyond parent {}", parent),
+        }
+    }
+}
+
+impl LintIssueKind {
+    pub fn severity(&self) -> Severity {
+        match self {
+            Self::UndeclaredSeqid | Self::OutsideParent(_) => Severity::Warning,
+            _ => Severity::Error,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LintIssue {
+    /// Position of the record in the checked slice.
+    pub record: usize,
+    pub feature_id: Option<String>,
+    pub kind: LintIssueKind,
+}
+
+impl LintIssue {
+    pub fn severity(&self) -> Severity {
+        self.kind.severity()
+    }
+}
+
+impl fmt::Display for LintIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.feature_id {
+            Some(id) => write!(f, "{} {}: {}", self.severity(), id, self.kind),
+            None => write!(
+                f,
+                "{} record {}: {}",
+                self.severity(),
+                self.record,
+                self.kind
+            ),
+        }
+    }
+}
+
+/// Checks records, and their seqids against the sequence regions when any are declared.
+pub fn lint_records(records: &[GffRecord], sequence_regions: &[SequenceRegion]) -> Vec<LintIssue> {
+    let mut issues = Vec::new();
+    let regions: HashMap<&str, &SequenceRegion> = sequence_regions
+        .iter()
+        .map(|r| (r.seqid.as_str(), r))
+        .collect();
+
+    let mut by_id: HashMap<&str, &GffRecord> = HashMap::new();
+    let mut reported: HashSet<&str> = HashSet::new();
+    for (i, record) in records.iter().enumerate() {
+        let mut push = |kind| {
+            issues.push(LintIssue {
+                record: i,
+                feature_id: record.id().map(|id| id.to_string()),
+                kind,
+            })
+        };
+
+        if record.start > record.end {
+            push(LintIssueKind::StartAfterEnd);
+        }
+        if record.r#type == CDS_TYPE && record.phase.is_none() {
+            push(LintIssueKind::MissingCdsPhase);
+        }
+        match regions.get(record.seqid.as_str()) {
+            Some(region)
+                if (record.start as u64) < region.start || record.end as u64 > region.end =>
+            {
+                push(LintIssueKind::OutsideSequenceRegion {
+                    start: region.start,
+                    end: region.end,
+                })
+            }
+            None if !regions.is_empty() => push(LintIssueKind::UndeclaredSeqid),
+            _ => {}
+        }
+
+        if let Some(id) = record.id() {
+            match by_id.get(id) {
+                Some(first)
+                    if (first.seqid != record.seqid || first.r#type != record.r#type)
+                        && reported.insert(id) =>
+                {
+                    push(LintIssueKind::DuplicateId(id.to_string()))
+                }
+                Some(_) => {}
+                None => {
+                    by_id.insert(id, record);
+                }
+            }
+        }
+    }
+
+    for (i, record) in records.iter().enumerate() {
+        for parent in record.parents() {
+            let kind = match by_id.get(parent) {
+                None => LintIssueKind::UnknownParent(parent.to_string()),
+                Some(p) if record.start < p.start || record.end > p.end => {
+                    LintIssueKind::OutsideParent(parent.to_string())
+                }
+                Some(_) => continue,
+            };
+            issues.push(LintIssue {
+                record: i,
+                feature_id: record.id().map(|id| id.to_string()),
+                kind,
+            });
+        }
+    }
+
+    issues
+}
+
+#[cfg(test)]
+mod test_lint {
+    use super::*;
+    use crate::parse_line;
+
+    #[test]
+    fn test_lint_records() {
+        let records: Vec<GffRecord> = "chr1\t.\tgene\t1\t1000\t.\t+\t.\tID=gene1\n\
+             chr1\t.\tmRNA\t1\t1200\t.\t+\t.\tID=mRNA1;Parent=gene1\n\
+             chr1\t.\tCDS\t10\t100\t.\t+\t.\tID=cds1;Parent=mRNA1\n\
+             chr1\t.\tCDS\t200\t300\t.\t+\t0\tID=cds1;Parent=mRNA1\n\
+             chr1\t.\texon\t200\t300\t.\t+\t.\tID=cds1;Parent=mRNA9\n\
+             chr2\t.\tgene\t500\t400\t.\t+\t.\tID=gene2"
+            .lines()
+            .map(|l| parse_line(l).unwrap())
+            .collect();
+        let regions = vec![SequenceRegion::new("chr1".parse().unwrap(), 1, 1100)];
+
+        let kinds: Vec<(usize, LintIssueKind)> = lint_records(&records, &regions)
+            .into_iter()
+            .map(|issue| (issue.record, issue.kind))
+            .collect();
+        assert_eq!(
+            kinds,
+            vec![
+                (
+                    1,
+                    LintIssueKind::OutsideSequenceRegion {
+                        start: 1,
+                        end: 1100
+                    }
+                ),
+                (2, LintIssueKind::MissingCdsPhase),
+                (4, LintIssueKind::DuplicateId("cds1".to_string())),
+                (5, LintIssueKind::StartAfterEnd),
+                (5, LintIssueKind::UndeclaredSeqid),
+                (1, LintIssueKind::OutsideParent("gene1".to_string())),
+                (4, LintIssueKind::UnknownParent("mRNA9".to_string())),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_discontinuous_feature() {
+        let records: Vec<GffRecord> = "chr1\t.\tmRNA\t1\t1000\t.\t+\t.\tID=mRNA1\n\
+             chr1\t.\tCDS\t10\t100\t.\t+\t0\tID=cds1;Parent=mRNA1\n\
+             chr1\t.\tCDS\t200\t300\t.\t+\t2\tID=cds1;Parent=mRNA1\n\
+             chr2\t.\tCDS\t200\t300\t.\t+\t2\tID=cds1;Parent=mRNA1"
+            .lines()
+            .map(|l| parse_line(l).unwrap())
+            .collect();
+
+        // Without sequence regions seqids are not checked; only the CDS on another seqid
+        // is a duplicate, and the shared ID is reported once.
+        let issues = lint_records(&records, &[]);
+        assert_eq!(issues.len(), 1);
+        assert_eq!(issues[0].record, 3);
+        assert_eq!(
+            issues[0].kind,
+            LintIssueKind::DuplicateId("cds1".to_string())
+        );
+        assert_eq!(issues[0].severity(), Severity::Error);
+    }
+
+    #[test]
+    fn test_lint_issue_display() {
+        let records: Vec<GffRecord> = "chr1\t.\tgene\t100\t1000\t.\t+\t.\tID=gene1\n\
+             chr1\t.\tmRNA\t50\t1000\t.\t+\t.\tID=mRNA1;Parent=gene1\n\
+             chr1\t.\texon\t300\t200\t.\t+\t.\tParent=mRNA1"
+            .lines()
+            .map(|l| parse_line(l).unwrap())
+            .collect();
+        let issues: Vec<String> = lint_records(&records, &[])
+            .iter()
+            .map(|issue| issue.to_string())
+            .collect();
+        assert_eq!(
+            issues,
+            vec![
+                "ERROR record 2: start is after end",
+                "WARNING mRNA1: extends beyond parent gene1",
+            ]
+        );
+    }
+}
@@ -0,0 +1,343 @@
+//! Merging of several annotation sources into one set of records.
+//!
+//! Colliding IDs are rewritten, together with every `Parent` and `Derives_from` reference to
+//! them, gene models with identical structure are collapsed, and every record is tagged with
+//! the source it came from.
+
+use std::collections::{HashMap, HashSet};
+
+use serde::{Deserialize, Serialize};
+
+use crate::attributes::{Tag, Value};
+use crate::gene_model::{assemble_gene_models, GeneModel, GeneModelError};
+use crate::{GffRecord, Strand};
+
+/// Attribute listing the sources a feature was found in.
+pub const PROVENANCE_ATTRIBUTE: &str = "provenance";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum CollisionStrategy {
+    /// Prefix colliding IDs with the source name, e.g. `curation:gene1`.
+    Namespace,
+    /// Append a numeric suffix to colliding IDs, e.g. `gene1_2`.
+    Rename,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MergeOptions {
+    pub collision: CollisionStrategy,
+    /// Drop gene models whose structure is identical to one from an earlier source.
+    pub collapse_identical: bool,
+    /// Overwrite the `source` column with the source name, on by default. The provenance
+    /// attribute is always set.
+    pub set_source_column: bool,
+}
+
+impl Default for MergeOptions {
+    fn default() -> Self {
+        Self {
+            collision: CollisionStrategy::Namespace,
+            collapse_identical: true,
+            set_source_column: true,
+        }
+    }
+}
+
+/// Records of one annotation source, named for provenance.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MergeSource {
+    pub name: String,
+    pub records: Vec<GffRecord>,
+}
+
+impl MergeSource {
+    pub fn new(name: &str, records: Vec<GffRecord>) -> Self {
+        Self {
+            name: name.to_string(),
+            records,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RenamedId {
+    pub source: String,
+    pub old_id: String,
+    pub new_id: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CollapsedModel {
+    pub source: String,
+    /// Gene ID in the collapsed source, after renaming.
+    pub id: String,
+    /// Gene ID of the kept model.
+    pub kept_id: String,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct MergeResult {
+    pub records: Vec<GffRecord>,
+    pub renamed: Vec<RenamedId>,
+    pub collapsed: Vec<CollapsedModel>,
+}
+
+/// Type, span, exon and CDS intervals of a transcript.
+type TranscriptSignature = (String, (u32, u32), Vec<(u32, u32)>, Vec<(u32, u32)>);
+
+/// Structure of a gene model, independent of IDs and sources.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct ModelSignature {
+    seqid: String,
+    strand: Option<Strand>,
+    span: (u32, u32),
+    transcripts: Vec<TranscriptSignature>,
+}
+
+impl ModelSignature {
+    fn new(gene: &GeneModel) -> Self {
+        let mut transcripts: Vec<_> = gene
+            .transcripts
+            .iter()
+            .map(|t| {
+                (
+                    t.transcript.r#type.clone(),
+                    (t.transcript.start, t.transcript.end),
+                    t.exon_intervals(),
+                    t.cds.iter().map(|c| (c.start, c.end)).collect(),
+                )
+            })
+            .collect();
+        transcripts.sort();
+
+        Self {
+            seqid: gene.seqid().to_string(),
+            strand: gene.strand(),
+            span: (gene.gene.start, gene.gene.end),
+            transcripts,
+        }
+    }
+}
+
+const REFERENCE_TAGS: [Tag; 2] = [Tag::Parent, Tag::DerivesFrom];
+
+fn rewrite_ids(record: &mut GffRecord, renames: &HashMap<String, String>) {
+    let rename = |id: &str| renames.get(id).cloned().unwrap_or_else(|| id.to_string());
+
+    if let Some(Value::String(id)) = record.attributes.get_mut(&Tag::Id) {
+        *id = rename(id);
+    }
+    for tag in REFERENCE_TAGS.iter() {
+        match record.attributes.get_mut(tag) {
+            Some(Value::String(id)) => *id = rename(id),
+            Some(Value::Array(ids)) => ids.iter_mut().for_each(|id| *id = rename(id)),
+            None => {}
+        }
+    }
+}
+
+fn resolve_collision(
+    id: &str,
+    source: &str,
+    strategy: CollisionStrategy,
+    used: &HashSet<String>,
+) -> String {
+    let base = match strategy {
+        CollisionStrategy::Namespace => format!("{}:{}", source, id),
+        CollisionStrategy::Rename => id.to_string(),
+    };
+    if !used.contains(&base) {
+        return base;
+    }
+    (2..)
+        .map(|n| format!("{}_{}", base, n))
+        .find(|candidate| !used.contains(candidate))
+        .unwrap_or(base)
+}
+
+fn add_provenance(record: &mut GffRecord, source: &str) {
+    let tag = Tag::Other(PROVENANCE_ATTRIBUTE.to_string());
+    let value = match record.attributes.swap_remove(&tag) {
+        None => Value::String(source.to_string()),
+        Some(value) => {
+            let mut sources: Vec<String> = value.iter().map(|s| s.to_string()).collect();
+            if !sources.iter().any(|s| s == source) {
+                sources.push(source.to_string());
+            }
+            match sources.len() {
+                1 => Value::String(sources.remove(0)),
+                _ => Value::Array(sources),
+            }
+        }
+    };
+    record.attributes.insert(tag, value);
+}
+
+/// IDs of the features forming a gene model, used to find its records again.
+fn model_ids(gene: &GeneModel) -> HashSet<String> {
+    std::iter::once(gene.id())
+        .chain(gene.transcripts.iter().map(|t| t.id()))
+        .map(|id| id.to_string())
+        .collect()
+}
+
+/// Whether a record belongs to a model, directly or through its parents.
+fn belongs_to(record: &GffRecord, ids: &HashSet<String>) -> bool {
+    record.id().is_some_and(|id| ids.contains(id))
+        || record.parents().iter().any(|p| ids.contains(*p))
+}
+
+/// Merges sources in order. Earlier sources win: their IDs are kept and their models are the
+/// ones retained when identical models are collapsed.
+pub fn merge_annotations(
+    sources: Vec<MergeSource>,
+    options: &MergeOptions,
+) -> Result<MergeResult, GeneModelError> {
+    let mut result = MergeResult::default();
+    let mut used_ids: HashSet<String> = HashSet::new();
+    // Gene ID and feature IDs of every kept model, by structure.
+    let mut kept_models: HashMap<ModelSignature, (String, HashSet<String>)> = HashMap::new();
+
+    for source in sources {
+        let mut renames: HashMap<String, String> = HashMap::new();
+        let source_ids: HashSet<String> = source
+            .records
+            .iter()
+            .filter_map(|r| r.id().map(|id| id.to_string()))
+            .collect();
+        let mut taken: HashSet<String> = used_ids.union(&source_ids).cloned().collect();
+        for id in source.records.iter().filter_map(|r| r.id()) {
+            if renames.contains_key(id) || !used_ids.contains(id) {
+                continue;
+            }
+            let new_id = resolve_collision(id, &source.name, options.collision, &taken);
+            taken.insert(new_id.clone());
+            result.renamed.push(RenamedId {
+                source: source.name.clone(),
+                old_id: id.to_string(),
+                new_id: new_id.clone(),
+            });
+            renames.insert(id.to_string(), new_id);
+        }
+        for id in &source_ids {
+            used_ids.insert(renames.get(id).unwrap_or(id).clone());
+        }
+
+        let mut records = source.records;
+        for record in records.iter_mut() {
+            rewrite_ids(record, &renames);
+            add_provenance(record, &source.name);
+            if options.set_source_column {
+                record.source = source.name.clone();
+            }
+        }
+
+        if options.collapse_identical {
+            let mut dropped: HashSet<String> = HashSet::new();
+            for gene in assemble_gene_models(records.clone())? {
+                let signature = ModelSignature::new(&gene);
+                match kept_models.get(&signature) {
+                    Some((kept_id, kept_ids)) => {
+                        for record in result.records.iter_mut() {
+                            if belongs_to(record, kept_ids) {
+                                add_provenance(record, &source.name);
+                            }
+                        }
+                        result.collapsed.push(CollapsedModel {
+                            source: source.name.clone(),
+                            id: gene.id().to_string(),
+                            kept_id: kept_id.clone(),
+                        });
+                        dropped.extend(model_ids(&gene));
+                    }
+                    None => {
+                        kept_models.insert(signature, (gene.id().to_string(), model_ids(&gene)));
+                    }
+                }
+            }
+            records.retain(|record| !belongs_to(record, &dropped));
+        }
+
+        result.records.extend(records);
+    }
+
+    Ok(result)
+}
+
+#[cfg(test)]
+mod test_merge {
+    use super::*;
+    use crate::parse_line;
+
+    fn records(lines: &str) -> Vec<GffRecord> {
+        lines.lines().map(|l| parse_line(l).unwrap()).collect()
+    }
+
+    fn provenance(record: &GffRecord) -> Vec<&str> {
+        record
+            .attribute(&Tag::Other(PROVENANCE_ATTRIBUTE.to_string()))
+            .map(|v| v.iter().collect())
+            .unwrap_or_default()
+    }
+
+    #[test]
+    fn test_merge_annotations() {
+        let predictions = records(
+            "chr1\tpred\tgene\t100\t500\t.\t+\t.\tID=gene1\n\
+             chr1\tpred\tmRNA\t100\t500\t.\t+\t.\tID=mRNA1;Parent=gene1\n\
+             chr1\tpred\tgene\t1000\t2000\t.\t-\t.\tID=gene2\n\
+             chr1\tpred\tmRNA\t1000\t2000\t.\t-\t.\tID=mRNA2;Parent=gene2",
+        );
+        let curation = records(
+            "chr1\tcur\tgene\t100\t500\t.\t+\t.\tID=g1\n\
+             chr1\tcur\tmRNA\t100\t500\t.\t+\t.\tID=t1;Parent=g1\n\
+             chr1\tcur\tgene\t3000\t4000\t.\t+\t.\tID=gene2\n\
+             chr1\tcur\tmRNA\t3000\t4000\t.\t+\t.\tID=mRNA2;Parent=gene2\n\
+             chr1\tcur\texon\t3000\t4000\t.\t+\t.\tParent=mRNA2",
+        );
+        let result = merge_annotations(
+            vec![
+                MergeSource::new("pred", predictions),
+                MergeSource::new("cur", curation),
+            ],
+            &MergeOptions::default(),
+        )
+        .unwrap();
+
+        let ids: Vec<Option<&str>> = result.records.iter().map(|r| r.id()).collect();
+        assert_eq!(
+            ids,
+            vec![
+                Some("gene1"),
+                Some("mRNA1"),
+                Some("gene2"),
+                Some("mRNA2"),
+                Some("cur:gene2"),
+                Some("cur:mRNA2"),
+                None,
+            ]
+        );
+        assert_eq!(result.records[5].parents(), vec!["cur:gene2"]);
+        assert_eq!(result.records[6].parents(), vec!["cur:mRNA2"]);
+        assert_eq!(provenance(&result.records[0]), vec!["pred", "cur"]);
+        assert_eq!(provenance(&result.records[4]), vec!["cur"]);
+        assert_eq!(result.records[4].source, "cur");
+        assert_eq!(
+            result.collapsed,
+            vec![CollapsedModel {
+                source: "cur".to_string(),
+                id: "g1".to_string(),
+                kept_id: "gene1".to_string()
+            }]
+        );
+    }
+
+    #[test]
+    fn test_resolve_collision_rename() {
+        let used: HashSet<String> = ["gene1", "gene1_2"].iter().map(|s| s.to_string()).collect();
+        assert_eq!(
+            resolve_collision("gene1", "cur", CollisionStrategy::Rename, &used),
+            "gene1_3"
+        );
+    }
+}
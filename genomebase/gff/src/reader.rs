@@ -0,0 +1,115 @@
+use std::io::BufRead;
+
+use anyhow::{anyhow, Result};
+
+use crate::{parse_line, GffRecord};
+
+const COMMENT_PREFIX: char = '#';
+const DIRECTIVE_PREFIX: &str = "##";
+const FASTA_DIRECTIVE: &str = "##FASTA";
+
+/// Streaming reader over the feature lines of a GFF3 file.
+///
+/// Comments and blank lines are skipped, directive lines are kept so that callers can
+/// inspect the header, and reading stops at the `##FASTA` directive.
+pub struct GffReader<R> {
+    inner: R,
+    buf: String,
+    line_number: usize,
+    directives: Vec<String>,
+    finished: bool,
+}
+
+impl<R: BufRead> GffReader<R> {
+    pub fn new(inner: R) -> Self {
+        Self {
+            inner,
+            buf: String::new(),
+            line_number: 0,
+            directives: Vec::new(),
+            finished: false,
+        }
+    }
+
+    /// Directive lines (starting with `##`) encountered so far, in file order.
+    pub fn directives(&self) -> &[String] {
+        &self.directives
+    }
+
+    /// Number of the last line read, counting from 1.
+    pub fn line_number(&self) -> usize {
+        self.line_number
+    }
+
+    fn read_record(&mut self) -> Result<Option<GffRecord>> {
+        loop {
+            self.buf.clear();
+            if self.inner.read_line(&mut self.buf)? == 0 {
+                return Ok(None);
+            }
+            self.line_number += 1;
+
+            let line = self.buf.trim_end_matches(['\n', '\r']);
+            if line.trim().is_empty() {
+                continue;
+            }
+            if line.starts_with(FASTA_DIRECTIVE) {
+                self.directives.push(line.to_string());
+                return Ok(None);
+            }
+            if line.starts_with(DIRECTIVE_PREFIX) {
+                self.directives.push(line.to_string());
+                continue;
+            }
+            if line.starts_with(COMMENT_PREFIX) {
+                continue;
+            }
+
+            return parse_line(line)
+                .map(Some)
+                .map_err(|e| anyhow!("line {}: {}", self.line_number, e));
+        }
+    }
+}
+
+impl<R: BufRead> Iterator for GffReader<R> {
+    type Item = Result<GffRecord>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.finished {
+            return None;
+        }
+
+        let record = self.read_record().transpose();
+        if !matches!(record, Some(Ok(_))) {
+            self.finished = true;
+        }
+        record
+    }
+}
+
+#[cfg(test)]
+mod test_reader {
+    use super::*;
+
+    #[test]
+    fn test_reader_skips_comments_and_stops_at_fasta() {
+        let gff = "##gff-version 3\n# comment\nchr1\t.\tgene\t1\t100\t.\t+\t.\tID=gene1\n\nchr1\t.\tmRNA\t1\t100\t.\t+\t.\tID=mRNA1;Parent=gene1\n##FASTA\n>chr1\nACGT\n";
+        let mut reader = GffReader::new(gff.as_bytes());
+        let records = reader.by_ref().collect::<Result<Vec<_>>>().unwrap();
+
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[1].parents(), vec!["gene1"]);
+        assert_eq!(reader.directives(), ["##gff-version 3", "##FASTA"]);
+    }
+
+    #[test]
+    fn test_reader_reports_line_number() {
+        let gff =
+            "chr1\t.\tgene\t1\t100\t.\t+\t.\tID=gene1\nchr1\t.\tgene\tx\t100\t.\t+\t.\tID=gene2\n";
+        let err = GffReader::new(gff.as_bytes())
+            .collect::<Result<Vec<_>>>()
+            .unwrap_err();
+        assert!(err.to_string().starts_with("line 2:"));
+    }
+}
@@ -0,0 +1,343 @@
+//! Summary statistics of an annotation, computed on every new genome release.
+
+use std::collections::{BTreeMap, HashSet};
+use std::io::{self, Write};
+
+use common::Seqid;
+use serde::{Deserialize, Serialize};
+
+use crate::directive::SequenceRegion;
+use crate::gene_model::{assemble_gene_models, GeneModel, GeneModelError};
+use crate::{GffRecord, Strand};
+
+const BASES_PER_MEGABASE: f64 = 1_000_000.0;
+
+/// Summary of a set of values, such as feature lengths.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Distribution {
+    pub count: usize,
+    pub total: u64,
+    pub min: u64,
+    pub max: u64,
+    pub mean: f64,
+    pub median: f64,
+}
+
+impl Distribution {
+    pub fn new(mut values: Vec<u64>) -> Self {
+        if values.is_empty() {
+            return Self::default();
+        }
+        values.sort_unstable();
+
+        let count = values.len();
+        let total: u64 = values.iter().sum();
+        let median = if count.is_multiple_of(2) {
+            (values[count / 2 - 1] + values[count / 2]) as f64 / 2.0
+        } else {
+            values[count / 2] as f64
+        };
+
+        Self {
+            count,
+            total,
+            min: values[0],
+            max: values[count - 1],
+            mean: total as f64 / count as f64,
+            median,
+        }
+    }
+
+    fn tsv_rows(&self, section: &str) -> Vec<(String, String, String)> {
+        let row = |key: &str, value: String| (section.to_string(), key.to_string(), value);
+        vec![
+            row("count", self.count.to_string()),
+            row("total", self.total.to_string()),
+            row("min", self.min.to_string()),
+            row("max", self.max.to_string()),
+            row("mean", format!("{:.2}", self.mean)),
+            row("median", format!("{:.1}", self.median)),
+        ]
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StrandBalance {
+    pub forward: usize,
+    pub reverse: usize,
+    pub unknown: usize,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct SeqidStats {
+    pub genes: usize,
+    pub features: usize,
+    /// Length from the `##sequence-region` directive, when present.
+    pub length: Option<u64>,
+    pub genes_per_megabase: Option<f64>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct AnnotationStats {
+    pub feature_types: BTreeMap<String, usize>,
+    pub sources: BTreeMap<String, usize>,
+    pub gene_length: Distribution,
+    /// Spliced length of each transcript, the sum of its exon lengths.
+    pub transcript_length: Distribution,
+    /// Length of each distinct exon; an exon shared by several isoforms counts once.
+    pub exon_length: Distribution,
+    pub intron_length: Distribution,
+    /// Total CDS length per coding transcript.
+    pub cds_length: Distribution,
+    pub transcripts_per_gene: Distribution,
+    pub exons_per_transcript: Distribution,
+    /// Genes whose transcripts all have a single exon.
+    pub mono_exonic_genes: usize,
+    pub multi_exonic_genes: usize,
+    pub coding_genes: usize,
+    pub gene_strands: StrandBalance,
+    pub seqids: BTreeMap<Seqid, SeqidStats>,
+}
+
+/// Length of a 1-based inclusive interval, 0 for intervals whose start is past their end.
+fn interval_length(start: u32, end: u32) -> u64 {
+    (end as u64 + 1).saturating_sub(start as u64)
+}
+
+fn introns(exons: &[(u32, u32)]) -> impl Iterator<Item = u64> + '_ {
+    exons
+        .windows(2)
+        .filter(|pair| pair[1].0 > pair[0].1 + 1)
+        .map(|pair| (pair[1].0 - pair[0].1 - 1) as u64)
+}
+
+impl AnnotationStats {
+    /// Computes statistics over records, using sequence regions for per-seqid density.
+    pub fn compute(
+        records: &[GffRecord],
+        sequence_regions: &[SequenceRegion],
+    ) -> Result<Self, GeneModelError> {
+        let genes = assemble_gene_models(records.iter().cloned())?;
+        Ok(Self::from_parts(records, &genes, sequence_regions))
+    }
+
+    /// Computes statistics from records and the gene models already assembled from them.
+    pub fn from_parts(
+        records: &[GffRecord],
+        genes: &[GeneModel],
+        sequence_regions: &[SequenceRegion],
+    ) -> Self {
+        let mut stats = Self::default();
+
+        for record in records {
+            *stats
+                .feature_types
+                .entry(record.r#type.clone())
+                .or_default() += 1;
+            *stats.sources.entry(record.source.clone()).or_default() += 1;
+            stats
+                .seqids
+                .entry(record.seqid.clone())
+                .or_default()
+                .features += 1;
+        }
+
+        let mut gene_lengths = Vec::new();
+        let mut transcript_lengths = Vec::new();
+        let mut exon_lengths = Vec::new();
+        let mut intron_lengths = Vec::new();
+        let mut cds_lengths = Vec::new();
+        let mut transcripts_per_gene = Vec::new();
+        let mut exons_per_transcript = Vec::new();
+        let mut distinct_exons: HashSet<(Seqid, Option<Strand>, u32, u32)> = HashSet::new();
+
+        for gene in genes {
+            gene_lengths.push(gene.gene.len() as u64);
+            transcripts_per_gene.push(gene.transcripts.len() as u64);
+            stats
+                .seqids
+                .entry(gene.gene.seqid.clone())
+                .or_default()
+                .genes += 1;
+            match gene.strand() {
+                Some(Strand::Forward) => stats.gene_strands.forward += 1,
+                Some(Strand::Reverse) => stats.gene_strands.reverse += 1,
+                None => stats.gene_strands.unknown += 1,
+            }
+
+            let mut max_exons = 0;
+            for transcript in &gene.transcripts {
+                let exons = transcript.exon_intervals();
+                max_exons = max_exons.max(exons.len());
+                transcript_lengths.push(exons.iter().map(|&(s, e)| interval_length(s, e)).sum());
+                exons_per_transcript.push(exons.len() as u64);
+                let record = &transcript.transcript;
+                for &(s, e) in &exons {
+                    if distinct_exons.insert((record.seqid.clone(), record.strand, s, e)) {
+                        exon_lengths.push(interval_length(s, e));
+                    }
+                }
+                intron_lengths.extend(introns(&exons));
+                if transcript.is_coding() {
+                    cds_lengths.push(transcript.cds.iter().map(|c| c.len() as u64).sum());
+                }
+            }
+            match max_exons {
+                0 => {}
+                1 => stats.mono_exonic_genes += 1,
+                _ => stats.multi_exonic_genes += 1,
+            }
+            if gene.transcripts.iter().any(|t| t.is_coding()) {
+                stats.coding_genes += 1;
+            }
+        }
+
+        stats.gene_length = Distribution::new(gene_lengths);
+        stats.transcript_length = Distribution::new(transcript_lengths);
+        stats.exon_length = Distribution::new(exon_lengths);
+        stats.intron_length = Distribution::new(intron_lengths);
+        stats.cds_length = Distribution::new(cds_lengths);
+        stats.transcripts_per_gene = Distribution::new(transcripts_per_gene);
+        stats.exons_per_transcript = Distribution::new(exons_per_transcript);
+
+        for region in sequence_regions {
+            let seqid = stats.seqids.entry(region.seqid.clone()).or_default();
+            let length = (region.end + 1).saturating_sub(region.start);
+            seqid.length = Some(length);
+            seqid.genes_per_megabase =
+                (length > 0).then(|| seqid.genes as f64 / (length as f64 / BASES_PER_MEGABASE));
+        }
+
+        stats
+    }
+
+    pub fn write_json<W: Write>(&self, writer: W) -> serde_json::Result<()> {
+        serde_json::to_writer_pretty(writer, self)
+    }
+
+    /// Writes the statistics as `section\tkey\tvalue` rows with a header line.
+    pub fn write_tsv<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        let mut rows: Vec<(String, String, String)> = Vec::new();
+        let count_rows = |section: &str, counts: &BTreeMap<String, usize>| {
+            counts
+                .iter()
+                .map(|(key, count)| (section.to_string(), key.clone(), count.to_string()))
+                .collect::<Vec<_>>()
+        };
+
+        rows.extend(count_rows("feature_type", &self.feature_types));
+        rows.extend(count_rows("source", &self.sources));
+        rows.extend(self.gene_length.tsv_rows("gene_length"));
+        rows.extend(self.transcript_length.tsv_rows("transcript_length"));
+        rows.extend(self.exon_length.tsv_rows("exon_length"));
+        rows.extend(self.intron_length.tsv_rows("intron_length"));
+        rows.extend(self.cds_length.tsv_rows("cds_length"));
+        rows.extend(self.transcripts_per_gene.tsv_rows("transcripts_per_gene"));
+        rows.extend(self.exons_per_transcript.tsv_rows("exons_per_transcript"));
+
+        let gene_row =
+            |key: &str, value: usize| ("genes".to_string(), key.to_string(), value.to_string());
+        rows.push(gene_row("mono_exonic", self.mono_exonic_genes));
+        rows.push(gene_row("multi_exonic", self.multi_exonic_genes));
+        rows.push(gene_row("coding", self.coding_genes));
+        rows.push(gene_row("forward_strand", self.gene_strands.forward));
+        rows.push(gene_row("reverse_strand", self.gene_strands.reverse));
+        rows.push(gene_row("unknown_strand", self.gene_strands.unknown));
+
+        for (seqid, stats) in &self.seqids {
+            let section = format!("seqid:{}", seqid);
+            rows.push((
+                section.clone(),
+                "features".to_string(),
+                stats.features.to_string(),
+            ));
+            rows.push((
+                section.clone(),
+                "genes".to_string(),
+                stats.genes.to_string(),
+            ));
+            if let (Some(length), Some(density)) = (stats.length, stats.genes_per_megabase) {
+                rows.push((section.clone(), "length".to_string(), length.to_string()));
+                rows.push((
+                    section,
+                    "genes_per_megabase".to_string(),
+                    format!("{:.2}", density),
+                ));
+            }
+        }
+
+        writeln!(writer, "section\tkey\tvalue")?;
+        for (section, key, value) in rows {
+            writeln!(writer, "{}\t{}\t{}", section, key, value)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test_stats {
+    use super::*;
+    use crate::parse_line;
+
+    const GFF: &str = "chr1\tpred\tgene\t1\t1000\t.\t+\t.\tID=gene1\n\
+        chr1\tpred\tmRNA\t1\t1000\t.\t+\t.\tID=mRNA1;Parent=gene1\n\
+        chr1\tpred\texon\t1\t100\t.\t+\t.\tParent=mRNA1\n\
+        chr1\tpred\texon\t201\t1000\t.\t+\t.\tParent=mRNA1\n\
+        chr1\tpred\tCDS\t51\t100\t.\t+\t0\tParent=mRNA1\n\
+        chr1\tpred\tCDS\t201\t300\t.\t+\t1\tParent=mRNA1\n\
+        chr2\tcuration\tgene\t1\t300\t.\t-\t.\tID=gene2\n\
+        chr2\tcuration\tlnc_RNA\t1\t300\t.\t-\t.\tID=lnc1;Parent=gene2\n\
+        chr2\tcuration\texon\t1\t300\t.\t-\t.\tParent=lnc1";
+
+    #[test]
+    fn test_annotation_stats() {
+        let records: Vec<GffRecord> = GFF.lines().map(|l| parse_line(l).unwrap()).collect();
+        let regions = vec![SequenceRegion::new("chr1".parse().unwrap(), 1, 2_000_000)];
+        let stats = AnnotationStats::compute(&records, &regions).unwrap();
+
+        assert_eq!(stats.feature_types.get("exon"), Some(&3));
+        assert_eq!(stats.sources.get("curation"), Some(&3));
+        assert_eq!(stats.gene_length.median, 650.0);
+        assert_eq!(stats.intron_length.total, 100);
+        assert_eq!(stats.cds_length.total, 150);
+        assert_eq!((stats.mono_exonic_genes, stats.multi_exonic_genes), (1, 1));
+        assert_eq!(stats.coding_genes, 1);
+        assert_eq!(stats.gene_strands.reverse, 1);
+        let seqid = |s: &str| s.parse::<Seqid>().unwrap();
+        assert_eq!(stats.seqids[&seqid("chr1")].genes_per_megabase, Some(0.5));
+        assert_eq!(stats.seqids[&seqid("chr2")].length, None);
+
+        let mut tsv = Vec::new();
+        stats.write_tsv(&mut tsv).unwrap();
+        let tsv = String::from_utf8(tsv).unwrap();
+        assert!(tsv.contains("feature_type\tCDS\t2\n"));
+        assert!(tsv.contains("seqid:chr1\tgenes_per_megabase\t0.50\n"));
+        assert_eq!(stats.transcript_length.total, 1200);
+    }
+
+    #[test]
+    fn test_shared_exons() {
+        let gff = format!(
+            "{}\n\
+             chr1\tpred\tmRNA\t1\t1000\t.\t+\t.\tID=mRNA2;Parent=gene1\n\
+             chr1\tpred\texon\t1\t100\t.\t+\t.\tParent=mRNA2\n\
+             chr1\tpred\texon\t501\t1000\t.\t+\t.\tParent=mRNA2",
+            GFF
+        );
+        let records: Vec<GffRecord> = gff.lines().map(|l| parse_line(l).unwrap()).collect();
+        let stats = AnnotationStats::compute(&records, &[]).unwrap();
+        // The first exon of both isoforms counts once.
+        assert_eq!(stats.exon_length.count, 4);
+        assert_eq!(stats.exon_length.total, 100 + 800 + 300 + 500);
+        assert_eq!(stats.exons_per_transcript.total, 5);
+        assert_eq!(stats.transcript_length.max, 900);
+        assert_eq!(stats.transcript_length.min, 300);
+
+        let region = "##sequence-region chr1 100 50".parse::<SequenceRegion>();
+        assert!(region.is_err());
+        let regions = vec![SequenceRegion::new("chr1".parse().unwrap(), 100, 50)];
+        let stats = AnnotationStats::compute(&records, &regions).unwrap();
+        let chr1 = &stats.seqids[&"chr1".parse::<Seqid>().unwrap()];
+        assert_eq!((chr1.length, chr1.genes_per_megabase), (Some(0), None));
+    }
+}
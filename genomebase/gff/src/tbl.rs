@@ -0,0 +1,638 @@
+//! NCBI five-column feature table (`.tbl`) export for `table2asn` submissions.
+//!
+//! See <https://www.ncbi.nlm.nih.gov/genbank/feature_table/> for the format.
+
+use std::collections::HashSet;
+use std::fmt;
+use std::io::{self, Write};
+
+use serde::{Deserialize, Serialize};
+
+use crate::attributes::Tag;
+use crate::gene_model::{GeneModel, TranscriptModel};
+use crate::{GffRecord, Phase, Strand};
+
+const FEATURE_HEADER: &str = ">Feature";
+const QUALIFIER_INDENT: &str = "\t\t\t";
+const FIVE_PRIME_PARTIAL: char = '<';
+const THREE_PRIME_PARTIAL: char = '>';
+/// Introns shorter than this are rejected by `table2asn` as likely frameshifts.
+pub const MIN_INTRON_LENGTH: u32 = 10;
+
+// GFF attributes, following the NCBI GFF3 conventions, read by the exporter.
+const LOCUS_TAG: &str = "locus_tag";
+const PRODUCT: &str = "product";
+const START_RANGE: &str = "start_range";
+const END_RANGE: &str = "end_range";
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TblOptions {
+    /// Database name used in `gnl|<db_name>|<id>` identifiers.
+    pub db_name: String,
+    /// Product used for coding transcripts without a `product` attribute.
+    pub default_product: String,
+}
+
+impl Default for TblOptions {
+    fn default() -> Self {
+        Self {
+            db_name: "genomebase".to_string(),
+            default_product: "hypothetical protein".to_string(),
+        }
+    }
+}
+
+impl TblOptions {
+    fn general_id(&self, id: &str) -> String {
+        format!("gnl|{}|{}", self.db_name, id)
+    }
+}
+
+/// Whether a feature extends beyond the annotated sequence at its 5' or 3' end.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Partiality {
+    pub five_prime: bool,
+    pub three_prime: bool,
+}
+
+/// Partiality in genomic orientation: `lower` is the start coordinate, `upper` the end.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+struct GenomicPartiality {
+    lower: bool,
+    upper: bool,
+}
+
+impl GenomicPartiality {
+    /// Reads the NCBI `start_range=.,<start>` / `end_range=<end>,.` attributes.
+    fn from_record(record: &GffRecord) -> Self {
+        let other = |tag: &str| record.attribute(&Tag::Other(tag.to_string()));
+        Self {
+            lower: other(START_RANGE).is_some(),
+            upper: other(END_RANGE).is_some(),
+        }
+    }
+
+    fn union(self, other: Self) -> Self {
+        Self {
+            lower: self.lower || other.lower,
+            upper: self.upper || other.upper,
+        }
+    }
+
+    fn oriented(self, strand: Option<Strand>) -> Partiality {
+        match strand {
+            Some(Strand::Reverse) => Partiality {
+                five_prime: self.upper,
+                three_prime: self.lower,
+            },
+            _ => Partiality {
+                five_prime: self.lower,
+                three_prime: self.upper,
+            },
+        }
+    }
+}
+
+/// One feature of the table: a key, its intervals in transcript order and its qualifiers.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TblFeature {
+    pub id: String,
+    pub key: String,
+    pub strand: Option<Strand>,
+    pub intervals: Vec<(u32, u32)>,
+    pub partiality: Partiality,
+    pub qualifiers: Vec<(String, String)>,
+}
+
+impl TblFeature {
+    fn new(id: &str, key: &str, strand: Option<Strand>, mut intervals: Vec<(u32, u32)>) -> Self {
+        intervals.sort();
+        if strand == Some(Strand::Reverse) {
+            intervals.reverse();
+        }
+        Self {
+            id: id.to_string(),
+            key: key.to_string(),
+            strand,
+            intervals,
+            partiality: Partiality::default(),
+            qualifiers: Vec::new(),
+        }
+    }
+
+    fn qualifier(&mut self, name: &str, value: &str) {
+        self.qualifiers.push((name.to_string(), value.to_string()));
+    }
+
+    pub fn get_qualifier(&self, name: &str) -> Option<&str> {
+        self.qualifiers
+            .iter()
+            .find(|(n, _)| n == name)
+            .map(|(_, v)| v.as_str())
+    }
+}
+
+impl fmt::Display for TblFeature {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let last = self.intervals.len().saturating_sub(1);
+        for (i, &(start, end)) in self.intervals.iter().enumerate() {
+            let (from, to) = match self.strand {
+                Some(Strand::Reverse) => (end, start),
+                _ => (start, end),
+            };
+            let from_prefix = if i == 0 && self.partiality.five_prime {
+                FIVE_PRIME_PARTIAL.to_string()
+            } else {
+                String::new()
+            };
+            let to_prefix = if i == last && self.partiality.three_prime {
+                THREE_PRIME_PARTIAL.to_string()
+            } else {
+                String::new()
+            };
+            write!(f, "{}{}\t{}{}", from_prefix, from, to_prefix, to)?;
+            if i == 0 {
+                write!(f, "\t{}", self.key)?;
+            }
+            writeln!(f)?;
+        }
+
+        for (name, value) in &self.qualifiers {
+            if value.is_empty() {
+                writeln!(f, "{}{}", QUALIFIER_INDENT, name)?;
+            } else {
+                writeln!(f, "{}{}\t{}", QUALIFIER_INDENT, name, value)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+fn other_attribute<'a>(record: &'a GffRecord, tag: &str) -> Option<&'a str> {
+    record
+        .attribute(&Tag::Other(tag.to_string()))
+        .and_then(|v| v.first())
+}
+
+fn product(transcript: &TranscriptModel) -> Option<&str> {
+    other_attribute(&transcript.transcript, PRODUCT).or_else(|| {
+        transcript
+            .cds
+            .iter()
+            .find_map(|c| other_attribute(c, PRODUCT))
+    })
+}
+
+fn protein_id(transcript: &TranscriptModel) -> String {
+    match transcript.cds.iter().find_map(|c| c.id()) {
+        Some(id) if id != transcript.id() => id.to_string(),
+        _ => format!("{}.p", transcript.id()),
+    }
+}
+
+fn cds_partiality(transcript: &TranscriptModel) -> GenomicPartiality {
+    transcript
+        .cds
+        .iter()
+        .map(GenomicPartiality::from_record)
+        .fold(GenomicPartiality::default(), GenomicPartiality::union)
+}
+
+/// Partiality of a transcript, which inherits the CDS partiality when the CDS reaches its ends.
+fn transcript_partiality(transcript: &TranscriptModel) -> GenomicPartiality {
+    let own = GenomicPartiality::from_record(&transcript.transcript);
+    let cds = cds_partiality(transcript);
+    let inherited = match transcript.cds_span() {
+        Some((start, end)) => GenomicPartiality {
+            lower: cds.lower && start == transcript.transcript.start,
+            upper: cds.upper && end == transcript.transcript.end,
+        },
+        None => GenomicPartiality::default(),
+    };
+    own.union(inherited)
+}
+
+fn gene_partiality(gene: &GeneModel) -> GenomicPartiality {
+    gene.transcripts
+        .iter()
+        .map(|t| {
+            let partiality = transcript_partiality(t);
+            GenomicPartiality {
+                lower: partiality.lower && t.transcript.start == gene.gene.start,
+                upper: partiality.upper && t.transcript.end == gene.gene.end,
+            }
+        })
+        .fold(
+            GenomicPartiality::from_record(&gene.gene),
+            GenomicPartiality::union,
+        )
+}
+
+/// Feature key for a transcript, with the `ncRNA_class` to report for ncRNA features.
+fn rna_key(r#type: &str) -> (&'static str, Option<&'static str>) {
+    match r#type {
+        "mRNA" => ("mRNA", None),
+        "tRNA" => ("tRNA", None),
+        "rRNA" => ("rRNA", None),
+        "lnc_RNA" | "lncRNA" => ("ncRNA", Some("lncRNA")),
+        "snRNA" => ("ncRNA", Some("snRNA")),
+        "snoRNA" => ("ncRNA", Some("snoRNA")),
+        "miRNA" => ("ncRNA", Some("miRNA")),
+        "ncRNA" => ("ncRNA", Some("other")),
+        _ => ("misc_RNA", None),
+    }
+}
+
+/// Converts one gene model into its gene, RNA and CDS feature table entries.
+pub fn gene_features(gene: &GeneModel, options: &TblOptions) -> Vec<TblFeature> {
+    let strand = gene.strand();
+    let mut features = Vec::new();
+
+    let mut gene_feature = TblFeature::new(
+        gene.id(),
+        "gene",
+        strand,
+        vec![(gene.gene.start, gene.gene.end)],
+    );
+    gene_feature.partiality = gene_partiality(gene).oriented(strand);
+    if let Some(name) = gene.gene.name() {
+        gene_feature.qualifier("gene", name);
+    }
+    if let Some(locus_tag) = other_attribute(&gene.gene, LOCUS_TAG) {
+        gene_feature.qualifier(LOCUS_TAG, locus_tag);
+    }
+    if gene.gene.r#type == "pseudogene" {
+        gene_feature.qualifier("pseudo", "");
+    }
+    features.push(gene_feature);
+
+    for transcript in &gene.transcripts {
+        let (key, ncrna_class) = if transcript.is_coding() {
+            ("mRNA", None)
+        } else {
+            rna_key(&transcript.transcript.r#type)
+        };
+        let mut rna = TblFeature::new(transcript.id(), key, strand, transcript.exon_intervals());
+        rna.partiality = transcript_partiality(transcript).oriented(strand);
+        if let Some(class) = ncrna_class {
+            rna.qualifier("ncRNA_class", class);
+        }
+
+        if !transcript.is_coding() {
+            if let Some(product) = product(transcript) {
+                rna.qualifier(PRODUCT, product);
+            }
+            features.push(rna);
+            continue;
+        }
+
+        let product = product(transcript).unwrap_or(&options.default_product);
+        let protein_id = options.general_id(&protein_id(transcript));
+        let transcript_id = options.general_id(transcript.id());
+
+        rna.qualifier(PRODUCT, product);
+        rna.qualifier("protein_id", &protein_id);
+        rna.qualifier("transcript_id", &transcript_id);
+        features.push(rna);
+
+        let mut cds = TblFeature::new(
+            &protein_id,
+            "CDS",
+            strand,
+            transcript.cds.iter().map(|c| (c.start, c.end)).collect(),
+        );
+        cds.partiality = cds_partiality(transcript).oriented(strand);
+        cds.qualifier(PRODUCT, product);
+        cds.qualifier("protein_id", &protein_id);
+        cds.qualifier("transcript_id", &transcript_id);
+        if let Some(codon_start) = codon_start(transcript) {
+            if codon_start != 1 {
+                cds.qualifier("codon_start", &codon_start.to_string());
+            }
+        }
+        features.push(cds);
+    }
+
+    features
+}
+
+/// The 5'-most CDS segment in transcript orientation.
+fn five_prime_cds(transcript: &TranscriptModel) -> Option<&GffRecord> {
+    match transcript.strand() {
+        Some(Strand::Reverse) => transcript.cds.last(),
+        _ => transcript.cds.first(),
+    }
+}
+
+/// `codon_start` qualifier (1-based reading frame) derived from the phase of the first CDS.
+fn codon_start(transcript: &TranscriptModel) -> Option<u8> {
+    let phase = five_prime_cds(transcript)?.phase?;
+    Some(match phase {
+        Phase::Zero => 1,
+        Phase::One => 2,
+        Phase::Two => 3,
+    })
+}
+
+/// Writes gene models as a feature table, one `>Feature` block per seqid in input order.
+pub fn write_feature_table<W: Write>(
+    writer: &mut W,
+    genes: &[GeneModel],
+    options: &TblOptions,
+) -> io::Result<()> {
+    let mut seqids: Vec<&str> = Vec::new();
+    for gene in genes {
+        if !seqids.contains(&gene.seqid()) {
+            seqids.push(gene.seqid());
+        }
+    }
+
+    for seqid in seqids {
+        writeln!(writer, "{} {}", FEATURE_HEADER, seqid)?;
+        for gene in genes.iter().filter(|g| g.seqid() == seqid) {
+            for feature in gene_features(gene, options) {
+                write!(writer, "{}", feature)?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Severity {
+    Warning,
+    Error,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Warning => write!(f, "WARNING"),
+            Self::Error => write!(f, "ERROR"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TblIssueKind {
+    MissingStrand,
+    MixedStrand,
+    MissingLocusTag,
+    MissingProduct,
+    EmptyTranscript,
+    OutsideParent { parent: String },
+    CdsOutsideExons,
+    OverlappingExons,
+    ShortIntron { start: u32, end: u32 },
+    CdsLengthNotMultipleOfThree { length: u32 },
+    InvalidCodonStart,
+    DuplicateProteinId(String),
+}
+
+impl fmt::Display for TblIssueKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::MissingStrand => write!(f, "missing strand"),
+            Self::MixedStrand => write!(f, "strand differs from the gene"),
+            Self::MissingLocusTag => write!(f, "missing locus_tag"),
+            Self::MissingProduct => write!(f, "missing product, default will be used"),
+            Self::EmptyTranscript => write!(f, "transcript has neither exons nor CDS"),
+            Self::OutsideParent { parent } => write!(f, "extends beyond parent {}", parent),
+            Self::CdsOutsideExons => write!(f, "CDS is not contained in an exon"),
+            Self::OverlappingExons => write!(f, "overlapping exons"),
+            Self::ShortIntron { start, end } => {
+                write!(
+                    f,
+                    "intron {}-{} shorter than {}",
+                    start, end, MIN_INTRON_LENGTH
+                )
+            }
+            Self::CdsLengthNotMultipleOfThree { length } => {
+                write!(f, "complete CDS length {} is not a multiple of 3", length)
+            }
+            Self::InvalidCodonStart => write!(f, "complete CDS does not start in phase 0"),
+            Self::DuplicateProteinId(id) => write!(f, "duplicate protein_id: {}", id),
+        }
+    }
+}
+
+impl TblIssueKind {
+    pub fn severity(&self) -> Severity {
+        match self {
+            Self::MissingLocusTag | Self::MissingProduct | Self::EmptyTranscript => {
+                Severity::Warning
+            }
+            _ => Severity::Error,
+        }
+    }
+}
+
+/// A problem found before export that `table2asn` would reject or flag.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TblIssue {
+    pub feature_id: String,
+    pub kind: TblIssueKind,
+}
+
+impl TblIssue {
+    fn new(feature_id: &str, kind: TblIssueKind) -> Self {
+        Self {
+            feature_id: feature_id.to_string(),
+            kind,
+        }
+    }
+
+    pub fn severity(&self) -> Severity {
+        self.kind.severity()
+    }
+}
+
+impl fmt::Display for TblIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {}: {}", self.severity(), self.feature_id, self.kind)
+    }
+}
+
+fn contains(outer: &GffRecord, inner: &GffRecord) -> bool {
+    outer.start <= inner.start && inner.end <= outer.end
+}
+
+fn validate_transcript(gene: &GeneModel, transcript: &TranscriptModel) -> Vec<TblIssue> {
+    let id = transcript.id();
+    let mut issues = Vec::new();
+
+    for record in transcript.records() {
+        let record_id = record.id().unwrap_or(id);
+        if record.strand.is_none() {
+            issues.push(TblIssue::new(record_id, TblIssueKind::MissingStrand));
+        } else if gene.strand().is_some() && record.strand != gene.strand() {
+            issues.push(TblIssue::new(record_id, TblIssueKind::MixedStrand));
+        }
+    }
+
+    if !contains(&gene.gene, &transcript.transcript) {
+        let kind = TblIssueKind::OutsideParent {
+            parent: gene.id().to_string(),
+        };
+        issues.push(TblIssue::new(id, kind));
+    }
+    for child in transcript.exons.iter().chain(transcript.cds.iter()) {
+        if !contains(&transcript.transcript, child) {
+            let kind = TblIssueKind::OutsideParent {
+                parent: id.to_string(),
+            };
+            issues.push(TblIssue::new(child.id().unwrap_or(id), kind));
+        }
+    }
+
+    if transcript.exons.is_empty() && transcript.cds.is_empty() {
+        issues.push(TblIssue::new(id, TblIssueKind::EmptyTranscript));
+        return issues;
+    }
+
+    if !transcript.exons.is_empty() {
+        for cds in &transcript.cds {
+            if !transcript.exons.iter().any(|e| contains(e, cds)) {
+                issues.push(TblIssue::new(
+                    cds.id().unwrap_or(id),
+                    TblIssueKind::CdsOutsideExons,
+                ));
+            }
+        }
+    }
+
+    let intervals = transcript.exon_intervals();
+    for pair in intervals.windows(2) {
+        let ((_, prev_end), (next_start, _)) = (pair[0], pair[1]);
+        if next_start <= prev_end {
+            issues.push(TblIssue::new(id, TblIssueKind::OverlappingExons));
+        } else if next_start - prev_end - 1 < MIN_INTRON_LENGTH {
+            let kind = TblIssueKind::ShortIntron {
+                start: prev_end + 1,
+                end: next_start - 1,
+            };
+            issues.push(TblIssue::new(id, kind));
+        }
+    }
+
+    if transcript.is_coding() {
+        if product(transcript).is_none() {
+            issues.push(TblIssue::new(id, TblIssueKind::MissingProduct));
+        }
+
+        let partiality = cds_partiality(transcript).oriented(transcript.strand());
+        if !partiality.five_prime
+            && five_prime_cds(transcript).and_then(|c| c.phase) != Some(Phase::Zero)
+        {
+            issues.push(TblIssue::new(id, TblIssueKind::InvalidCodonStart));
+        }
+        let length: u32 = transcript.cds.iter().map(|c| c.len()).sum();
+        if !partiality.five_prime && !partiality.three_prime && !length.is_multiple_of(3) {
+            let kind = TblIssueKind::CdsLengthNotMultipleOfThree { length };
+            issues.push(TblIssue::new(id, kind));
+        }
+    }
+
+    issues
+}
+
+/// Checks gene models for problems that would make `table2asn` reject the feature table.
+pub fn validate_gene_models(genes: &[GeneModel], options: &TblOptions) -> Vec<TblIssue> {
+    let mut issues = Vec::new();
+    let mut protein_ids: HashSet<String> = HashSet::new();
+
+    for gene in genes {
+        if gene.strand().is_none() {
+            issues.push(TblIssue::new(gene.id(), TblIssueKind::MissingStrand));
+        }
+        if other_attribute(&gene.gene, LOCUS_TAG).is_none() {
+            issues.push(TblIssue::new(gene.id(), TblIssueKind::MissingLocusTag));
+        }
+
+        for transcript in &gene.transcripts {
+            issues.extend(validate_transcript(gene, transcript));
+
+            if transcript.is_coding() {
+                let protein_id = options.general_id(&protein_id(transcript));
+                if !protein_ids.insert(protein_id.clone()) {
+                    let kind = TblIssueKind::DuplicateProteinId(protein_id);
+                    issues.push(TblIssue::new(transcript.id(), kind));
+                }
+            }
+        }
+    }
+
+    issues
+}
+
+#[cfg(test)]
+mod test_tbl {
+    use super::*;
+    use crate::gene_model::assemble_gene_models;
+    use crate::parse_line;
+
+    fn genes(lines: &str) -> Vec<GeneModel> {
+        assemble_gene_models(lines.lines().map(|line| parse_line(line).unwrap())).unwrap()
+    }
+
+    const MINUS_STRAND_GENE: &str = "chr1\t.\tgene\t100\t1000\t.\t-\t.\tID=gene1;Name=abc1;locus_tag=ABC_0001;start_range=.,100\n\
+        chr1\t.\tmRNA\t100\t1000\t.\t-\t.\tID=mRNA1;Parent=gene1\n\
+        chr1\t.\texon\t100\t300\t.\t-\t.\tParent=mRNA1\n\
+        chr1\t.\texon\t501\t1000\t.\t-\t.\tParent=mRNA1\n\
+        chr1\t.\tCDS\t100\t300\t.\t-\t2\tID=cds1;Parent=mRNA1;product=kinase;start_range=.,100\n\
+        chr1\t.\tCDS\t501\t900\t.\t-\t0\tID=cds1;Parent=mRNA1;product=kinase;start_range=.,100";
+
+    #[test]
+    fn test_write_feature_table() {
+        let mut tbl = Vec::new();
+        write_feature_table(&mut tbl, &genes(MINUS_STRAND_GENE), &TblOptions::default()).unwrap();
+
+        let expected = ">Feature chr1\n\
+            1000\t>100\tgene\n\
+            \t\t\tgene\tabc1\n\
+            \t\t\tlocus_tag\tABC_0001\n\
+            1000\t501\tmRNA\n\
+            300\t>100\n\
+            \t\t\tproduct\tkinase\n\
+            \t\t\tprotein_id\tgnl|genomebase|cds1\n\
+            \t\t\ttranscript_id\tgnl|genomebase|mRNA1\n\
+            900\t501\tCDS\n\
+            300\t>100\n\
+            \t\t\tproduct\tkinase\n\
+            \t\t\tprotein_id\tgnl|genomebase|cds1\n\
+            \t\t\ttranscript_id\tgnl|genomebase|mRNA1\n";
+        assert_eq!(String::from_utf8(tbl).unwrap(), expected);
+    }
+
+    #[test]
+    fn test_validate_gene_models() {
+        let genes = genes(
+            "chr1\t.\tgene\t1\t1000\t.\t+\t.\tID=gene1\n\
+             chr1\t.\tmRNA\t1\t1000\t.\t+\t.\tID=mRNA1;Parent=gene1\n\
+             chr1\t.\texon\t1\t100\t.\t+\t.\tParent=mRNA1\n\
+             chr1\t.\texon\t105\t1000\t.\t+\t.\tParent=mRNA1\n\
+             chr1\t.\tCDS\t50\t100\t.\t+\t1\tID=cds1;Parent=mRNA1\n\
+             chr1\t.\tCDS\t105\t201\t.\t+\t0\tID=cds1;Parent=mRNA1",
+        );
+        let kinds: Vec<TblIssueKind> = validate_gene_models(&genes, &TblOptions::default())
+            .into_iter()
+            .map(|issue| issue.kind)
+            .collect();
+
+        assert_eq!(
+            kinds,
+            vec![
+                TblIssueKind::MissingLocusTag,
+                TblIssueKind::ShortIntron {
+                    start: 101,
+                    end: 104
+                },
+                TblIssueKind::MissingProduct,
+                TblIssueKind::InvalidCodonStart,
+                TblIssueKind::CdsLengthNotMultipleOfThree { length: 148 },
+            ]
+        );
+    }
+}